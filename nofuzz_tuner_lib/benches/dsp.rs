@@ -0,0 +1,48 @@
+// Frame helpers at the common frame sizes.
+// Run with: cargo bench -p nofuzz_tuner_lib
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nofuzz_tuner_lib::dsp;
+
+const FRAME_LENS: [usize; 3] = [1024, 2048, 4096];
+
+fn frame(len: usize) -> Vec<f64> {
+    (0..len)
+        .map(|i| (2.0 * std::f64::consts::PI * 110.0 * i as f64 / 44100.0).sin())
+        .collect()
+}
+
+fn bench_rms(c: &mut Criterion) {
+    for len in FRAME_LENS {
+        let data = frame(len);
+        c.bench_function(&format!("rms {}", len), |b| {
+            b.iter(|| dsp::rms(black_box(&data)))
+        });
+    }
+}
+
+fn bench_hann(c: &mut Criterion) {
+    for len in FRAME_LENS {
+        let data = frame(len);
+        c.bench_function(&format!("hann {}", len), |b| {
+            b.iter(|| {
+                let mut d = data.clone();
+                dsp::apply_hann(black_box(&mut d));
+                d
+            })
+        });
+    }
+}
+
+fn bench_autocorrelation(c: &mut Criterion) {
+    for len in FRAME_LENS {
+        let data = frame(len);
+        let mut out = vec![0.0; len / 2];
+        c.bench_function(&format!("autocorrelation {}", len), |b| {
+            b.iter(|| dsp::autocorrelation(black_box(&data), &mut out))
+        });
+    }
+}
+
+criterion_group!(benches, bench_rms, bench_hann, bench_autocorrelation);
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+// examples/accuracy.rs
+//
+// Accuracy harness over the recordings in manual_testing/. Each file is
+// named after its frequency; every file is analysed with and without lag
+// refinement and the average error and frame-to-frame spread are printed
+// in cents. An optional second argument picks the detector (yin or acf).
+//
+// cargo run -p nofuzz_tuner_lib --example accuracy -- ../manual_testing acf
+
+use nofuzz_tuner_lib::wav::decode_wav;
+use nofuzz_tuner_lib::{cents_between, AcfPitchDetector, PitchTracker, RangePreset, TuningHandle};
+
+fn analyse(samples: &[f64], sample_rate: usize, refine: bool, detector: &str) -> Vec<f64> {
+    let settings = RangePreset::FullChromatic.settings(sample_rate);
+    let mut tracker = match detector {
+        "acf" => PitchTracker::with_detector(
+            Box::new(AcfPitchDetector::new(settings.freq_min, settings.freq_max, sample_rate)),
+            TuningHandle::standard(),
+            sample_rate,
+        ),
+        _ => PitchTracker::new(0.1, settings.freq_min, settings.freq_max, sample_rate),
+    };
+    tracker.set_lag_refinement(refine);
+    tracker.set_smoothing(1.0);
+    samples
+        .chunks_exact(settings.frame_len)
+        .filter_map(|frame| tracker.process(frame).map(|r| r.freq))
+        .collect()
+}
+
+fn stats(freqs: &[f64], expected: f64) -> (f64, f64) {
+    let cents: Vec<f64> = freqs.iter().map(|f| cents_between(*f, expected)).collect();
+    let mean = cents.iter().sum::<f64>() / cents.len() as f64;
+    let var = cents.iter().map(|c| (c - mean) * (c - mean)).sum::<f64>() / cents.len() as f64;
+    (mean, var.sqrt())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::args().nth(1).unwrap_or("manual_testing".to_string());
+    let detector = std::env::args().nth(2).unwrap_or("yin".to_string());
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)?.filter_map(|e| e.ok().map(|e| e.path())).collect();
+    paths.sort();
+
+    println!("{:>10} {:>22} {:>22}", "file", "plain mean/spread", "refined mean/spread");
+    for path in paths {
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+        let digits: String = stem.chars().filter(|c| c.is_ascii_digit()).collect();
+        let (Ok(expected), Some("wav")) = (digits.parse::<f64>(), path.extension().and_then(|e| e.to_str())) else {
+            continue;
+        };
+        let (samples, sample_rate) = decode_wav(&std::fs::read(&path)?)?;
+        let plain = analyse(&samples, sample_rate as usize, false, &detector);
+        let refined = analyse(&samples, sample_rate as usize, true, &detector);
+        if plain.is_empty() || refined.is_empty() {
+            println!("{:>10} no detections", stem);
+            continue;
+        }
+        let (pm, ps) = stats(&plain, expected);
+        let (rm, rs) = stats(&refined, expected);
+        println!("{:>10} {:>+11.2} {:>10.3} {:>+11.2} {:>10.3}", stem, pm, ps, rm, rs);
+    }
+    Ok(())
+}
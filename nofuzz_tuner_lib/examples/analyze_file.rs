@@ -0,0 +1,41 @@
+// examples/analyze_file.rs
+//
+// Offline analysis of a recording: runs the tracker over a 16-bit WAV file
+// frame by frame and prints every detection with its timestamp. With
+// --notes it prints one line per note played instead.
+//
+// cargo run -p nofuzz_tuner_lib --example analyze_file -- recording.wav [--notes]
+
+use nofuzz_tuner_lib::wav::decode_wav;
+use nofuzz_tuner_lib::{NoteEvent, NoteTracker, PitchTracker, RangePreset};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::args().nth(1).ok_or("usage: analyze_file <file.wav> [--notes]")?;
+    let notes_only = std::env::args().any(|a| a == "--notes");
+    let (samples, sample_rate) = decode_wav(&std::fs::read(path)?)?;
+    let settings = RangePreset::Guitar.settings(sample_rate as usize);
+
+    let mut tracker = PitchTracker::new(0.1, settings.freq_min, settings.freq_max, sample_rate as usize);
+    let mut notes = NoteTracker::default();
+    for frame in samples.chunks_exact(settings.frame_len) {
+        let result = tracker.process(frame);
+        if notes_only {
+            for event in notes.update(tracker.stream_time(), result.as_ref()) {
+                print_note(&event);
+            }
+        } else if let Some(result) = result {
+            let t = result.tuning_to;
+            println!("{:7.3}s  {:7.2} Hz  {} {:+.1} cents", tracker.stream_time(), result.freq, t.note, t.cents);
+        }
+    }
+    if let Some(event) = notes.finish() {
+        print_note(&event);
+    }
+    Ok(())
+}
+
+fn print_note(event: &NoteEvent) {
+    if let NoteEvent::NoteOff { note, start, duration, mean_cents, .. } = event {
+        println!("{:7.3}s  {:<4} {:5.2} s  {:+.1} cents on average", start, note, duration, mean_cents);
+    }
+}
@@ -0,0 +1,42 @@
+// examples/native_cpal.rs
+//
+// Minimal native embedding: default input device -> PitchTracker -> stdout.
+// Assumes the device delivers f32 samples, see src/main.rs for handling the
+// other sample formats.
+//
+// cargo run -p nofuzz_tuner_lib --example native_cpal
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+use nofuzz_tuner_lib::{PitchTracker, RangePreset};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let host = cpal::default_host();
+    let device = host.default_input_device().ok_or("no input device")?;
+    let sample_rate = device.default_input_config()?.sample_rate();
+
+    let settings = RangePreset::Guitar.settings(sample_rate.0 as usize);
+    let config = cpal::StreamConfig {
+        channels: 1,
+        sample_rate,
+        buffer_size: cpal::BufferSize::Fixed(settings.frame_len as u32),
+    };
+
+    let mut tracker = PitchTracker::new(0.1, settings.freq_min, settings.freq_max, sample_rate.0 as usize);
+    tracker.set_live_input(true);
+    let stream = device.build_input_stream(
+        &config,
+        move |data: &[f32], _| {
+            let frame: Vec<f64> = data.iter().map(|x| *x as f64).collect();
+            if let Some(result) = tracker.process(&frame) {
+                let t = result.tuning_to;
+                println!("{:.1} Hz  {} {:+.1} cents", result.freq, t.note, t.cents);
+            }
+        },
+        |err| eprintln!("{}", err),
+    )?;
+
+    stream.play()?;
+    std::thread::sleep(std::time::Duration::from_secs(30));
+    Ok(())
+}
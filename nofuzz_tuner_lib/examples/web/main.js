@@ -0,0 +1,22 @@
+// examples/web/main.js
+//
+// Wires the microphone into tuner-worklet.js. Build the package into
+// examples/web/pkg with:
+//
+//   wasm-pack build --release --target web --out-dir examples/web/pkg
+
+export async function startTuner(onResult) {
+    const context = new AudioContext();
+    await context.audioWorklet.addModule('tuner-worklet.js');
+
+    const media = await navigator.mediaDevices.getUserMedia({ audio: true });
+    const source = context.createMediaStreamSource(media);
+    const node = new AudioWorkletNode(context, 'tuner-processor');
+
+    const wasm = await (await fetch('pkg/nofuzz_tuner_lib_bg.wasm')).arrayBuffer();
+    node.port.postMessage({ wasm });
+    node.port.onmessage = (e) => onResult(e.data.result, e.data.level);
+
+    source.connect(node);
+    return context;
+}
@@ -0,0 +1,39 @@
+// examples/web/tuner-worklet.js
+//
+// AudioWorklet processor running the Rust PitchTracker on the audio thread.
+// The worklet can't fetch, so the main thread sends the compiled wasm bytes
+// (see main.js) and the module is instantiated with initSync. Worklets get
+// 128-sample render quanta; they are collected until a full frame is ready.
+
+import { initSync, PitchTracker, range_preset_settings } from './pkg/nofuzz_tuner_lib.js';
+
+class TunerProcessor extends AudioWorkletProcessor {
+    constructor() {
+        super();
+        this.tracker = null;
+        this.port.onmessage = (e) => {
+            initSync(e.data.wasm);
+            const settings = range_preset_settings('guitar', sampleRate);
+            this.tracker = new PitchTracker(0.1, settings.freq_min, settings.freq_max, sampleRate);
+            this.frame = new Float64Array(settings.frame_len);
+            this.filled = 0;
+        };
+    }
+
+    process(inputs) {
+        const input = inputs[0][0];
+        if (!this.tracker || !input) {
+            return true;
+        }
+        for (let i = 0; i < input.length; i++) {
+            this.frame[this.filled++] = input[i];
+            if (this.filled === this.frame.length) {
+                this.filled = 0;
+                this.port.postMessage({ result: this.tracker.process(this.frame) ?? null, level: this.tracker.level() });
+            }
+        }
+        return true;
+    }
+}
+
+registerProcessor('tuner-processor', TunerProcessor);
@@ -0,0 +1,45 @@
+// examples/websocket_server.rs
+//
+// Detection as a service: clients send binary WebSocket messages holding
+// little-endian f32 frames and get each PitchResult back as JSON, or null
+// when nothing was detected. One tracker per connection keeps smoothing and
+// note events separate between clients.
+//
+// cargo run -p nofuzz_tuner_lib --example websocket_server -- 48000
+
+use std::net::TcpListener;
+use std::thread;
+
+use tungstenite::{accept, Message};
+
+use nofuzz_tuner_lib::{PitchTracker, RangePreset};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let sample_rate: usize = std::env::args().nth(1).map_or(Ok(44100), |s| s.parse())?;
+    let listener = TcpListener::bind("127.0.0.1:9001")?;
+    println!("listening on ws://127.0.0.1:9001");
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        thread::spawn(move || {
+            let settings = RangePreset::Guitar.settings(sample_rate);
+            let mut tracker = PitchTracker::new(0.1, settings.freq_min, settings.freq_max, sample_rate);
+            let mut socket = match accept(stream) {
+                Ok(socket) => socket,
+                Err(e) => return eprintln!("handshake failed: {}", e),
+            };
+            while let Ok(msg) = socket.read() {
+                let Message::Binary(bytes) = msg else { continue };
+                let frame: Vec<f64> = bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+                    .collect();
+                let reply = serde_json::to_string(&tracker.process(&frame)).unwrap();
+                if socket.send(Message::Text(reply)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    Ok(())
+}
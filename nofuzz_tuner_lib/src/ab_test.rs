@@ -0,0 +1,181 @@
+// src/ab_test.rs
+//
+// A/B listening test for filter settings: the same phrase rendered through
+// two filter configurations, to pick one by ear instead of by trial and
+// error in live mode. Record the phrase without filters (clear_filters
+// before start_capture, or any mono WAV) so both sides start from the same
+// input.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::dsp;
+use crate::filters::{FilterChain, FilterSpec};
+#[cfg(feature = "wasm")]
+use crate::filters::FilterSpecs;
+use crate::wav::encode_wav;
+
+// Silence between A and B in the back to back render
+pub const DEFAULT_AB_GAP_SECONDS: f64 = 1.0;
+
+// Phrase and filters for the CLI, see Config::ab_test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbTestConfig {
+    // Mono 16-bit WAV to render
+    pub phrase: String,
+    pub a: Vec<FilterSpec>,
+    pub b: Vec<FilterSpec>,
+    #[serde(default)]
+    pub gap_seconds: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AbTest {
+    pub a: Vec<f64>,
+    pub b: Vec<f64>,
+    pub sample_rate: usize,
+}
+
+impl AbTest {
+    // Both renders are aligned with the phrase (the FIR delay is taken out)
+    // and brought to the phrase's RMS, as the louder side tends to sound
+    // better whatever the filters do
+    pub fn render(phrase: &[f64], sample_rate: usize, a: &[FilterSpec], b: &[FilterSpec]) -> Result<AbTest, String> {
+        let a = FilterChain::from_specs(a, sample_rate).map_err(|e| format!("filters A: {}", e))?;
+        let b = FilterChain::from_specs(b, sample_rate).map_err(|e| format!("filters B: {}", e))?;
+        let mut a = render_chain(phrase, a);
+        let mut b = render_chain(phrase, b);
+        let target = dsp::rms(phrase);
+        for side in [&mut a, &mut b] {
+            let rms = dsp::rms(side);
+            if rms > 0.0 {
+                side.iter_mut().for_each(|x| *x *= target / rms);
+            }
+        }
+        // Matching can push a side past full scale; scale both alike
+        let peak = a.iter().chain(b.iter()).fold(0.0_f64, |peak, x| peak.max(x.abs()));
+        if peak > 1.0 {
+            a.iter_mut().chain(b.iter_mut()).for_each(|x| *x /= peak);
+        }
+        Ok(AbTest { a, b, sample_rate })
+    }
+
+    pub fn a_wav(&self) -> Vec<u8> {
+        encode_wav(&self.a, self.sample_rate as u32)
+    }
+
+    pub fn b_wav(&self) -> Vec<u8> {
+        encode_wav(&self.b, self.sample_rate as u32)
+    }
+
+    // A, `gap_seconds` of silence, then B, to play back to back
+    pub fn sequence(&self, gap_seconds: f64) -> Vec<f64> {
+        let gap = (gap_seconds.max(0.0) * self.sample_rate as f64).round() as usize;
+        let mut out = self.a.clone();
+        out.resize(out.len() + gap, 0.0);
+        out.extend_from_slice(&self.b);
+        out
+    }
+
+    pub fn sequence_wav(&self, gap_seconds: f64) -> Vec<u8> {
+        encode_wav(&self.sequence(gap_seconds), self.sample_rate as u32)
+    }
+
+    // Writes <stem>_a.wav, <stem>_b.wav and <stem>_ab.wav (A then B) and
+    // returns their paths
+    pub fn save(&self, stem: &str, gap_seconds: f64) -> Result<Vec<String>, String> {
+        let files = [("a", self.a_wav()), ("b", self.b_wav()), ("ab", self.sequence_wav(gap_seconds))];
+        let mut paths = vec![];
+        for (suffix, bytes) in files {
+            let path = format!("{}_{}.wav", stem, suffix);
+            std::fs::write(&path, bytes).map_err(|e| format!("can't write {}: {}", path, e))?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+// The phrase through `chain` from rest, shifted back by the chain's delay
+// so it lines up with the input
+fn render_chain(phrase: &[f64], mut chain: FilterChain) -> Vec<f64> {
+    let delay = (chain.latency() * chain.sample_rate() as f64).round() as usize;
+    let mut out = phrase.to_vec();
+    out.resize(phrase.len() + delay, 0.0);
+    chain.process_frame(&mut out);
+    out.drain(..delay);
+    out
+}
+
+// A then B as one WAV file, see AbTest::sequence
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn render_ab_wav(
+    phrase: &[f64],
+    sample_rate: usize,
+    a: FilterSpecs,
+    b: FilterSpecs,
+    gap_seconds: Option<f64>,
+) -> Result<Vec<u8>, JsError> {
+    let test = AbTest::render(phrase, sample_rate, &a.0, &b.0).map_err(|e| JsError::new(&e))?;
+    Ok(test.sequence_wav(gap_seconds.unwrap_or(DEFAULT_AB_GAP_SECONDS)))
+}
+
+// One side on its own, e.g. to offer both files for download
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn render_filtered_wav(phrase: &[f64], sample_rate: usize, filters: FilterSpecs) -> Result<Vec<u8>, JsError> {
+    let test = AbTest::render(phrase, sample_rate, &filters.0, &[]).map_err(|e| JsError::new(&e))?;
+    Ok(test.a_wav())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::harmonic_tone;
+
+    const SAMPLE_RATE: usize = 44100;
+
+    #[test]
+    fn renders_line_up_with_the_phrase() {
+        let phrase = harmonic_tone(110.0, &[1.0, 0.5, 0.25], SAMPLE_RATE, SAMPLE_RATE / 2);
+        let fir = [FilterSpec::FirLowpass { fc: 2000.0, taps: 255 }];
+        let test = AbTest::render(&phrase, SAMPLE_RATE, &fir, &[]).unwrap();
+        assert_eq!(test.a.len(), phrase.len());
+        assert_eq!(test.b.len(), phrase.len());
+        // The lowpass leaves these partials alone; without the delay taken
+        // out A would lag B by 127 samples
+        let middle = SAMPLE_RATE / 8..SAMPLE_RATE * 3 / 8;
+        let error = test.a[middle.clone()].iter().zip(&test.b[middle]).fold(0.0_f64, |e, (a, b)| e.max((a - b).abs()));
+        assert!(error < 0.01, "A and B differ by {}", error);
+    }
+
+    #[test]
+    fn sides_are_level_matched() {
+        let phrase = harmonic_tone(82.41, &[1.0, 1.0, 1.0, 1.0], SAMPLE_RATE, SAMPLE_RATE / 2);
+        let a = [FilterSpec::ButterworthHighpass { fc: 200.0, order: 4 }];
+        let test = AbTest::render(&phrase, SAMPLE_RATE, &a, &[]).unwrap();
+        let (rms_a, rms_b) = (dsp::rms(&test.a), dsp::rms(&test.b));
+        assert!((rms_a / rms_b - 1.0).abs() < 0.01, "A {} B {}", rms_a, rms_b);
+    }
+
+    #[test]
+    fn sequence_is_a_then_gap_then_b() {
+        let phrase = harmonic_tone(220.0, &[1.0], SAMPLE_RATE, 1000);
+        let test = AbTest::render(&phrase, SAMPLE_RATE, &[FilterSpec::Lowpass { fc: 1000.0, q: 0.707 }], &[]).unwrap();
+        let sequence = test.sequence(0.5);
+        assert_eq!(sequence.len(), 2000 + SAMPLE_RATE / 2);
+        assert_eq!(&sequence[..1000], &test.a[..]);
+        assert!(sequence[1000..1000 + SAMPLE_RATE / 2].iter().all(|x| *x == 0.0));
+        assert_eq!(&sequence[1000 + SAMPLE_RATE / 2..], &test.b[..]);
+        let (decoded, rate) = crate::wav::decode_wav(&test.sequence_wav(0.5)).unwrap();
+        assert_eq!((decoded.len(), rate), (sequence.len(), SAMPLE_RATE as u32));
+    }
+
+    #[test]
+    fn bad_filters_name_the_side() {
+        let phrase = vec![0.0; 100];
+        let err = AbTest::render(&phrase, SAMPLE_RATE, &[], &[FilterSpec::FirLowpass { fc: 1000.0, taps: 4 }]).unwrap_err();
+        assert!(err.starts_with("filters B"), "{}", err);
+    }
+}
@@ -0,0 +1,91 @@
+// src/acf.rs
+//
+// Plain autocorrelation detector: the lag with the highest normalised
+// autocorrelation within the range, refined by fitting a parabola through
+// the peak. Simpler and usually less robust than YIN or McLeod, mostly here
+// as a baseline to benchmark them against.
+
+use crate::{dsp, fit_freq_max, min_frame_len, PitchFindTrait, TuningHandle};
+
+// Normalised autocorrelation a peak needs to count as a pitch
+const ACF_MIN_PEAK: f64 = 0.5;
+// The first peak within this share of the highest one wins, so a period of
+// two cycles doesn't beat the true one (an octave low reading)
+const ACF_FIRST_PEAK_RATIO: f64 = 0.9;
+
+pub struct AcfPitchDetector {
+    freq_min: f64,
+    freq_max: f64,
+    sample_rate: usize,
+    acf: Vec<f64>,
+    peak: Option<f64>,
+}
+
+impl AcfPitchDetector {
+    pub fn new(freq_min: f64, freq_max: f64, sample_rate: usize) -> AcfPitchDetector {
+        AcfPitchDetector { freq_min, freq_max, sample_rate, acf: vec![], peak: None }
+    }
+
+    fn lag_range(&self) -> (usize, usize) {
+        let min_lag = (self.sample_rate as f64 / self.freq_max).floor().max(2.0) as usize;
+        let max_lag = (self.sample_rate as f64 / self.freq_min).ceil() as usize;
+        (min_lag, max_lag)
+    }
+}
+
+impl PitchFindTrait for AcfPitchDetector {
+    fn maybe_find_pitch(&mut self, data: &[f64]) -> Option<f64> {
+        self.peak = None;
+        let (min_lag, max_lag) = self.lag_range();
+        let n = data.len();
+        if n < 2 * max_lag {
+            return None;
+        }
+        // One lag past the range so the last one can be interpolated
+        self.acf.resize(max_lag + 2, 0.0);
+        dsp::autocorrelation(data, &mut self.acf);
+        let energy = self.acf[0] / n as f64;
+        if energy <= 0.0 {
+            return None;
+        }
+        // Unbiased: every lag averages over the samples it overlaps
+        for (lag, r) in self.acf.iter_mut().enumerate() {
+            *r /= (n - lag) as f64 * energy;
+        }
+
+        let acf = &self.acf;
+        let is_peak = |lag: usize| acf[lag] >= acf[lag - 1] && acf[lag] >= acf[lag + 1];
+        let highest = (min_lag..=max_lag).filter(|l| is_peak(*l)).map(|l| acf[l]).fold(f64::NEG_INFINITY, f64::max);
+        if highest < ACF_MIN_PEAK {
+            return None;
+        }
+        let lag = (min_lag..=max_lag).find(|l| is_peak(*l) && acf[*l] >= ACF_FIRST_PEAK_RATIO * highest)?;
+
+        let (a, b, c) = (acf[lag - 1], acf[lag], acf[lag + 1]);
+        let denom = a - 2.0 * b + c;
+        let offset = if denom < 0.0 { 0.5 * (a - c) / denom } else { 0.0 };
+        self.peak = Some(b.clamp(0.0, 1.0));
+        Some(self.sample_rate as f64 / (lag as f64 + offset))
+    }
+
+    fn preferred_frame_len(&self) -> usize {
+        min_frame_len(self.freq_min, self.sample_rate).next_power_of_two()
+    }
+
+    fn accepts_frame_len(&self, len: usize) -> bool {
+        len >= 2 * self.lag_range().1
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: usize) -> Result<(), String> {
+        self.sample_rate = sample_rate;
+        Ok(())
+    }
+
+    fn fit_to_tuning(&mut self, tuning: &TuningHandle) {
+        self.freq_max = fit_freq_max(tuning, self.freq_max, self.sample_rate);
+    }
+
+    fn last_confidence(&self) -> Option<f64> {
+        self.peak
+    }
+}
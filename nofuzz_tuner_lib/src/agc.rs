@@ -0,0 +1,68 @@
+// src/agc.rs
+//
+// Automatic gain control ahead of detection. A phone mic across the room
+// can deliver a plucked string at -50 dBFS, where the detector's dips and
+// peaks shrink towards rounding noise and its thresholds stop meaning what
+// they were tuned for. The gain brings each frame's RMS into a band around
+// the target: it drops quickly when a loud pluck arrives and climbs slowly
+// as the note decays, so a ringing note isn't pumped back up to full level.
+// The gain is constant over a frame so the waveform within a window keeps
+// its shape.
+
+use crate::dsp;
+
+pub const DEFAULT_AGC_TARGET_RMS: f64 = 0.1;
+// Frames within this many dB of the target leave the gain alone
+const AGC_DEADBAND_DB: f64 = 6.0;
+// Gain limits; more than this only lifts the noise
+const AGC_MAX_GAIN_DB: f64 = 40.0;
+const AGC_MIN_GAIN_DB: f64 = -20.0;
+// Time constants for lowering and raising the gain
+const AGC_ATTACK_SECONDS: f64 = 0.05;
+const AGC_RELEASE_SECONDS: f64 = 1.0;
+
+#[derive(Debug, Clone)]
+pub struct Agc {
+    target_db: f64,
+    gain_db: f64,
+    // The first frame with signal sets the gain outright
+    primed: bool,
+}
+
+impl Agc {
+    // `target_rms` as a linear amplitude, e.g. 0.1 for -20 dBFS
+    pub fn new(target_rms: f64) -> Agc {
+        Agc { target_db: 20.0 * target_rms.clamp(1e-6, 1.0).log10(), gain_db: 0.0, primed: false }
+    }
+
+    // Current gain in dB
+    pub fn gain_db(&self) -> f64 {
+        self.gain_db
+    }
+
+    // Updates the gain from the level of the frame, which advances the
+    // stream by `seconds`, and applies it
+    pub fn process_frame(&mut self, data: &mut [f64], seconds: f64) {
+        let rms = dsp::rms(data);
+        if rms > 0.0 {
+            let level_db = 20.0 * rms.log10();
+            let error = self.target_db - (level_db + self.gain_db);
+            if error.abs() > AGC_DEADBAND_DB {
+                // Aim for the edge of the band rather than the middle, so
+                // the level doesn't hunt around the target
+                let wanted = (self.gain_db + error - AGC_DEADBAND_DB * error.signum()).clamp(AGC_MIN_GAIN_DB, AGC_MAX_GAIN_DB);
+                let tau = if wanted < self.gain_db { AGC_ATTACK_SECONDS } else { AGC_RELEASE_SECONDS };
+                let weight = if self.primed { 1.0 - (-seconds / tau).exp() } else { 1.0 };
+                self.gain_db += weight * (wanted - self.gain_db);
+            }
+            self.primed = true;
+        }
+        let gain = 10f64.powf(self.gain_db / 20.0);
+        data.iter_mut().for_each(|x| *x *= gain);
+    }
+
+    pub fn reset(&mut self) {
+        self.gain_db = 0.0;
+        self.primed = false;
+    }
+}
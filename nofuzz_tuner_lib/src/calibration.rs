@@ -0,0 +1,153 @@
+// src/calibration.rs
+//
+// Calibration against a known reference (tuning fork, 440 Hz tone). Cheap
+// interfaces often run their sample clock a little off, which shifts every
+// reading by the same ratio. Playing the reference measures that ratio once;
+// it's stored in parts per million and divided out of later results.
+
+use crate::{dsp, probe_string};
+
+// Frames needed before a calibration is trusted
+const MIN_CALIBRATION_FRAMES: usize = 20;
+// A frame counts when the reference carries at least this share of its
+// amplitude (a pure sine has amplitude sqrt(2) * rms)
+const MIN_REFERENCE_RATIO: f64 = 0.7;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Calibration {
+    reference: f64,
+    cents: Vec<f64>,
+}
+
+impl Calibration {
+    pub(crate) fn new(reference: f64) -> Calibration {
+        Calibration { reference, cents: vec![] }
+    }
+
+    pub(crate) fn add_frame(&mut self, data: &[f64], sample_rate: usize) {
+        let (cents, amplitude) = probe_string(data, sample_rate, self.reference);
+        if amplitude >= MIN_REFERENCE_RATIO * std::f64::consts::SQRT_2 * dsp::rms(data) {
+            self.cents.push(cents);
+        }
+    }
+
+    // Measured offset of the chain in ppm, positive when it reads sharp
+    pub(crate) fn finish(&self) -> Result<f64, String> {
+        if self.cents.len() < MIN_CALIBRATION_FRAMES {
+            return Err(format!(
+                "only {} usable frames of the {} Hz reference, need {}",
+                self.cents.len(),
+                self.reference,
+                MIN_CALIBRATION_FRAMES
+            ));
+        }
+        let mut sorted = self.cents.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let median = sorted[sorted.len() / 2];
+        Ok((2f64.powf(median / 1200.0) - 1.0) * 1e6)
+    }
+}
+
+// Drift between the audio clock and the wall clock. Fits a line through
+// (wall time, stream time) pairs taken about once a second; the slope's
+// distance from 1 is the drift. Callback jitter averages out, so the
+// estimate is only reported after a while.
+const DRIFT_SAMPLE_INTERVAL_SECONDS: f64 = 1.0;
+const MIN_DRIFT_SECONDS: f64 = 30.0;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ClockDrift {
+    origin: Option<(f64, f64)>,
+    last_wall: f64,
+    n: f64,
+    sum_x: f64,
+    sum_y: f64,
+    sum_xy: f64,
+    sum_xx: f64,
+}
+
+impl ClockDrift {
+    pub(crate) fn observe(&mut self, wall: f64, stream: f64) {
+        let (wall0, stream0) = *self.origin.get_or_insert((wall, stream));
+        let (x, y) = (wall - wall0, stream - stream0);
+        if self.n > 0.0 && x - self.last_wall < DRIFT_SAMPLE_INTERVAL_SECONDS {
+            return;
+        }
+        self.last_wall = x;
+        self.n += 1.0;
+        self.sum_x += x;
+        self.sum_y += y;
+        self.sum_xy += x * y;
+        self.sum_xx += x * x;
+    }
+
+    // Positive when the audio clock runs fast compared to the wall clock
+    pub(crate) fn ppm(&self) -> Option<f64> {
+        if self.last_wall < MIN_DRIFT_SECONDS {
+            return None;
+        }
+        let denom = self.n * self.sum_xx - self.sum_x * self.sum_x;
+        if denom <= 0.0 {
+            return None;
+        }
+        let slope = (self.n * self.sum_xy - self.sum_x * self.sum_y) / denom;
+        Some((slope - 1.0) * 1e6)
+    }
+}
+
+// Removes a measured ppm offset from a frequency
+pub fn apply_ppm(freq: f64, ppm: f64) -> f64 {
+    freq / (1.0 + ppm / 1e6)
+}
+
+// Quick check of the capture rate against the rate the stream claims. After
+// a device switch a browser can keep reporting the AudioContext rate while
+// delivering audio at another one, which moves every reading by a constant
+// interval (44.1 vs 48 kHz is about 1.5 semitones). Samples arriving per
+// wall clock second show that within seconds, long before ClockDrift would.
+const RATE_CHECK_SECONDS: f64 = 5.0;
+// Offsets below this are left to calibration and drift estimation
+const RATE_MISMATCH_RATIO: f64 = 0.02;
+// A pause this long between frames (suspended tab, stopped stream)
+// restarts the window instead of counting as a slow clock
+const RATE_CHECK_MAX_GAP_SECONDS: f64 = 1.0;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RateCheck {
+    start: Option<f64>,
+    last: f64,
+    samples: u64,
+    observed: Option<f64>,
+}
+
+impl RateCheck {
+    // Called once per frame with the wall clock and the frame length. Returns
+    // the observed rate when a window finishes and it's off from `expected`.
+    pub(crate) fn observe(&mut self, wall: f64, samples: usize, expected: usize) -> Option<f64> {
+        let start = match self.start {
+            Some(start) if wall - self.last <= RATE_CHECK_MAX_GAP_SECONDS => start,
+            _ => {
+                // The first frame's samples were captured before `wall`
+                self.start = Some(wall);
+                self.last = wall;
+                self.samples = 0;
+                return None;
+            }
+        };
+        self.last = wall;
+        self.samples += samples as u64;
+        if wall - start < RATE_CHECK_SECONDS {
+            return None;
+        }
+        let observed = self.samples as f64 / (wall - start);
+        self.start = Some(wall);
+        self.samples = 0;
+        self.observed = Some(observed);
+        if (observed / expected as f64 - 1.0).abs() > RATE_MISMATCH_RATIO { Some(observed) } else { None }
+    }
+
+    // Rate measured over the last full window
+    pub(crate) fn observed(&self) -> Option<f64> {
+        self.observed
+    }
+}
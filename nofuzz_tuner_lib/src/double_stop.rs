@@ -0,0 +1,95 @@
+// src/double_stop.rs
+//
+// Two strings bowed or plucked together, the usual way violinists and
+// mandolinists check their fifths. Not general polyphony: the monitor probes
+// every string, the two loudest neighbouring ones are taken as the pair and
+// their interval is compared with a pure or an equal tempered reference.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{monitor_strings, StringStatus, TuningHandle};
+#[cfg(feature = "wasm")]
+use crate::InstrumentProfile;
+
+// Intervals within this many cents of a pure ratio are compared against it
+const JUST_MATCH_CENTS: f64 = 30.0;
+// Pure ratios string players tune by ear
+const JUST_RATIOS: [f64; 5] = [3.0 / 2.0, 4.0 / 3.0, 5.0 / 4.0, 6.0 / 5.0, 2.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub enum IntervalReference {
+    // Beatless ratios, a fifth is 701.96 cents
+    Just,
+    // Whole semitones, a fifth is 700 cents
+    Equal,
+}
+
+impl IntervalReference {
+    pub fn from_name(name: &str) -> Result<IntervalReference, String> {
+        match name {
+            "just" => Ok(IntervalReference::Just),
+            "equal" => Ok(IntervalReference::Equal),
+            _ => Err(format!("unknown interval reference '{}', expected just or equal", name)),
+        }
+    }
+
+    // Target for an interval that is nominally `cents` wide
+    pub fn target_cents(&self, cents: f64) -> f64 {
+        let equal = (cents / 100.0).round() * 100.0;
+        match self {
+            IntervalReference::Equal => equal,
+            IntervalReference::Just => JUST_RATIOS
+                .iter()
+                .map(|r| 1200.0 * r.log2())
+                .find(|just| (just - equal).abs() < JUST_MATCH_CENTS)
+                .unwrap_or(equal),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DoubleStop {
+    pub lower: StringStatus,
+    pub upper: StringStatus,
+    // Measured interval between the two
+    pub interval_cents: f64,
+    // Measured minus the reference interval, positive when too wide
+    pub interval_error_cents: f64,
+}
+
+// The pair of neighbouring strings ringing loudest together, None unless two
+// neighbours ring. Needs the same long frames as monitor_strings.
+pub fn detect_double_stop(
+    tuning: &TuningHandle,
+    data: &[f64],
+    sample_rate: usize,
+    reference: IntervalReference,
+) -> Option<DoubleStop> {
+    let statuses = monitor_strings(tuning, data, sample_rate);
+    let (lower, upper) = statuses
+        .windows(2)
+        .filter(|pair| pair[0].ringing && pair[1].ringing)
+        .max_by(|a, b| (a[0].amplitude.min(a[1].amplitude)).total_cmp(&b[0].amplitude.min(b[1].amplitude)))
+        .map(|pair| {
+            if pair[0].frequency <= pair[1].frequency { (pair[0].clone(), pair[1].clone()) } else { (pair[1].clone(), pair[0].clone()) }
+        })?;
+    let nominal = 1200.0 * (upper.frequency / lower.frequency).log2();
+    let interval_cents = nominal + upper.cents - lower.cents;
+    let interval_error_cents = interval_cents - reference.target_cents(nominal);
+    Some(DoubleStop { lower, upper, interval_cents, interval_error_cents })
+}
+
+// Uses the instrument's tuning and its interval reference
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = detect_double_stop)]
+pub fn detect_double_stop_js(instrument: &str, data: &[f64], sample_rate: usize) -> Result<Option<DoubleStop>, JsError> {
+    let instrument = InstrumentProfile::from_name(instrument).map_err(|e| JsError::new(&e))?;
+    let tuning = TuningHandle::resolve(instrument.tuning()).map_err(|e| JsError::new(&e))?;
+    Ok(detect_double_stop(&tuning, data, sample_rate, instrument.interval_reference()))
+}
@@ -0,0 +1,120 @@
+// src/dsp.rs
+//
+// Frame level helpers used in the hot path. Plain slice loops: versions
+// specialised on 1024/2048/4096 sample frames benchmarked no faster.
+
+pub fn rms(data: &[f64]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = data.iter().map(|x| x * x).sum();
+    (sum / data.len() as f64).sqrt()
+}
+
+// Applies a Hann window in place.
+pub fn apply_hann(data: &mut [f64]) {
+    let n = data.len();
+    if n < 2 {
+        return;
+    }
+    let step = 2.0 * std::f64::consts::PI / (n - 1) as f64;
+    for (i, x) in data.iter_mut().enumerate() {
+        *x *= 0.5 - 0.5 * (step * i as f64).cos();
+    }
+}
+
+// Unnormalised autocorrelation for lags 0..out.len(). Lags beyond the frame
+// length are set to zero.
+pub fn autocorrelation(data: &[f64], out: &mut [f64]) {
+    let n = data.len();
+    for (lag, r) in out.iter_mut().enumerate() {
+        if lag >= n {
+            *r = 0.0;
+            continue;
+        }
+        *r = data[..n - lag]
+            .iter()
+            .zip(&data[lag..])
+            .map(|(a, b)| a * b)
+            .sum();
+    }
+}
+
+// Power of a single frequency component using the Goertzel algorithm.
+// Normalised by frame length so frames of different sizes compare.
+pub fn goertzel_power(data: &[f64], freq: f64, sample_rate: usize) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let w = 2.0 * std::f64::consts::PI * freq / sample_rate as f64;
+    let coeff = 2.0 * w.cos();
+    let mut s_prev = 0.0;
+    let mut s_prev2 = 0.0;
+    for x in data {
+        let s = x + coeff * s_prev - s_prev2;
+        s_prev2 = s_prev;
+        s_prev = s;
+    }
+    let power = s_prev2 * s_prev2 + s_prev * s_prev - coeff * s_prev * s_prev2;
+    power / (data.len() * data.len()) as f64
+}
+
+// Squared difference between the frame and itself shifted by `lag`, over
+// the first `window` samples (the YIN difference function at one lag).
+pub fn difference_at(data: &[f64], lag: usize, window: usize) -> f64 {
+    data[..window].iter().zip(&data[lag..lag + window]).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+// How periodic the frame is at `freq`, 0 (noise) to 1 (a perfectly
+// repeating waveform): one minus YIN's aperiodicity, the difference function
+// at the period relative to the energy of both windows. The best of the
+// neighbouring whole-sample lags is taken so fractional periods aren't
+// penalised.
+pub fn periodicity(data: &[f64], sample_rate: usize, freq: f64) -> f64 {
+    if !freq.is_finite() || freq <= 0.0 {
+        return 0.0;
+    }
+    let period = (sample_rate as f64 / freq).round() as usize;
+    let last = period + 1;
+    let Some(window) = data.len().checked_sub(last).filter(|w| *w > 0 && period > 1) else {
+        return 0.0;
+    };
+    let energy = |from: usize| data[from..from + window].iter().map(|x| x * x).sum::<f64>();
+    let aperiodicity = (period - 1..=last)
+        .map(|lag| {
+            let total = energy(0) + energy(lag);
+            if total > 0.0 { difference_at(data, lag, window) / total } else { 1.0 }
+        })
+        .fold(f64::INFINITY, f64::min);
+    (1.0 - aperiodicity).clamp(0.0, 1.0)
+}
+
+// Lag-domain refinement of a coarse pitch estimate. Detectors working on
+// whole-sample lags are limited to about 1/lag relative precision (several
+// cents for high notes); fitting a parabola through the difference function
+// around the coarse period recovers a fractional lag and sub-cent precision.
+// Returns None when the frame is too short or the minimum isn't bracketed.
+pub fn refine_pitch(data: &[f64], sample_rate: usize, freq: f64) -> Option<f64> {
+    if !freq.is_finite() || freq <= 0.0 {
+        return None;
+    }
+    let period = (sample_rate as f64 / freq).round() as usize;
+    if period < 3 {
+        return None;
+    }
+    let (first, last) = (period - 2, period + 2);
+    let window = data.len().checked_sub(last)?;
+    if window < period {
+        return None;
+    }
+    let d: Vec<f64> = (first..=last).map(|lag| difference_at(data, lag, window)).collect();
+    let (best, _) = d.iter().enumerate().min_by(|a, b| a.1.total_cmp(b.1))?;
+    if best == 0 || best == d.len() - 1 {
+        return None;
+    }
+    let (a, b, c) = (d[best - 1], d[best], d[best + 1]);
+    let denom = a - 2.0 * b + c;
+    let offset = if denom > 0.0 { 0.5 * (a - c) / denom } else { 0.0 };
+    let lag = (first + best) as f64 + offset;
+    Some(sample_rate as f64 / lag)
+}
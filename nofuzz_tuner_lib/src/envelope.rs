@@ -0,0 +1,81 @@
+// src/envelope.rs
+//
+// Amplitude envelope of the note currently ringing: the attack peak and an
+// estimate of the decay time constant, assuming the usual exponential decay
+// of a plucked string. Levels are frame RMS values.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use crate::dsp;
+
+// A frame this much louder than the previous one is treated as a new attack
+// (re-plucking the same string doesn't change the note)
+const REATTACK_RATIO: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct NoteEnvelope {
+    // Highest RMS since the attack
+    pub peak: f64,
+    // RMS of the latest frame
+    pub level: f64,
+    // Seconds since the peak
+    pub since_peak: f64,
+    // Time for the level to fall to 1/e, None while still rising
+    pub decay_seconds: Option<f64>,
+}
+
+impl NoteEnvelope {
+    // Level relative to the attack peak, 1.0 at the peak and falling towards
+    // zero as the note dies; handy for fading the needle
+    pub fn relative_level(&self) -> f64 {
+        if self.peak > 0.0 { (self.level / self.peak).min(1.0) } else { 0.0 }
+    }
+
+    // Current level below the attack peak in dB (positive number)
+    pub fn decay_db(&self) -> f64 {
+        let rel = self.relative_level();
+        if rel > 0.0 { -20.0 * rel.log10() } else { f64::INFINITY }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EnvelopeFollower {
+    envelope: Option<NoteEnvelope>,
+}
+
+impl EnvelopeFollower {
+    // Starts a new envelope from the current level, used when the note changes
+    pub(crate) fn restart(&mut self) {
+        if let Some(env) = self.envelope.as_mut() {
+            *env = NoteEnvelope { peak: env.level, level: env.level, since_peak: 0.0, decay_seconds: None };
+        }
+    }
+
+    pub(crate) fn update(&mut self, data: &[f64], frame_seconds: f64) {
+        let level = dsp::rms(data);
+        let attack = NoteEnvelope { peak: level, level, since_peak: 0.0, decay_seconds: None };
+        let env = match self.envelope {
+            Some(prev) if level <= prev.level * REATTACK_RATIO => prev,
+            _ => {
+                self.envelope = Some(attack);
+                return;
+            }
+        };
+        self.envelope = Some(if level >= env.peak {
+            attack
+        } else {
+            let since_peak = env.since_peak + frame_seconds;
+            // level = peak * exp(-t / tau)
+            let drop = (env.peak / level).ln();
+            let decay_seconds = if level > 0.0 && drop > 0.0 { Some(since_peak / drop) } else { env.decay_seconds };
+            NoteEnvelope { peak: env.peak, level, since_peak, decay_seconds }
+        });
+    }
+
+    pub(crate) fn envelope(&self) -> Option<NoteEnvelope> {
+        self.envelope
+    }
+}
@@ -0,0 +1,135 @@
+// src/fft.rs
+//
+// Planning an FFT allocates twiddle tables and picks an algorithm, which is
+// too slow to do per frame. Plans are kept in a small cache keyed by length
+// so changing block size or zero-padding reuses earlier plans. When the cache
+// is full the least recently used plan is dropped.
+
+use std::sync::{Arc, Mutex};
+
+use lazy_static::lazy_static;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+// Enough for a few block sizes, each with and without zero-padding
+pub const DEFAULT_PLAN_CAPACITY: usize = 8;
+
+pub struct FftPlanCache {
+    planner: FftPlanner<f64>,
+    // Most recently used last
+    plans: Vec<(usize, Arc<dyn Fft<f64>>)>,
+    capacity: usize,
+}
+
+impl FftPlanCache {
+    pub fn new(capacity: usize) -> FftPlanCache {
+        FftPlanCache { planner: FftPlanner::new(), plans: vec![], capacity: capacity.max(1) }
+    }
+
+    // Forward FFT plan for `len` points, planned on first use
+    pub fn plan(&mut self, len: usize) -> Arc<dyn Fft<f64>> {
+        if let Some(pos) = self.plans.iter().position(|(l, _)| *l == len) {
+            let entry = self.plans.remove(pos);
+            let fft = entry.1.clone();
+            self.plans.push(entry);
+            return fft;
+        }
+        if self.plans.len() >= self.capacity {
+            self.plans.remove(0);
+        }
+        let fft = self.planner.plan_fft_forward(len);
+        self.plans.push((len, fft.clone()));
+        fft
+    }
+
+    pub fn len(&self) -> usize {
+        self.plans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plans.is_empty()
+    }
+
+    pub fn contains(&self, len: usize) -> bool {
+        self.plans.iter().any(|(l, _)| *l == len)
+    }
+}
+
+impl Default for FftPlanCache {
+    fn default() -> Self {
+        FftPlanCache::new(DEFAULT_PLAN_CAPACITY)
+    }
+}
+
+lazy_static! {
+    static ref PLANS: Mutex<FftPlanCache> = Mutex::new(FftPlanCache::default());
+}
+
+// Plan from the process wide cache, for callers without their own cache
+pub fn shared_plan(len: usize) -> Arc<dyn Fft<f64>> {
+    PLANS.lock().unwrap().plan(len)
+}
+
+// Magnitudes of bins 0..=n/2 of `data` zero-padded to `fft_len`
+pub fn magnitude_spectrum(data: &[f64], fft_len: usize) -> Vec<f64> {
+    let fft_len = fft_len.max(data.len());
+    let mut buffer: Vec<Complex<f64>> = data.iter().map(|&x| Complex::new(x, 0.0)).collect();
+    buffer.resize(fft_len, Complex::new(0.0, 0.0));
+    shared_plan(fft_len).process(&mut buffer);
+    buffer[..fft_len / 2 + 1].iter().map(|c| c.norm()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plans_are_reused_by_length() {
+        let mut cache = FftPlanCache::new(4);
+        let first = cache.plan(1024);
+        let other = cache.plan(2048);
+        assert!(Arc::ptr_eq(&first, &cache.plan(1024)));
+        assert!(!Arc::ptr_eq(&first, &other));
+        assert_eq!(first.len(), 1024);
+        assert_eq!(other.len(), 2048);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn misses_plan_and_cache_new_lengths() {
+        let mut cache = FftPlanCache::new(4);
+        assert!(cache.is_empty());
+        assert!(!cache.contains(512));
+        cache.plan(512);
+        assert!(cache.contains(512));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn least_recently_used_plan_is_evicted() {
+        let mut cache = FftPlanCache::new(3);
+        cache.plan(256);
+        cache.plan(512);
+        cache.plan(1024);
+        // A hit makes 256 the most recently used, leaving 512 the oldest
+        let kept = cache.plan(256);
+        cache.plan(2048);
+        assert_eq!(cache.len(), 3);
+        assert!(!cache.contains(512));
+        assert!(cache.contains(256) && cache.contains(1024) && cache.contains(2048));
+        assert!(Arc::ptr_eq(&kept, &cache.plan(256)));
+
+        cache.plan(4096);
+        assert!(!cache.contains(1024));
+        assert!(cache.contains(256) && cache.contains(2048) && cache.contains(4096));
+    }
+
+    #[test]
+    fn capacity_is_at_least_one() {
+        let mut cache = FftPlanCache::new(0);
+        cache.plan(128);
+        cache.plan(256);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains(256));
+    }
+}
@@ -0,0 +1,545 @@
+// src/filters.rs
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+// Direct form II transposed biquad section. Coefficients are normalised so
+// that a0 == 1.
+#[derive(Debug, Clone)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    pub fn new(b0: f64, b1: f64, b2: f64, a1: f64, a2: f64) -> Biquad {
+        Biquad { b0, b1, b2, a1, a2, z1: 0.0, z2: 0.0 }
+    }
+
+    // Second order high-pass (RBJ cookbook)
+    pub fn highpass(freq: f64, q: f64, sample_rate: usize) -> Biquad {
+        let w0 = 2.0 * std::f64::consts::PI * freq / sample_rate as f64;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let a0 = 1.0 + alpha;
+        Biquad::new(
+            (1.0 + cos) / 2.0 / a0,
+            -(1.0 + cos) / a0,
+            (1.0 + cos) / 2.0 / a0,
+            -2.0 * cos / a0,
+            (1.0 - alpha) / a0,
+        )
+    }
+
+    // Second order low-pass (RBJ cookbook)
+    pub fn lowpass(freq: f64, q: f64, sample_rate: usize) -> Biquad {
+        let w0 = 2.0 * std::f64::consts::PI * freq / sample_rate as f64;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let a0 = 1.0 + alpha;
+        Biquad::new(
+            (1.0 - cos) / 2.0 / a0,
+            (1.0 - cos) / a0,
+            (1.0 - cos) / 2.0 / a0,
+            -2.0 * cos / a0,
+            (1.0 - alpha) / a0,
+        )
+    }
+
+    // Band-pass with 0 dB gain at `freq` (RBJ cookbook)
+    pub fn bandpass(freq: f64, q: f64, sample_rate: usize) -> Biquad {
+        let w0 = 2.0 * std::f64::consts::PI * freq / sample_rate as f64;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let a0 = 1.0 + alpha;
+        Biquad::new(alpha / a0, 0.0, -alpha / a0, -2.0 * cos / a0, (1.0 - alpha) / a0)
+    }
+
+    // Notch removing `freq`, e.g. mains hum (RBJ cookbook)
+    pub fn notch(freq: f64, q: f64, sample_rate: usize) -> Biquad {
+        let w0 = 2.0 * std::f64::consts::PI * freq / sample_rate as f64;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let a0 = 1.0 + alpha;
+        Biquad::new(1.0 / a0, -2.0 * cos / a0, 1.0 / a0, -2.0 * cos / a0, (1.0 - alpha) / a0)
+    }
+
+    // Peaking EQ boosting (gain_db > 0) or cutting around `freq` (RBJ cookbook)
+    pub fn peaking(freq: f64, q: f64, gain_db: f64, sample_rate: usize) -> Biquad {
+        let w0 = 2.0 * std::f64::consts::PI * freq / sample_rate as f64;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let a = 10f64.powf(gain_db / 40.0);
+        let a0 = 1.0 + alpha / a;
+        Biquad::new(
+            (1.0 + alpha * a) / a0,
+            -2.0 * cos / a0,
+            (1.0 - alpha * a) / a0,
+            -2.0 * cos / a0,
+            (1.0 - alpha / a) / a0,
+        )
+    }
+
+    pub fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+
+    pub fn process_frame(&mut self, data: &mut [f64]) {
+        for x in data.iter_mut() {
+            *x = self.process(*x);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
+
+// One-pole DC blocker, y[n] = x[n] - x[n-1] + r * y[n-1]. Some USB
+// interfaces add a constant offset, which counts as level in the gates and
+// tilts the detectors' difference and correlation functions.
+const DC_BLOCKER_CUTOFF_HZ: f64 = 5.0;
+
+#[derive(Debug, Clone)]
+pub struct DcBlocker {
+    r: f64,
+    x1: f64,
+    y1: f64,
+}
+
+impl DcBlocker {
+    pub fn new(sample_rate: usize) -> DcBlocker {
+        let r = (-2.0 * std::f64::consts::PI * DC_BLOCKER_CUTOFF_HZ / sample_rate as f64).exp();
+        DcBlocker { r, x1: 0.0, y1: 0.0 }
+    }
+
+    pub fn process_frame(&mut self, data: &mut [f64]) {
+        for x in data.iter_mut() {
+            let y = *x - self.x1 + self.r * self.y1;
+            self.x1 = *x;
+            self.y1 = y;
+            *x = y;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.x1 = 0.0;
+        self.y1 = 0.0;
+    }
+}
+
+// Linear-phase FIR filter, a Blackman windowed sinc. Unlike a biquad it
+// delays every frequency by the same (taps - 1) / 2 samples, so the shape of
+// the waveform survives; the cost is `taps` multiplies per sample.
+#[derive(Debug, Clone)]
+pub struct Fir {
+    coefficients: Vec<f64>,
+    // Last taps - 1 input samples
+    history: Vec<f64>,
+}
+
+impl Fir {
+    // `taps` must be odd and at least 3, or the filter loses its linear
+    // phase. `freq` is the -6 dB point; the transition around it is about
+    // 5.5 * sample_rate / taps wide.
+    pub fn lowpass(freq: f64, taps: usize, sample_rate: usize) -> Result<Fir, String> {
+        check_fir_taps(taps)?;
+        let cutoff = freq / sample_rate as f64;
+        let centre = (taps / 2) as f64;
+        let mut coefficients: Vec<f64> = (0..taps)
+            .map(|i| {
+                let n = i as f64 - centre;
+                let sinc = if n == 0.0 { 2.0 * cutoff } else { (2.0 * std::f64::consts::PI * cutoff * n).sin() / (std::f64::consts::PI * n) };
+                sinc * blackman(i, taps)
+            })
+            .collect();
+        // Unity gain at DC
+        let sum: f64 = coefficients.iter().sum();
+        coefficients.iter_mut().for_each(|c| *c /= sum);
+        Fir::new(coefficients)
+    }
+
+    // The lowpass subtracted from the delayed input
+    pub fn highpass(freq: f64, taps: usize, sample_rate: usize) -> Result<Fir, String> {
+        let mut fir = Fir::lowpass(freq, taps, sample_rate)?;
+        fir.coefficients.iter_mut().for_each(|c| *c = -*c);
+        fir.coefficients[taps / 2] += 1.0;
+        Ok(fir)
+    }
+
+    pub fn new(coefficients: Vec<f64>) -> Result<Fir, String> {
+        if coefficients.is_empty() {
+            return Err("FIR filter needs at least one coefficient".to_string());
+        }
+        let history = vec![0.0; coefficients.len() - 1];
+        Ok(Fir { coefficients, history })
+    }
+
+    // Group delay in samples
+    pub fn delay(&self) -> f64 {
+        self.history.len() as f64 / 2.0
+    }
+
+    pub fn process_frame(&mut self, data: &mut [f64]) {
+        let mut input = std::mem::take(&mut self.history);
+        input.extend_from_slice(data);
+        let taps = self.coefficients.len();
+        for (i, y) in data.iter_mut().enumerate() {
+            // input[i + taps - 1] is the current sample
+            *y = self.coefficients.iter().zip(input[i..i + taps].iter().rev()).map(|(c, x)| c * x).sum();
+        }
+        input.drain(..input.len() + 1 - taps);
+        self.history = input;
+    }
+
+    pub fn reset(&mut self) {
+        self.history.iter_mut().for_each(|x| *x = 0.0);
+    }
+}
+
+fn check_fir_taps(taps: usize) -> Result<(), String> {
+    if taps < 3 || taps.is_multiple_of(2) || taps > MAX_FIR_TAPS {
+        return Err(format!("FIR taps must be odd, from 3 to {}, got {}", MAX_FIR_TAPS, taps));
+    }
+    Ok(())
+}
+
+fn blackman(i: usize, len: usize) -> f64 {
+    if len < 2 {
+        return 1.0;
+    }
+    let x = 2.0 * std::f64::consts::PI * i as f64 / (len - 1) as f64;
+    0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos()
+}
+
+// One stage of a FilterChain, described independently of the sample rate.
+// Frequencies in Hz. The Butterworth stages are cascades of order / 2
+// biquads, falling off at 6 dB per octave per order: a 4th order highpass
+// takes out rumble a 2nd order one leaves near the low strings. The FIR
+// stages are linear phase, see Fir; more taps give a sharper edge.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum FilterSpec {
+    Highpass { fc: f64, q: f64 },
+    Lowpass { fc: f64, q: f64 },
+    Bandpass { fc: f64, q: f64 },
+    Notch { fc: f64, q: f64 },
+    Peaking { fc: f64, q: f64, gain_db: f64 },
+    ButterworthHighpass { fc: f64, order: usize },
+    ButterworthLowpass { fc: f64, order: usize },
+    FirHighpass { fc: f64, taps: usize },
+    FirLowpass { fc: f64, taps: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct FilterSpecs(pub Vec<FilterSpec>);
+
+// Highest Butterworth order; even orders only
+const MAX_BUTTERWORTH_ORDER: usize = 8;
+// Largest FIR length; odd lengths only
+const MAX_FIR_TAPS: usize = 4095;
+
+// A designed FilterChain stage
+#[derive(Debug, Clone)]
+enum Section {
+    Biquads(Vec<Biquad>),
+    Fir(Fir),
+}
+
+impl Section {
+    fn process_frame(&mut self, data: &mut [f64]) {
+        match self {
+            Section::Biquads(biquads) => biquads.iter_mut().for_each(|b| b.process_frame(data)),
+            Section::Fir(fir) => fir.process_frame(data),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Section::Biquads(biquads) => biquads.iter_mut().for_each(Biquad::reset),
+            Section::Fir(fir) => fir.reset(),
+        }
+    }
+
+    // Group delay in samples of the linear-phase sections; biquads are left
+    // out as their delay varies with frequency and is small above cutoff
+    fn delay(&self) -> f64 {
+        match self {
+            Section::Biquads(_) => 0.0,
+            Section::Fir(fir) => fir.delay(),
+        }
+    }
+}
+
+impl FilterSpec {
+    fn design(&self, sample_rate: usize) -> Result<Section, String> {
+        let biquad = |b: Biquad| Section::Biquads(vec![b]);
+        Ok(match *self {
+            FilterSpec::Highpass { fc, q } => biquad(Biquad::highpass(fc, q, sample_rate)),
+            FilterSpec::Lowpass { fc, q } => biquad(Biquad::lowpass(fc, q, sample_rate)),
+            FilterSpec::Bandpass { fc, q } => biquad(Biquad::bandpass(fc, q, sample_rate)),
+            FilterSpec::Notch { fc, q } => biquad(Biquad::notch(fc, q, sample_rate)),
+            FilterSpec::Peaking { fc, q, gain_db } => biquad(Biquad::peaking(fc, q, gain_db, sample_rate)),
+            FilterSpec::ButterworthHighpass { fc, order } => {
+                Section::Biquads(butterworth_qs(order).map(|q| Biquad::highpass(fc, q, sample_rate)).collect())
+            }
+            FilterSpec::ButterworthLowpass { fc, order } => {
+                Section::Biquads(butterworth_qs(order).map(|q| Biquad::lowpass(fc, q, sample_rate)).collect())
+            }
+            FilterSpec::FirHighpass { fc, taps } => Section::Fir(Fir::highpass(fc, taps, sample_rate)?),
+            FilterSpec::FirLowpass { fc, taps } => Section::Fir(Fir::lowpass(fc, taps, sample_rate)?),
+        })
+    }
+
+    fn check(&self, sample_rate: usize) -> Result<(), String> {
+        let (fc, q) = match *self {
+            FilterSpec::Highpass { fc, q }
+            | FilterSpec::Lowpass { fc, q }
+            | FilterSpec::Bandpass { fc, q }
+            | FilterSpec::Notch { fc, q }
+            | FilterSpec::Peaking { fc, q, .. } => (fc, Some(q)),
+            FilterSpec::ButterworthHighpass { fc, order } | FilterSpec::ButterworthLowpass { fc, order } => {
+                if order == 0 || !order.is_multiple_of(2) || order > MAX_BUTTERWORTH_ORDER {
+                    return Err(format!("Butterworth order must be 2, 4, 6 or 8, got {}", order));
+                }
+                (fc, None)
+            }
+            FilterSpec::FirHighpass { fc, taps } | FilterSpec::FirLowpass { fc, taps } => {
+                check_fir_taps(taps)?;
+                (fc, None)
+            }
+        };
+        if !(fc > 0.0 && fc < sample_rate as f64 / 2.0) {
+            return Err(format!("filter frequency {} Hz must be between 0 and {} Hz", fc, sample_rate / 2));
+        }
+        if let Some(q) = q.filter(|q| !(*q > 0.0 && q.is_finite())) {
+            return Err(format!("filter Q must be positive, got {}", q));
+        }
+        Ok(())
+    }
+}
+
+// Q of each biquad in a Butterworth cascade of even `order`, from the
+// angles of its pole pairs
+fn butterworth_qs(order: usize) -> impl Iterator<Item = f64> {
+    (1..=order / 2).map(move |k| {
+        let angle = (2 * k - 1) as f64 * std::f64::consts::PI / (2 * order) as f64;
+        1.0 / (2.0 * angle.cos())
+    })
+}
+
+// A FilterChain entry as listed to callers
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct FilterStage {
+    pub spec: FilterSpec,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct FilterStages(pub Vec<FilterStage>);
+
+// Filters run in order over every frame before analysis, built from specs
+// so they can be redesigned for a new sample rate:
+//
+//     FilterChain::new(48000)
+//         .with(FilterSpec::Highpass { fc: 70.0, q: FRAC_1_SQRT_2 })?
+//         .with(FilterSpec::Notch { fc: 50.0, q: 10.0 })?
+#[derive(Debug, Clone)]
+pub struct FilterChain {
+    sample_rate: usize,
+    stages: Vec<FilterStage>,
+    sections: Vec<Section>,
+}
+
+impl FilterChain {
+    pub fn new(sample_rate: usize) -> FilterChain {
+        FilterChain { sample_rate, stages: vec![], sections: vec![] }
+    }
+
+    pub fn from_specs(specs: &[FilterSpec], sample_rate: usize) -> Result<FilterChain, String> {
+        specs.iter().try_fold(FilterChain::new(sample_rate), |chain, spec| chain.with(*spec))
+    }
+
+    // Appends a stage; fails for a frequency outside 0..Nyquist or Q <= 0
+    pub fn with(mut self, spec: FilterSpec) -> Result<FilterChain, String> {
+        self.push(spec)?;
+        Ok(self)
+    }
+
+    pub fn push(&mut self, spec: FilterSpec) -> Result<(), String> {
+        spec.check(self.sample_rate)?;
+        self.sections.push(spec.design(self.sample_rate)?);
+        self.stages.push(FilterStage { spec, enabled: true });
+        Ok(())
+    }
+
+    // Takes out the stage at `index`; later stages move down one
+    pub fn remove(&mut self, index: usize) -> Result<FilterSpec, String> {
+        self.check_index(index)?;
+        self.sections.remove(index);
+        Ok(self.stages.remove(index).spec)
+    }
+
+    pub fn clear(&mut self) {
+        self.stages.clear();
+        self.sections.clear();
+    }
+
+    // A disabled stage keeps its place and is skipped. It starts from rest
+    // when enabled again rather than ringing out stale state.
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) -> Result<(), String> {
+        self.check_index(index)?;
+        if enabled && !self.stages[index].enabled {
+            self.sections[index].reset();
+        }
+        self.stages[index].enabled = enabled;
+        Ok(())
+    }
+
+    pub fn stages(&self) -> &[FilterStage] {
+        &self.stages
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    // Whether any stage would touch the signal
+    pub fn is_active(&self) -> bool {
+        self.stages.iter().any(|stage| stage.enabled)
+    }
+
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    // Delay of the enabled FIR stages in seconds
+    pub fn latency(&self) -> f64 {
+        let samples: f64 = self
+            .sections
+            .iter()
+            .zip(self.stages.iter())
+            .filter(|(_, stage)| stage.enabled)
+            .map(|(section, _)| section.delay())
+            .sum();
+        samples / self.sample_rate as f64
+    }
+
+    // Redesigns every stage for the new rate, dropping any whose frequency
+    // is no longer below Nyquist
+    pub fn set_sample_rate(&mut self, sample_rate: usize) {
+        let stages = std::mem::take(&mut self.stages);
+        *self = FilterChain::new(sample_rate);
+        for stage in stages {
+            if self.push(stage.spec).is_ok() {
+                let last = self.stages.len() - 1;
+                self.stages[last].enabled = stage.enabled;
+            }
+        }
+    }
+
+    pub fn process_frame(&mut self, data: &mut [f64]) {
+        for (section, stage) in self.sections.iter_mut().zip(self.stages.iter()) {
+            if stage.enabled {
+                section.process_frame(data);
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.sections.iter_mut().for_each(Section::reset);
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), String> {
+        if index >= self.stages.len() {
+            return Err(format!("no filter {}, the chain has {}", index, self.stages.len()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: usize = 44100;
+
+    // Steady-state amplitude of a sine at `freq` through `fir`
+    fn gain(fir: &mut Fir, freq: f64) -> f64 {
+        let mut data: Vec<f64> =
+            (0..8192).map(|i| (2.0 * std::f64::consts::PI * freq * i as f64 / SAMPLE_RATE as f64).sin()).collect();
+        fir.process_frame(&mut data);
+        data[4096..].iter().fold(0.0_f64, |peak, x| peak.max(x.abs()))
+    }
+
+    #[test]
+    fn fir_lowpass_passes_below_cutoff_and_stops_above() {
+        let mut fir = Fir::lowpass(1000.0, 255, SAMPLE_RATE).unwrap();
+        assert!((gain(&mut fir, 200.0) - 1.0).abs() < 0.01);
+        fir.reset();
+        assert!(gain(&mut fir, 3000.0) < 0.001);
+    }
+
+    #[test]
+    fn fir_highpass_stops_below_cutoff_and_passes_above() {
+        let mut fir = Fir::highpass(1000.0, 255, SAMPLE_RATE).unwrap();
+        assert!(gain(&mut fir, 200.0) < 0.001);
+        fir.reset();
+        assert!((gain(&mut fir, 3000.0) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn fir_impulse_peaks_at_group_delay() {
+        for taps in [3, 31, 255] {
+            let mut fir = Fir::lowpass(2000.0, taps, SAMPLE_RATE).unwrap();
+            assert_eq!(fir.delay(), ((taps - 1) / 2) as f64);
+            let mut impulse = vec![0.0; taps + 16];
+            impulse[0] = 1.0;
+            fir.process_frame(&mut impulse);
+            let peak = (0..impulse.len()).max_by(|&a, &b| impulse[a].total_cmp(&impulse[b])).unwrap();
+            assert_eq!(peak, (taps - 1) / 2);
+            // Linear phase: the response is symmetric about the delay
+            for i in 0..taps {
+                assert!((impulse[i] - impulse[taps - 1 - i]).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn fir_keeps_state_across_frames() {
+        let mut whole = Fir::lowpass(1000.0, 63, SAMPLE_RATE).unwrap();
+        let mut split = whole.clone();
+        let signal: Vec<f64> = (0..1000).map(|i| ((i * 7919) % 101) as f64 / 50.0 - 1.0).collect();
+        let mut expected = signal.clone();
+        whole.process_frame(&mut expected);
+        let mut actual = signal;
+        for frame in actual.chunks_mut(10) {
+            split.process_frame(frame);
+        }
+        for (a, b) in actual.iter().zip(expected.iter()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn fir_rejects_bad_taps() {
+        for taps in [0, 1, 2, 64, MAX_FIR_TAPS + 2] {
+            assert!(Fir::lowpass(1000.0, taps, SAMPLE_RATE).is_err(), "taps {}", taps);
+            assert!(Fir::highpass(1000.0, taps, SAMPLE_RATE).is_err(), "taps {}", taps);
+        }
+        assert!(Fir::new(vec![]).is_err());
+    }
+}
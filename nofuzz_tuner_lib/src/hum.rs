@@ -0,0 +1,127 @@
+// src/hum.rs
+//
+// Mains hum analysis. Measures the 50 Hz and 60 Hz families (fundamental plus
+// harmonics) against the total signal power and suggests which notches to
+// enable. Telling 50 Hz from 60 Hz needs a frame of at least ~0.2 s, the
+// longer the better; feed it a stretch of input with the strings muted.
+// HumProbe does this over the first second of a stream.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::dsp;
+use crate::filters::FilterSpec;
+
+const MAINS_FREQS: [f64; 2] = [50.0, 60.0];
+const HUM_HARMONICS: usize = 4;
+// Harmonics weaker than this share of the strongest one don't need a notch
+const NOTCH_SUGGEST_RATIO: f64 = 0.1;
+// Notch width, the same for every harmonic so the 100 Hz notch stays clear
+// of G2 at 98 Hz
+const HUM_NOTCH_BANDWIDTH_HZ: f64 = 2.0;
+// Input analysed by HumProbe
+const HUM_PROBE_SECONDS: f64 = 1.0;
+// Hum weaker than this share of the signal power is left alone
+const HUM_PROBE_MIN_RATIO: f64 = 0.01;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct HumReport {
+    // 50 or 60, None when there's no signal at all
+    pub mains_hz: Option<f64>,
+    // Hum power as a share of total signal power, 0..1
+    pub hum_ratio: f64,
+    // 0 (clean) .. 100 (signal is all hum)
+    pub score: f64,
+    pub suggested_notches: Vec<f64>,
+}
+
+impl HumReport {
+    // Narrow notches for the suggested frequencies, none for a clean signal
+    pub fn notch_filters(&self) -> Vec<FilterSpec> {
+        self.suggested_notches.iter().map(|fc| FilterSpec::Notch { fc: *fc, q: fc / HUM_NOTCH_BANDWIDTH_HZ }).collect()
+    }
+}
+
+fn family_powers(data: &[f64], sample_rate: usize, mains: f64) -> Vec<(f64, f64)> {
+    (1..=HUM_HARMONICS)
+        .map(|h| {
+            let freq = mains * h as f64;
+            // Goertzel power is amplitude^2 / 4; a sine's mean square is amplitude^2 / 2
+            (freq, 2.0 * dsp::goertzel_power(data, freq, sample_rate))
+        })
+        .collect()
+}
+
+pub fn analyze_hum(data: &[f64], sample_rate: usize) -> HumReport {
+    let total = dsp::rms(data).powi(2);
+    if total <= 0.0 {
+        return HumReport { mains_hz: None, hum_ratio: 0.0, score: 0.0, suggested_notches: vec![] };
+    }
+
+    let mut mains = MAINS_FREQS[0];
+    let mut powers = family_powers(data, sample_rate, mains);
+    let mut hum_power: f64 = powers.iter().map(|(_, p)| p).sum();
+    for candidate in &MAINS_FREQS[1..] {
+        let candidate_powers = family_powers(data, sample_rate, *candidate);
+        let sum: f64 = candidate_powers.iter().map(|(_, p)| p).sum();
+        if sum > hum_power {
+            mains = *candidate;
+            powers = candidate_powers;
+            hum_power = sum;
+        }
+    }
+
+    let hum_ratio = (hum_power / total).min(1.0);
+    let strongest = powers.iter().map(|(_, p)| *p).fold(0.0, f64::max);
+    let suggested_notches = powers
+        .iter()
+        .filter(|(_, p)| strongest > 0.0 && *p >= strongest * NOTCH_SUGGEST_RATIO)
+        .map(|(f, _)| *f)
+        .collect();
+
+    HumReport {
+        mains_hz: Some(mains),
+        hum_ratio,
+        score: hum_ratio * 100.0,
+        suggested_notches,
+    }
+}
+
+// Collects the start of a stream and analyses it once there is enough
+#[derive(Debug, Clone)]
+pub(crate) struct HumProbe {
+    samples: Vec<f64>,
+    needed: usize,
+}
+
+impl HumProbe {
+    pub(crate) fn new(sample_rate: usize) -> HumProbe {
+        let needed = (HUM_PROBE_SECONDS * sample_rate as f64) as usize;
+        HumProbe { samples: Vec::with_capacity(needed), needed }
+    }
+
+    // The report once enough input has been seen. The notches it suggests
+    // are dropped when the hum is negligible.
+    pub(crate) fn push(&mut self, data: &[f64], sample_rate: usize) -> Option<HumReport> {
+        let take = (self.needed - self.samples.len()).min(data.len());
+        self.samples.extend_from_slice(&data[..take]);
+        if self.samples.len() < self.needed {
+            return None;
+        }
+        let mut report = analyze_hum(&self.samples, sample_rate);
+        if report.hum_ratio < HUM_PROBE_MIN_RATIO {
+            report.suggested_notches.clear();
+        }
+        Some(report)
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn analyze_hum_js(data: &[f64], sample_rate: usize) -> HumReport {
+    analyze_hum(data, sample_rate)
+}
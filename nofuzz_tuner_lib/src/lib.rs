@@ -1,20 +1,107 @@
 // src/lib.rs
 
+#[cfg(feature = "mcleod")]
 use pitch_detection::detector::mcleod::McLeodDetector;
+#[cfg(feature = "mcleod")]
 use pitch_detection::detector::PitchDetector;
 
-use audioviz::spectrum::{config::{StreamConfig as StreamConfig2, ProcessorConfig, VolumeNormalisation, PositionNormalisation, Interpolation}, stream::Stream};
+#[cfg(feature = "fft-detector")]
+use rustfft::num_complex::Complex;
 
 use std::collections::HashMap;
-use lazy_static::lazy_static;
 
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
 
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
+#[cfg(all(feature = "wasm", feature = "yin"))]
 use js_sys::Float64Array;
+#[cfg(feature = "wasm")]
 use console_error_panic_hook;
 
+mod ab_test;
+mod acf;
+mod agc;
+mod calibration;
+pub mod dsp;
+mod double_stop;
+mod envelope;
+pub mod fft;
+pub mod filters;
+mod hum;
+mod load;
+mod loudness;
+mod monitor;
+#[cfg(feature = "neural")]
+mod neural;
+mod notes;
+mod onset;
+mod poly;
+mod presets;
+mod probe;
+mod resample;
+mod score;
+mod segment;
+mod state;
+mod string_id;
+mod temperament;
+#[cfg(test)]
+mod test_util;
+mod tracker;
+mod tunings;
+mod vibrato;
+mod warnings;
+mod zcr;
+pub mod wav;
 
+pub use ab_test::{AbTest, AbTestConfig, DEFAULT_AB_GAP_SECONDS};
+pub use acf::AcfPitchDetector;
+pub use agc::{Agc, DEFAULT_AGC_TARGET_RMS};
+pub use calibration::apply_ppm;
+pub use double_stop::{detect_double_stop, DoubleStop, IntervalReference};
+pub use envelope::NoteEnvelope;
+pub use hum::{analyze_hum, HumReport};
+#[cfg(feature = "neural")]
+pub use neural::NeuralPitchDetector;
+pub use monitor::{
+    is_dominant_pitch, monitor_score, monitor_strings, relative_band_energy, string_band_energies, StringMonitor,
+    StringStatus,
+};
+#[cfg(feature = "wasm")]
+pub use monitor::{monitor_score_js, monitor_strings_js, relative_band_energy_js};
+#[cfg(feature = "yin")]
+pub(crate) use monitor::band_energy;
+pub(crate) use monitor::probe_string;
+pub use loudness::{loudness, KWeighting, NoiseFloor, AUTO_GATE_MAX_LUFS, AUTO_GATE_MIN_LUFS, SILENCE_LUFS};
+pub use temperament::{inharmonic_stretch_cents, Temperament};
+pub use score::{aggregate_score, TuningScore, PRACTICALLY_IN_TUNE_CENTS};
+pub use segment::{NoteEvent, NoteEvents, NoteTracker};
+pub use state::TunerState;
+pub use poly::{detect_polyphonic, PolyPitchResult, PolyString};
+pub use resample::Resampler;
+pub use probe::{probe_best_detector, DetectorProbe, DetectorScore, DetectorScores};
+pub use notes::{
+    cents_between, freq_to_midi, freq_to_note, freq_to_period_samples, midi_to_freq, midi_to_name, note_to_freq,
+    note_to_midi, pitch_class, Note, NoteNotation, NoteSpelling, A4_HZ,
+};
+pub use presets::{fit_freq_max, min_frame_len, validate_range, InTuneSettings, InputKind, InstrumentProfile, RangePreset, RangeSettings};
+pub use tracker::{
+    MeasurementSummary, PitchResult, PitchTracker, ResultView, SignalState, TrackerEvent, TrackerEvents, TunerSnapshot,
+};
+pub use tunings::{
+    compare_tunings, custom_tunings, find_string_and_distance,
+    guitar_strings, in_string_range, list_tunings, shifted_tuning, tuning_notes, maybe_find_string_and_distance, register_tuning, GuitarStrings,
+    OctavePolicy, StringInfo, StringMatch, TuningComparison, TuningComparisons, TuningHandle, TuneDirection, TuningNames, TuningTo, Tunings,
+    DEFAULT_TUNING,
+};
+pub use vibrato::{Vibrato, VibratoAnalyzer};
+pub use warnings::{TunerWarning, TunerWarnings, WarningChannel, WarningKind};
+pub use zcr::ZeroCrossingPitchDetector;
+
+
+#[cfg(feature = "wasm")]
 #[wasm_bindgen(start)]
 pub fn start() {
     // Set the panic hook for better error messages in the browser console
@@ -22,26 +109,15 @@ pub fn start() {
 }
 
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub fn greet(name: &str) -> String {
     format!("Hello, {}!", name)
 }
 
-// Guitar string frequencies cheat-sheet:
-lazy_static! {
-    static ref GUITAR_STRINGS: HashMap<String, f64> = {
-        let mut m = HashMap::new();
-        m.insert("E2".to_string(), 82.41);
-        m.insert("A2".to_string(), 110.00);
-        m.insert("D3".to_string(), 146.83);
-        m.insert("G3".to_string(), 196.00);
-        m.insert("B3".to_string(), 246.94);
-        m.insert("E4".to_string(), 329.63);
-        m
-    };
-}
-
+// Types crossing the wasm boundary derive Tsify (with the wasm feature) so
+// wasm-pack emits their TypeScript definitions next to the generated bindings.
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
 pub struct Config {
     pub device_id: usize,
     pub pitch_detection: String,
@@ -51,45 +127,582 @@ pub struct Config {
     pub freq_max: f64,
     // Mcleod parameters
     pub power_threshold: f64, 
-    pub clarity_threshold: f64
+    pub clarity_threshold: f64,
+    // Monitor all strings at once instead of detecting a single pitch
+    #[serde(default)]
+    pub monitor: bool,
+    #[serde(default = "default_tuning")]
+    pub tuning: String,
+    // YAML or JSON file with custom tunings, loaded before `tuning` is looked up
+    #[serde(default)]
+    pub tunings_file: Option<String>,
+    // guitar, bass, ukulele, violin, viola, cello, mandolin, banjo or voice:
+    // sets range, frame length, in-tune window and input filters, and the
+    // tuning unless another one than standard is given
+    #[serde(default)]
+    pub instrument: Option<String>,
+    // Range preset (low_bass, guitar, high_strings, voice, full_chromatic).
+    // When set it overrides freq_min/freq_max and the frame length.
+    #[serde(default)]
+    pub preset: Option<String>,
+    // Debug: mix a reference tone of this frequency (Hz) into the analysis
+    #[serde(default)]
+    pub inject_tone: Option<f64>,
+    // Debug: write the audio the detector analyses to this WAV file
+    #[serde(default)]
+    pub record_wav: Option<String>,
+    // Render a recorded phrase through two filter settings to compare by
+    // ear, write the WAVs and exit, see AbTest
+    #[serde(default)]
+    pub ab_test: Option<AbTestConfig>,
+    // Frames quieter than this (LUFS, e.g. -50.0) are ignored
+    #[serde(default)]
+    pub gate_lufs: Option<f64>,
+    // microphone, piezo, magnetic or line_di: picks filters and gating
+    #[serde(default)]
+    pub input_kind: Option<String>,
+    // Filters run before analysis in place of the input_kind's, in order,
+    // e.g. [Highpass: {fc: 70.0, q: 0.707}, Notch: {fc: 50.0, q: 10.0}]
+    #[serde(default)]
+    pub filters: Option<Vec<filters::FilterSpec>>,
+    // Measure mains hum over the first second and notch it out
+    #[serde(default)]
+    pub auto_hum_notch: bool,
+    // Remove a DC offset from the input, as some USB interfaces add one
+    #[serde(default)]
+    pub dc_blocker: bool,
+    // Bring quiet input up (and loud input down) to about this RMS before
+    // detection, e.g. 0.1 for a distant phone mic; unset leaves the level
+    #[serde(default)]
+    pub agc_target_rms: Option<f64>,
+    // Derive the gate from the measured noise floor (gate_lufs overrides it)
+    #[serde(default)]
+    pub auto_gate: bool,
+    // Stop detecting once a note has decayed this many dB below its attack
+    #[serde(default)]
+    pub decay_gate_db: Option<f64>,
+    // Report cents from this many ms after the attack, once the pluck settled
+    #[serde(default)]
+    pub settle_ms: Option<f64>,
+    // Realtime priority for the CLI analysis thread (needs the realtime feature)
+    #[serde(default)]
+    pub realtime: bool,
+    // Analysis window and smoothing sized to the note being tracked, longer
+    // for low strings and shorter for high ones
+    #[serde(default)]
+    pub multi_resolution: bool,
+    // Or only double the analysis window while tracking notes below 100 Hz
+    #[serde(default)]
+    pub dynamic_block_size: bool,
+    // Resample the input to this rate before detection, e.g. 48000 on a
+    // 96 kHz interface; unset analyses at the device rate
+    #[serde(default)]
+    pub analysis_rate: Option<usize>,
+    // Smooth with a Kalman filter that skips outliers instead of the moving
+    // average; noises in cents squared, defaults 0.25 and 4
+    #[serde(default)]
+    pub kalman: bool,
+    #[serde(default)]
+    pub kalman_process_noise: Option<f64>,
+    #[serde(default)]
+    pub kalman_measurement_noise: Option<f64>,
+    // Smooth with the median of this many frames instead, e.g. 5
+    #[serde(default)]
+    pub median_frames: Option<usize>,
+    // Follow an identified string with a narrowband scan instead of full detection
+    #[serde(default)]
+    pub narrowband: bool,
+    // Override the preset's in-tune window (cents)
+    #[serde(default)]
+    pub in_tune_cents: Option<f64>,
+    #[serde(default)]
+    pub in_tune_hysteresis_cents: Option<f64>,
+    // Sample clock correction measured with a reference tone
+    #[serde(default)]
+    pub calibration_ppm: Option<f64>,
+    // Intended offset in cents per string name, e.g. for a compensated nut
+    #[serde(default)]
+    pub string_offsets: Option<HashMap<String, f64>>,
+    // Name notes with "sharps" or "flats"; unset keeps each tuning's names
+    #[serde(default)]
+    pub note_spelling: Option<String>,
+    // Labels in "scientific" (default), "solfege" or "helmholtz" notation
+    #[serde(default)]
+    pub note_notation: Option<String>,
+    // equal, just or meantone, over temperament_tonic (e.g. "G")
+    #[serde(default)]
+    pub temperament: Option<String>,
+    #[serde(default)]
+    pub temperament_tonic: Option<String>,
+    // exact, pitch_class or snap_to_string
+    #[serde(default)]
+    pub octave_policy: Option<String>,
+    // Send notes to a JACK MIDI port (CLI built with the jack-midi feature)
+    #[serde(default)]
+    pub jack_midi: bool,
+    // Note label hysteresis: margin in cents and frames a new note must win
+    #[serde(default)]
+    pub note_hysteresis_cents: Option<f64>,
+    #[serde(default)]
+    pub note_hysteresis_frames: Option<usize>,
+    // Fractional-lag refinement of every detection (sub-cent precision)
+    #[serde(default)]
+    pub refine_lag: bool,
+    // YIN: check up to octave_guard_octaves (default 1) below each pitch for
+    // a fundamental YIN skipped, e.g. E2 read as E3
+    #[serde(default)]
+    pub octave_guard: bool,
+    #[serde(default)]
+    pub octave_guard_octaves: Option<u32>,
+    // YIN: adapt the threshold per frame to the SNR between threshold_min
+    // (noisy frames) and threshold_max (clean ones), defaults 0.05 and 0.2
+    #[serde(default)]
+    pub adaptive_threshold: bool,
+    #[serde(default)]
+    pub threshold_min: Option<f64>,
+    #[serde(default)]
+    pub threshold_max: Option<f64>,
+    // YIN: when no pitch is found, measure clean notes above 200 Hz from
+    // their zero crossings
+    #[serde(default)]
+    pub zero_crossing_fallback: bool,
+    // FFT detector: FFT length independent of the frame length, e.g. 8192,
+    // with fft_accumulate analysing the latest fft_len / 2 samples
+    #[serde(default)]
+    pub fft_len: Option<usize>,
+    #[serde(default)]
+    pub fft_accumulate: bool,
+    // Hold back readings for this long after each pluck, e.g. 50
+    #[serde(default)]
+    pub onset_hold_ms: Option<f64>,
+    // Move detections an octave when the frame's partials contradict them
+    // (B3 or E4 reported an octave low), for any detector
+    #[serde(default)]
+    pub octave_correction: bool,
+    // Drop readings quieter than another string's band, taken for a string
+    // ringing along with the one being played
+    #[serde(default)]
+    pub dominant_pitch_gate: bool,
+    // ONNX model for pitch_detection: neural (built with the neural feature)
+    #[serde(default)]
+    pub neural_model: Option<String>,
+}
+
+fn default_tuning() -> String {
+    DEFAULT_TUNING.to_string()
+}
+
+impl Config {
+    // freq_max to detect with: unless a preset is set it is raised to reach
+    // the highest string of the tuning, see fit_freq_max
+    pub fn fitted_freq_max(&self, sample_rate: usize) -> f64 {
+        match (&self.preset, TuningHandle::resolve(&self.tuning)) {
+            (None, Ok(tuning)) => fit_freq_max(&tuning, self.freq_max, sample_rate),
+            _ => self.freq_max,
+        }
+    }
 }
 
 pub trait PitchFindTrait: Send + Sync  {
     fn maybe_find_pitch(&mut self, data: &[f64]) -> Option<f64>;
+
+    // Frame length callers should send when they have the choice.
+    fn preferred_frame_len(&self) -> usize;
+
+    // Whether a frame of this length can be analysed at all.
+    fn accepts_frame_len(&self, len: usize) -> bool {
+        len >= self.preferred_frame_len()
+    }
+
+    // Re-plans for a new input rate, e.g. after an audio device switch.
+    // Detectors that can't do that report it and need to be recreated.
+    fn set_sample_rate(&mut self, sample_rate: usize) -> Result<(), String> {
+        let _ = sample_rate;
+        Err("this detector can't change its sample rate, create a new one".to_string())
+    }
+
+    // Raises freq_max when a string of the tuning sits above it, see
+    // fit_freq_max. Detectors without an upper bound have nothing to do.
+    fn fit_to_tuning(&mut self, tuning: &TuningHandle) {
+        let _ = tuning;
+    }
+
+    // The detector's own confidence in its last pitch, 0..1, if it has one
+    // (McLeod's clarity). Callers fall back to dsp::periodicity.
+    fn last_confidence(&self) -> Option<f64> {
+        None
+    }
+}
+
+// Checks a frame before it is handed to a detector. Returns a message
+// suitable for showing to the caller when the frame can't be analysed.
+pub fn validate_frame(data: &[f64], required_len: usize) -> Result<(), String> {
+    if data.is_empty() {
+        return Err("empty frame: expected at least one sample".to_string());
+    }
+    if data.len() < required_len {
+        return Err(format!(
+            "frame too short: got {} samples, need at least {}",
+            data.len(),
+            required_len
+        ));
+    }
+    if let Some(i) = data.iter().position(|x| !x.is_finite()) {
+        return Err(format!("non-finite sample {} at index {}", data[i], i));
+    }
+    Ok(())
 }
 
-#[wasm_bindgen]
+#[cfg(feature = "yin")]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct YinPitchDetector {
     yin: yin::Yin,
+    threshold: f64,
+    freq_min: f64,
+    freq_max: f64,
+    sample_rate: usize,
+    warnings: WarningChannel,
+    tuning: TuningHandle,
+    // Octaves the guard looks below each pitch, None when off
+    octave_guard: Option<u32>,
+    // Adaptive threshold bounds (strict, permissive), None for the fixed one
+    adaptive: Option<(f64, f64)>,
+    // Threshold `yin` was set up with
+    current_threshold: f64,
+    // Running minimum of frame RMS, the noise estimate for the SNR
+    noise_rms: Option<f64>,
+    // Tried on high notes when YIN finds no pitch, None when off
+    zero_crossing: Option<ZeroCrossingPitchDetector>,
 }
 
-#[wasm_bindgen]
+// Octave guard: the odd partials of a fundamental below the detected pitch
+// must carry at least this share of the pitch's band energy. YIN only skips
+// a fundamental whose odd partials are weak, under about 5% of the power at
+// its default threshold, so the bar sits well below that; a true pitch
+// leaves a few tenths of a percent there through leakage.
+#[cfg(feature = "yin")]
+const OCTAVE_GUARD_MIN_RATIO: f64 = 0.01;
+#[cfg(feature = "yin")]
+const DEFAULT_OCTAVE_GUARD_OCTAVES: u32 = 1;
+// Adaptive threshold: the strict bound applies up to the low SNR, the
+// permissive one from the high SNR, linear in dB between
+#[cfg(feature = "yin")]
+const ADAPTIVE_LOW_SNR_DB: f64 = 10.0;
+#[cfg(feature = "yin")]
+const ADAPTIVE_HIGH_SNR_DB: f64 = 40.0;
+#[cfg(feature = "yin")]
+const DEFAULT_THRESHOLD_MIN: f64 = 0.05;
+#[cfg(feature = "yin")]
+const DEFAULT_THRESHOLD_MAX: f64 = 0.2;
+// The noise estimate follows quieter frames at once and creeps up by this
+// factor per frame, so it recovers after a level change
+#[cfg(feature = "yin")]
+const NOISE_RISE_PER_FRAME: f64 = 1.01;
+// YIN is set up again only for threshold changes larger than this
+#[cfg(feature = "yin")]
+const ADAPTIVE_THRESHOLD_STEP: f64 = 0.005;
+// Zero-crossing fallback only reports notes from here up, where partials
+// are weak enough for the crossings to be regular
+#[cfg(feature = "yin")]
+const ZERO_CROSSING_FALLBACK_MIN_HZ: f64 = 200.0;
+
+#[cfg(feature = "yin")]
 impl YinPitchDetector {
-    #[wasm_bindgen(constructor)]
+    // Detector set up from config.yaml settings, as the CLI uses it
+    pub fn from_config(config: &Config, sample_rate: usize) -> YinPitchDetector {
+        let freq_max = config.fitted_freq_max(sample_rate);
+        let mut detector = YinPitchDetector::new(config.threshold, config.freq_min, freq_max, sample_rate);
+        let octaves = config.octave_guard_octaves.unwrap_or(DEFAULT_OCTAVE_GUARD_OCTAVES);
+        detector.set_octave_guard(config.octave_guard, octaves);
+        detector.set_adaptive_threshold(config.adaptive_threshold, config.threshold_min, config.threshold_max);
+        detector.set_zero_crossing_fallback(config.zero_crossing_fallback);
+        detector
+    }
+}
+
+#[cfg(feature = "yin")]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl YinPitchDetector {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(threshold: f64, freq_min: f64, freq_max: f64, sample_rate: usize) -> YinPitchDetector {
         let yin = yin::Yin::init(threshold, freq_min, freq_max, sample_rate);
-        YinPitchDetector { yin: yin }
+        YinPitchDetector {
+            yin: yin,
+            threshold,
+            freq_min,
+            freq_max,
+            sample_rate,
+            warnings: WarningChannel::default(),
+            tuning: TuningHandle::standard(),
+            octave_guard: None,
+            adaptive: None,
+            current_threshold: threshold,
+            noise_rms: None,
+            zero_crossing: None,
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = from_config)]
+    pub fn from_config_js(config: Config, sample_rate: usize) -> YinPitchDetector {
+        YinPitchDetector::from_config(&config, sample_rate)
+    }
+
+    // Off by default. When YIN finds no pitch, a clean note above 200 Hz
+    // is measured from its zero crossings instead, which closes the gaps in
+    // the readings of a softly played E4.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_zero_crossing_fallback(&mut self, enabled: bool) {
+        let freq_min = self.freq_min.max(ZERO_CROSSING_FALLBACK_MIN_HZ);
+        self.zero_crossing = (enabled && freq_min < self.freq_max)
+            .then(|| ZeroCrossingPitchDetector::new(freq_min, self.freq_max, self.sample_rate));
+    }
+
+    // Adjusts the threshold per frame to the signal-to-noise ratio: noisy
+    // frames need the strict `min` (clearly periodic), clean ones, like the
+    // quiet tail of a decaying note over a silent room, get the permissive
+    // `max`, by default 0.05 and 0.2. Disabled, the fixed threshold applies.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_adaptive_threshold(&mut self, enabled: bool, min: Option<f64>, max: Option<f64>) {
+        let (min, max) = (min.unwrap_or(DEFAULT_THRESHOLD_MIN), max.unwrap_or(DEFAULT_THRESHOLD_MAX));
+        self.adaptive = if enabled { Some((min.min(max), min.max(max))) } else { None };
+        if !enabled {
+            self.set_threshold(self.threshold);
+        }
+    }
+
+    // Threshold used for the last frame
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn current_threshold(&self) -> f64 {
+        self.current_threshold
+    }
+
+    // Off by default. YIN on a string with a weak fundamental (low E through
+    // a small speaker, a bright pickup) can lock onto the second partial and
+    // read E2 as E3; the guard then reports the fundamental instead.
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_octave_guard(&mut self, enabled: bool, max_octaves: u32) {
+        self.octave_guard = if enabled && max_octaves > 0 { Some(max_octaves) } else { None };
+    }
+
+    // Smallest frame that fits two periods of freq_min, which YIN needs to
+    // find the lowest note.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn required_frame_len(&self) -> usize {
+        min_frame_len(self.freq_min, self.sample_rate)
     }
 
+    // Throws a descriptive error if the frame is empty, too short or contains
+    // NaN/infinite samples.
+    #[cfg(feature = "wasm")]
     #[wasm_bindgen]
-    pub fn maybe_find_pitch_js(&mut self, data: &Float64Array) -> Option<f64> {
+    pub fn maybe_find_pitch_js(&mut self, data: &Float64Array) -> Result<Option<f64>, JsError> {
         // Convert the Float64Array from JavaScript to a Rust slice
         let data_vec = data.to_vec(); // Convert the Float64Array to Vec<f64>
-        
+        self.check_frame(&data_vec);
+        validate_frame(&data_vec, self.required_frame_len()).map_err(|e| JsError::new(&e))?;
+
+        Ok(self.maybe_find_pitch(&data_vec))
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn preferred_frame_len_js(&self) -> usize {
+        self.preferred_frame_len()
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn accepts_frame_len_js(&self, len: usize) -> bool {
+        self.accepts_frame_len(len)
+    }
+
+    // Non-throwing variant: invalid frames simply yield no pitch.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen]
+    pub fn try_maybe_find_pitch_js(&mut self, data: &Float64Array) -> Option<f64> {
+        let data_vec = data.to_vec();
+        self.check_frame(&data_vec);
+        if validate_frame(&data_vec, self.required_frame_len()).is_err() {
+            return None;
+        }
         self.maybe_find_pitch(&data_vec)
     }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn tuning(&self) -> String {
+        self.tuning.name().to_string()
+    }
+
+    // Unknown names are reported to the caller and leave the tuning unchanged
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen]
+    pub fn set_tuning(&mut self, name: &str) -> Result<(), JsError> {
+        self.tuning = TuningHandle::resolve(name).map_err(|e| JsError::new(&e))?;
+        Ok(())
+    }
+
+    // Closest string of the selected tuning, None when the pitch is out of range
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn find_string_js(&self, freq: f64) -> Option<StringMatch> {
+        let (frequency, distance, name) = self.tuning.maybe_find_string_and_distance(freq)?;
+        Some(StringMatch { name, frequency, distance })
+    }
+
+    // Warnings queued since the last call, e.g. clipping or a quiet input
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn take_warnings(&mut self) -> TunerWarnings {
+        TunerWarnings(self.warnings.drain())
+    }
+
+    fn set_threshold(&mut self, threshold: f64) {
+        if (threshold - self.current_threshold).abs() > f64::EPSILON {
+            self.yin = yin::Yin::init(threshold, self.freq_min, self.freq_max, self.sample_rate);
+            self.current_threshold = threshold;
+        }
+    }
+
+    fn adapt_threshold(&mut self, data: &[f64], (min, max): (f64, f64)) {
+        let rms = dsp::rms(data);
+        let noise = match self.noise_rms {
+            Some(noise) if noise < rms => noise * NOISE_RISE_PER_FRAME,
+            _ => rms,
+        };
+        self.noise_rms = Some(noise);
+        let snr_db = 20.0 * (rms / noise.max(1e-9)).log10();
+        let t = ((snr_db - ADAPTIVE_LOW_SNR_DB) / (ADAPTIVE_HIGH_SNR_DB - ADAPTIVE_LOW_SNR_DB)).clamp(0.0, 1.0);
+        let threshold = min + t * (max - min);
+        if (threshold - self.current_threshold).abs() > ADAPTIVE_THRESHOLD_STEP {
+            self.set_threshold(threshold);
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    fn check_frame(&mut self, data: &[f64]) {
+        let accepted = self.accepts_frame_len(data.len());
+        let preferred = self.preferred_frame_len();
+        self.warnings.check_frame(data, accepted, preferred);
+    }
+
+    // Steps down an octave at a time while the candidate's fundamental and
+    // third partial ring: a pitch at `freq` has no energy at freq / 2 or
+    // 1.5 * freq, a fundamental it skipped does.
+    fn guard_octave(&self, data: &[f64], freq: f64, max_octaves: u32) -> f64 {
+        let reference = band_energy(data, self.sample_rate, freq);
+        let mut guarded = freq;
+        for _ in 0..max_octaves {
+            let candidate = guarded / 2.0;
+            if candidate < self.freq_min {
+                break;
+            }
+            let odd = band_energy(data, self.sample_rate, candidate) + band_energy(data, self.sample_rate, 3.0 * candidate);
+            if odd < OCTAVE_GUARD_MIN_RATIO * reference {
+                break;
+            }
+            guarded = candidate;
+        }
+        guarded
+    }
 }
 
+#[cfg(feature = "yin")]
 impl PitchFindTrait for YinPitchDetector {
     fn maybe_find_pitch(&mut self, data: &[f64]) -> Option<f64> {
+        if let Some(bounds) = self.adaptive {
+            self.adapt_threshold(data, bounds);
+        }
         let freq = self.yin.estimate_freq(data);
         if freq != std::f64::INFINITY {
-            return Some(freq);
+            return Some(match self.octave_guard {
+                Some(octaves) => self.guard_octave(data, freq, octaves),
+                None => freq,
+            });
+        }
+        if let Some(zero_crossing) = self.zero_crossing.as_mut() {
+            return zero_crossing.maybe_find_pitch(data);
         }
         return None;
     }
+
+    // Next power of two that still fits two periods of freq_min
+    fn preferred_frame_len(&self) -> usize {
+        self.required_frame_len().next_power_of_two()
+    }
+
+    fn accepts_frame_len(&self, len: usize) -> bool {
+        len >= self.required_frame_len()
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: usize) -> Result<(), String> {
+        self.yin = yin::Yin::init(self.current_threshold, self.freq_min, self.freq_max, sample_rate);
+        self.sample_rate = sample_rate;
+        if let Some(zero_crossing) = self.zero_crossing.as_mut() {
+            zero_crossing.set_sample_rate(sample_rate)?;
+        }
+        Ok(())
+    }
+
+    fn fit_to_tuning(&mut self, tuning: &TuningHandle) {
+        let freq_max = fit_freq_max(tuning, self.freq_max, self.sample_rate);
+        if freq_max != self.freq_max {
+            self.freq_max = freq_max;
+            self.yin = yin::Yin::init(self.current_threshold, self.freq_min, freq_max, self.sample_rate);
+            if let Some(zero_crossing) = self.zero_crossing.as_mut() {
+                zero_crossing.fit_to_tuning(tuning);
+            }
+        }
+    }
+}
+
+// McLeodDetector keeps its FFT buffers in Rc<RefCell<..>> pools, so it
+// can't live in a detector that must be Send + Sync. Each thread keeps a few
+// instead, keyed by (size, padding) and created on the first frame of that
+// size, so detectors of different sizes on one thread don't replace each
+// other's and the audio thread doesn't allocate per block. Like the FFT plan
+// cache, the least recently used one is dropped when the cache is full; the
+// rest live as long as the thread.
+#[cfg(feature = "mcleod")]
+const MCLEOD_CACHE_CAPACITY: usize = 4;
+
+#[cfg(feature = "mcleod")]
+struct McleodCache {
+    // Most recently used last
+    detectors: Vec<((usize, usize), McLeodDetector<f64>)>,
+    capacity: usize,
+}
+
+#[cfg(feature = "mcleod")]
+impl McleodCache {
+    fn new(capacity: usize) -> McleodCache {
+        McleodCache { detectors: vec![], capacity: capacity.max(1) }
+    }
+
+    fn detector(&mut self, size: usize, padding: usize) -> &mut McLeodDetector<f64> {
+        match self.detectors.iter().position(|(key, _)| *key == (size, padding)) {
+            Some(pos) => {
+                let entry = self.detectors.remove(pos);
+                self.detectors.push(entry);
+            }
+            None => {
+                if self.detectors.len() >= self.capacity {
+                    self.detectors.remove(0);
+                }
+                self.detectors.push(((size, padding), McLeodDetector::new(size, padding)));
+            }
+        }
+        &mut self.detectors.last_mut().unwrap().1
+    }
+
+    #[cfg(test)]
+    fn contains(&self, size: usize, padding: usize) -> bool {
+        self.detectors.iter().any(|(key, _)| *key == (size, padding))
+    }
+}
+
+#[cfg(feature = "mcleod")]
+thread_local! {
+    static MCLEOD: std::cell::RefCell<McleodCache> = std::cell::RefCell::new(McleodCache::new(MCLEOD_CACHE_CAPACITY));
 }
 
+#[cfg(feature = "mcleod")]
 pub struct McleodPitchDetector {
     sample_rate: usize,
     power_threshold: f64,
@@ -97,86 +710,291 @@ pub struct McleodPitchDetector {
 
     size: usize,
     padding: usize,
+    clarity: Option<f64>,
 }
+#[cfg(feature = "mcleod")]
 impl McleodPitchDetector {
     pub fn new(size: usize, padding: usize, sample_rate: usize, power_threshold: f64, clarity_threshold: f64) -> McleodPitchDetector {
-        McleodPitchDetector { sample_rate, power_threshold, clarity_threshold, size, padding }
+        McleodPitchDetector { sample_rate, power_threshold, clarity_threshold, size, padding, clarity: None }
     }
 }
 
+#[cfg(feature = "mcleod")]
 impl PitchFindTrait for McleodPitchDetector {
     fn maybe_find_pitch(&mut self, data: &[f64]) -> Option<f64> {
-        let mut mcleod = McLeodDetector::new(self.size, self.padding);
-        let pitch = mcleod.get_pitch(data, self.sample_rate, self.power_threshold, self.clarity_threshold);
+        let pitch = MCLEOD.with(|cell| {
+            let mut cache = cell.borrow_mut();
+            cache.detector(self.size, self.padding).get_pitch(data, self.sample_rate, self.power_threshold, self.clarity_threshold)
+        });
+        self.clarity = pitch.as_ref().map(|p| p.clarity.clamp(0.0, 1.0));
         if pitch.is_some() {
             return Some(pitch.unwrap().frequency);
         }
         return None
     }
+
+    fn preferred_frame_len(&self) -> usize {
+        self.size
+    }
+
+    // The McLeod detector is planned for exactly `size` samples
+    fn accepts_frame_len(&self, len: usize) -> bool {
+        len == self.size
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: usize) -> Result<(), String> {
+        self.sample_rate = sample_rate;
+        Ok(())
+    }
+
+    fn last_confidence(&self) -> Option<f64> {
+        self.clarity
+    }
 }
 
+#[cfg(feature = "fft-detector")]
 pub struct FftPitchDetector {
-    stream: Stream,
+    sample_rate: usize,
+    freq_min: f64,
+    freq_max: f64,
+    // FFT length set by the caller, None follows the frame length
+    fft_len: Option<usize>,
+    // Keep the latest fft_len / 2 samples across frames and analyse those
+    accumulate: bool,
+    history: Vec<f64>,
+    buffer: Vec<Complex<f64>>,
 }
 
+#[cfg(feature = "fft-detector")]
 impl FftPitchDetector {
-    pub fn new() -> FftPitchDetector {
-        // spectrum visualizer stream
-        let stream: Stream = Stream::new(StreamConfig2 {
-            channel_count: 1,
-            processor: ProcessorConfig {
-                sampling_rate: 8192,
-                frequency_bounds: [0, 1000],
-                resolution: None,
-                volume: 1.0,
-                volume_normalisation: VolumeNormalisation::Mixture,
-                position_normalisation: PositionNormalisation::Harmonic,
-                manual_position_distribution: None,
-                interpolation: Interpolation::Cubic,
-            },
-            fft_resolution: 1024,
-            refresh_rate: 30,
-            gravity: Some(5.0),
-        });
+    pub fn new(sample_rate: usize, freq_min: f64, freq_max: f64) -> FftPitchDetector {
+        FftPitchDetector { sample_rate, freq_min, freq_max, fft_len: None, accumulate: false, history: vec![], buffer: vec![] }
+    }
+
+    // Analyses with an FFT of `fft_len` points (rounded up to a power of
+    // two) whatever the frame length, e.g. 8192 for finer bins on G3 with
+    // 2048 sample frames. Shorter frames are zero-padded; with `accumulate`
+    // the latest fft_len / 2 samples of consecutive frames are analysed
+    // instead, which sharpens the peak at the cost of latency. Frames longer
+    // than fft_len are analysed whole. None goes back to twice the frame.
+    pub fn set_fft_len(&mut self, fft_len: Option<usize>, accumulate: bool) {
+        self.fft_len = fft_len.map(|len| len.max(2).next_power_of_two());
+        self.accumulate = accumulate && self.fft_len.is_some();
+        self.history.clear();
+    }
 
-        FftPitchDetector {stream}
+    pub fn fft_len(&self) -> Option<usize> {
+        self.fft_len
     }
 }
 
+// Strongest spectral peak in range: the frame is Hann windowed, zero-padded
+// to twice the next power of two (or to the FFT length set) and the peak
+// bin refined by a parabola through the log magnitudes around it (close to
+// exact for a Hann window). Reports the loudest partial, which is not
+// always the fundamental.
+#[cfg(feature = "fft-detector")]
 impl PitchFindTrait for FftPitchDetector {
     fn maybe_find_pitch(&mut self, data: &[f64]) -> Option<f64> {
-        let vec: Vec<f32> = data.iter().map(|&x| x as f32).collect();
-
-        self.stream.push_data(vec);
-        self.stream.update();
-        
-        let mut hvol :f32 = 0.0;
-        let mut highest :f32 = 0.0;
-
-        let frequencies = self.stream.get_frequencies();
-        for (_, frequency) in frequencies.iter().enumerate() {
-            for item in frequency {
-                if item.volume > hvol {
-                    hvol = item.volume;
-                    highest = item.freq;
-                }
+        if let (true, Some(len)) = (self.accumulate, self.fft_len) {
+            self.history.extend_from_slice(data);
+            let excess = self.history.len().saturating_sub((len / 2).max(data.len()));
+            self.history.drain(..excess);
+        }
+        let frame = if self.accumulate { &self.history[..] } else { data };
+        if frame.len() < 2 {
+            return None;
+        }
+        let fft_len = match self.fft_len {
+            Some(len) => len.max(frame.len().next_power_of_two()),
+            None => 2 * frame.len().next_power_of_two(),
+        };
+        let mut windowed = frame.to_vec();
+        dsp::apply_hann(&mut windowed);
+        self.buffer.clear();
+        self.buffer.extend(windowed.iter().map(|&x| Complex::new(x, 0.0)));
+        self.buffer.resize(fft_len, Complex::new(0.0, 0.0));
+        fft::shared_plan(fft_len).process(&mut self.buffer);
+
+        let bin_hz = self.sample_rate as f64 / fft_len as f64;
+        let first = ((self.freq_min / bin_hz).floor() as usize).max(1);
+        let last = ((self.freq_max / bin_hz).ceil() as usize).min(fft_len / 2 - 1);
+        if first >= last {
+            return None;
+        }
+        let magnitude = |bin: usize| self.buffer[bin].norm();
+        let peak = (first..=last).max_by(|a, b| magnitude(*a).total_cmp(&magnitude(*b)))?;
+        let (a, b, c) = (magnitude(peak - 1), magnitude(peak), magnitude(peak + 1));
+        if b <= 0.0 {
+            return None;
+        }
+        let mut offset = 0.0;
+        if a > 0.0 && c > 0.0 {
+            let (a, b, c) = (a.ln(), b.ln(), c.ln());
+            let denom = a - 2.0 * b + c;
+            if denom < 0.0 {
+                offset = 0.5 * (a - c) / denom;
             }
         }
-        return Some(highest as f64);
+        Some((peak as f64 + offset) * bin_hz)
+    }
+
+    fn preferred_frame_len(&self) -> usize {
+        min_frame_len(self.freq_min, self.sample_rate).next_power_of_two()
+    }
+
+    // One period of freq_min is enough to see it, more sharpens the peak
+    fn accepts_frame_len(&self, len: usize) -> bool {
+        len >= min_frame_len(self.freq_min, self.sample_rate) / 2
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: usize) -> Result<(), String> {
+        self.sample_rate = sample_rate;
+        self.history.clear();
+        Ok(())
+    }
+
+    fn fit_to_tuning(&mut self, tuning: &TuningHandle) {
+        self.freq_max = fit_freq_max(tuning, self.freq_max, self.sample_rate);
+    }
+}
+
+// Octave check: the odd partials of a pitch must carry at least this share
+// of the magnitude at its octave for the pitch to stand
+const OCTAVE_ODD_PARTIAL_RATIO: f64 = 0.1;
+
+// Detectors on the high strings sometimes report B3 or E4 an octave low (a
+// subharmonic) or, with a weak fundamental, an octave high. The spectrum of
+// the frame decides: a true pitch f has energy at its odd partials f and
+// 3f, a subharmonic reading has none there but plenty at 2f; an octave-high
+// reading leaves energy at f/2 and 3f/2 unexplained. Corrections stay
+// within the tuning's range.
+pub fn correct_octave(tuning: &TuningHandle, data: &[f64], sample_rate: usize, freq: f64) -> f64 {
+    if data.len() < 2 || !freq.is_finite() || freq <= 0.0 {
+        return freq;
+    }
+    let mut windowed = data.to_vec();
+    dsp::apply_hann(&mut windowed);
+    let fft_len = 2 * data.len().next_power_of_two();
+    let spectrum = fft::magnitude_spectrum(&windowed, fft_len);
+    let bin_hz = sample_rate as f64 / fft_len as f64;
+    // Strongest bin within two either side, so detuning doesn't matter
+    let at = |f: f64| {
+        let centre = (f / bin_hz).round() as usize;
+        let (from, to) = (centre.saturating_sub(2), (centre + 3).min(spectrum.len()));
+        spectrum.get(from..to).map_or(0.0, |s| s.iter().cloned().fold(0.0, f64::max))
+    };
+    let odd = |f: f64| at(f) + at(3.0 * f);
+
+    if tuning.in_range(2.0 * freq) && odd(freq) < OCTAVE_ODD_PARTIAL_RATIO * at(2.0 * freq) {
+        return 2.0 * freq;
+    }
+    if tuning.in_range(freq / 2.0) && odd(freq / 2.0) >= OCTAVE_ODD_PARTIAL_RATIO * at(freq) {
+        return freq / 2.0;
+    }
+    freq
+}
+
+#[cfg(all(test, feature = "yin"))]
+mod tests {
+    use super::*;
+    use crate::test_util::{cents, harmonic_tone};
+
+    const SAMPLE_RATE: usize = 48000;
+    const E2: f64 = 82.41;
+    const E3: f64 = 164.81;
+
+    // Low E with a weak fundamental and weak odd partials, as through a
+    // small speaker, which YIN reads an octave high
+    const WEAK_FUNDAMENTALS: [&[f64]; 3] = [&[0.05, 1.0, 0.1, 0.5], &[0.1, 1.0, 0.1, 0.3], &[0.15, 1.0, 0.1, 0.3, 0.05]];
+
+    fn detect(partials: &[f64], freq: f64, octave_guard: bool) -> f64 {
+        let mut detector = YinPitchDetector::new(0.1, 60.0, 500.0, SAMPLE_RATE);
+        detector.set_octave_guard(octave_guard, DEFAULT_OCTAVE_GUARD_OCTAVES);
+        let data = harmonic_tone(freq, partials, SAMPLE_RATE, 4096);
+        detector.maybe_find_pitch(&data).expect("a pitch")
+    }
+
+    #[test]
+    fn weak_fundamental_e2_reads_as_e3_without_the_guard() {
+        for partials in WEAK_FUNDAMENTALS {
+            let freq = detect(partials, E2, false);
+            assert!(cents(freq, E3).abs() < 20.0, "{:?} read {} Hz", partials, freq);
+        }
+    }
+
+    #[test]
+    fn octave_guard_recovers_weak_fundamental_e2() {
+        for partials in WEAK_FUNDAMENTALS {
+            let freq = detect(partials, E2, true);
+            assert!(cents(freq, E2).abs() < 20.0, "{:?} read {} Hz", partials, freq);
+        }
+    }
+
+    #[test]
+    fn octave_guard_leaves_real_e3_alone() {
+        for partials in [&[1.0, 0.5, 0.3, 0.2][..], &[0.6, 1.0, 0.4, 0.3], &[1.0]] {
+            let freq = detect(partials, E3, true);
+            assert!(cents(freq, E3).abs() < 20.0, "{:?} read {} Hz", partials, freq);
+        }
+    }
+
+    #[test]
+    fn octave_guard_leaves_full_e2_alone() {
+        let freq = detect(&[1.0, 0.5, 0.3, 0.2], E2, true);
+        assert!(cents(freq, E2).abs() < 20.0, "read {} Hz", freq);
+    }
+
+    #[test]
+    fn octave_guard_keeps_recordings_in_place() {
+        // Tones from manual_testing, named after their frequency
+        for name in ["82", "110", "147", "196", "247", "330"] {
+            let path = format!("{}/../manual_testing/{}.wav", env!("CARGO_MANIFEST_DIR"), name);
+            let bytes = std::fs::read(&path).expect("recording");
+            let (samples, sample_rate) = crate::wav::decode_wav(&bytes).expect("wav");
+            let mut detector = YinPitchDetector::new(0.1, 60.0, 500.0, sample_rate as usize);
+            detector.set_octave_guard(true, DEFAULT_OCTAVE_GUARD_OCTAVES);
+            let expected: f64 = name.parse().unwrap();
+            let freq = detector.maybe_find_pitch(&samples[..4096]).expect("a pitch");
+            assert!(cents(freq, expected).abs() < 20.0, "{}.wav read {} Hz", name, freq);
+        }
     }
 }
 
-pub fn find_string_and_distance(freq: f64) -> (f64, f64, String) {
-    let mut min_distance = std::f64::INFINITY;
-    let mut string_freq = 0.0;
-    let mut string_key = "".to_string();
-    for (key, sf) in GUITAR_STRINGS.iter() {
-        let distance = freq - sf;
-        if distance.abs() < min_distance.abs() {
-            min_distance = distance;
-            string_freq = *sf;
-            string_key = key.to_string();
+#[cfg(all(test, feature = "mcleod"))]
+mod mcleod_tests {
+    use super::*;
+
+    #[test]
+    fn alternating_sizes_keep_both_detectors() {
+        let mut cache = McleodCache::new(2);
+        for _ in 0..3 {
+            cache.detector(1024, 512);
+            cache.detector(2048, 1024);
+        }
+        assert!(cache.contains(1024, 512));
+        assert!(cache.contains(2048, 1024));
+    }
+
+    #[test]
+    fn least_recently_used_detector_is_dropped() {
+        let mut cache = McleodCache::new(2);
+        cache.detector(1024, 512);
+        cache.detector(2048, 1024);
+        cache.detector(1024, 512);
+        cache.detector(4096, 2048);
+        assert!(cache.contains(1024, 512));
+        assert!(!cache.contains(2048, 1024));
+        assert!(cache.contains(4096, 2048));
+    }
+
+    #[test]
+    fn changing_sizes_stay_within_capacity() {
+        let mut cache = McleodCache::new(MCLEOD_CACHE_CAPACITY);
+        for size in (1..=32).map(|i| i * 256) {
+            cache.detector(size, size / 2);
         }
+        let kept = (1..=32).map(|i| i * 256).filter(|&size| cache.contains(size, size / 2)).count();
+        assert_eq!(kept, MCLEOD_CACHE_CAPACITY);
     }
-    return (string_freq, min_distance, string_key);
-}
\ No newline at end of file
+}
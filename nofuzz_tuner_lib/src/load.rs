@@ -0,0 +1,78 @@
+// src/load.rs
+//
+// Keeps analysis within a CPU budget on slow devices. The time spent in a
+// detection is compared with the duration of audio it covers; when the
+// budget is exceeded only every n-th frame is analysed (a larger hop) and n
+// is lowered again once there is headroom.
+
+// Largest number of frames per analysis
+const MAX_HOP_FRAMES: usize = 8;
+// Weight of the newest measurement in the load average
+const LOAD_SMOOTHING: f64 = 0.2;
+
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+pub(crate) fn now_seconds() -> f64 {
+    use lazy_static::lazy_static;
+    use std::time::Instant;
+    lazy_static! {
+        static ref START: Instant = Instant::now();
+    }
+    START.elapsed().as_secs_f64()
+}
+
+// std::time::Instant isn't available in the browser
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub(crate) fn now_seconds() -> f64 {
+    js_sys::Date::now() / 1000.0
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct HopController {
+    // Fraction of real time analysis may use, e.g. 0.5
+    budget: f64,
+    hop_frames: usize,
+    counter: usize,
+    // Average analysis time relative to the audio of one frame
+    load: Option<f64>,
+}
+
+impl HopController {
+    pub(crate) fn new(budget: f64) -> HopController {
+        HopController { budget, hop_frames: 1, counter: 0, load: None }
+    }
+
+    // Whether the current frame should be analysed
+    pub(crate) fn should_analyse(&mut self) -> bool {
+        let analyse = self.counter == 0;
+        self.counter = (self.counter + 1) % self.hop_frames;
+        analyse
+    }
+
+    // Records how long an analysis of `frame_seconds` of audio took and
+    // adapts the hop
+    pub(crate) fn record(&mut self, elapsed: f64, frame_seconds: f64) {
+        if frame_seconds <= 0.0 {
+            return;
+        }
+        let sample = elapsed / frame_seconds;
+        let load = match self.load {
+            Some(prev) => prev + LOAD_SMOOTHING * (sample - prev),
+            None => sample,
+        };
+        self.load = Some(load);
+
+        let used = load / self.hop_frames as f64;
+        if used > self.budget && self.hop_frames < MAX_HOP_FRAMES {
+            self.hop_frames *= 2;
+            self.counter = 0;
+        } else if self.hop_frames > 1 && load / (self.hop_frames / 2) as f64 <= self.budget * 0.5 {
+            // Only shrink when the smaller hop would still leave headroom
+            self.hop_frames /= 2;
+            self.counter = 0;
+        }
+    }
+
+    pub(crate) fn hop_frames(&self) -> usize {
+        self.hop_frames
+    }
+}
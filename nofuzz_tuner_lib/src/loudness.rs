@@ -0,0 +1,151 @@
+// src/loudness.rs
+//
+// Simplified ITU-R BS.1770 loudness (K-weighting, no gating blocks). RMS
+// thresholds depend heavily on the input: a DI box and a laptop mic read very
+// differently for the same perceived level. K-weighted loudness tracks what
+// is heard more closely, so thresholds carry over between devices better.
+
+use std::collections::VecDeque;
+
+use crate::filters::Biquad;
+
+// Loudness reported for digital silence
+pub const SILENCE_LUFS: f64 = -120.0;
+
+// K-weighting pre-filter: high shelf followed by the RLB high-pass. The
+// coefficients are derived for any sample rate as in libebur128.
+#[derive(Debug, Clone)]
+pub struct KWeighting {
+    shelf: Biquad,
+    highpass: Biquad,
+}
+
+impl KWeighting {
+    pub fn new(sample_rate: usize) -> KWeighting {
+        let fs = sample_rate as f64;
+
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (std::f64::consts::PI * f0 / fs).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+            2.0 * (k * k - 1.0) / a0,
+            (1.0 - k / q + k * k) / a0,
+        );
+
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (std::f64::consts::PI * f0 / fs).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let highpass = Biquad::new(
+            1.0,
+            -2.0,
+            1.0,
+            2.0 * (k * k - 1.0) / a0,
+            (1.0 - k / q + k * k) / a0,
+        );
+
+        KWeighting { shelf, highpass }
+    }
+
+    // Loudness of one frame in LUFS. Filter state carries over between
+    // frames, so feed consecutive frames of the same stream.
+    pub fn frame_loudness(&mut self, data: &[f64]) -> f64 {
+        if data.is_empty() {
+            return SILENCE_LUFS;
+        }
+        let mut sum = 0.0;
+        for x in data {
+            let y = self.highpass.process(self.shelf.process(*x));
+            sum += y * y;
+        }
+        let mean_square = sum / data.len() as f64;
+        if mean_square <= 0.0 {
+            return SILENCE_LUFS;
+        }
+        (-0.691 + 10.0 * mean_square.log10()).max(SILENCE_LUFS)
+    }
+}
+
+// One-off loudness of a buffer
+pub fn loudness(data: &[f64], sample_rate: usize) -> f64 {
+    KWeighting::new(sample_rate).frame_loudness(data)
+}
+
+// Noise floor tracking for an automatic gate, by minimum statistics: the
+// level of frames without a note is smoothed, and the floor is the lowest
+// smoothed level over the last few seconds of them. The minimum ignores the
+// tails of notes and the odd bump, so the floor drops as soon as the room
+// goes quiet and rises once a noisier room has filled the window, e.g. when
+// moving from a quiet room to a rehearsal space.
+
+// Weight of the newest frame in the smoothing, on power
+const FLOOR_SMOOTHING: f64 = 0.2;
+// The window is FLOOR_SUBWINDOWS of FLOOR_SUBWINDOW_SECONDS each, so it
+// slides on in steps rather than keeping every frame
+const FLOOR_SUBWINDOW_SECONDS: f64 = 0.5;
+const FLOOR_SUBWINDOWS: usize = 4;
+// The minimum of a fluctuating level sits below its average by about this
+const FLOOR_BIAS_LU: f64 = 1.5;
+// Gate sits this far above the floor
+const AUTO_GATE_MARGIN_LU: f64 = 10.0;
+// The automatic gate never goes outside these
+pub const AUTO_GATE_MIN_LUFS: f64 = -70.0;
+pub const AUTO_GATE_MAX_LUFS: f64 = -30.0;
+
+#[derive(Debug, Clone, Default)]
+pub struct NoiseFloor {
+    // Smoothed mean square, on the LUFS scale's power
+    smoothed: Option<f64>,
+    // Lowest smoothed power in the current subwindow and its length so far
+    current: Option<f64>,
+    elapsed: f64,
+    // Minima of the completed subwindows, oldest first
+    minima: VecDeque<f64>,
+}
+
+impl NoiseFloor {
+    // Feed frames that carry no note, with their length in seconds
+    pub fn update(&mut self, level: f64, seconds: f64) {
+        let power = 10f64.powf(level / 10.0);
+        let smoothed = self.smoothed.map_or(power, |s| s + FLOOR_SMOOTHING * (power - s));
+        self.smoothed = Some(smoothed);
+        self.current = Some(self.current.map_or(smoothed, |c| c.min(smoothed)));
+        self.elapsed += seconds;
+        if self.elapsed >= FLOOR_SUBWINDOW_SECONDS {
+            self.minima.extend(self.current.take());
+            if self.minima.len() > FLOOR_SUBWINDOWS {
+                self.minima.pop_front();
+            }
+            self.elapsed = 0.0;
+        }
+    }
+
+    // A floor measured earlier, e.g. in the last session. It counts as one
+    // subwindow, so a different room replaces it within a few seconds.
+    pub fn from_floor(floor: f64) -> NoiseFloor {
+        let power = 10f64.powf((floor - FLOOR_BIAS_LU) / 10.0);
+        NoiseFloor { minima: VecDeque::from([power]), ..NoiseFloor::default() }
+    }
+
+    // Starts over, e.g. after the input device changed
+    pub fn reset(&mut self) {
+        *self = NoiseFloor::default();
+    }
+
+    pub fn floor(&self) -> Option<f64> {
+        let lowest = self.minima.iter().chain(self.current.iter()).copied().reduce(f64::min)?;
+        Some((10.0 * lowest.log10()).max(SILENCE_LUFS) + FLOOR_BIAS_LU)
+    }
+
+    pub fn gate(&self) -> Option<f64> {
+        self.floor()
+            .map(|floor| (floor + AUTO_GATE_MARGIN_LU).clamp(AUTO_GATE_MIN_LUFS, AUTO_GATE_MAX_LUFS))
+    }
+}
@@ -0,0 +1,243 @@
+// src/monitor.rs
+//
+// Per-string band analysis: how much energy sits around each string of the
+// tuning, used to tell the string being played from ones ringing along
+// with it, and the multi-string monitor that reports every string at once.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{aggregate_score, dsp, TuningHandle, TuningScore};
+
+// Band energy is probed at the centre and at +-25 and +-50 cents around it so
+// small detuning doesn't fall between probes.
+const BAND_OFFSETS_CENTS: [f64; 5] = [-50.0, -25.0, 0.0, 25.0, 50.0];
+
+// Minimum share of the loudest string band the detected pitch must have.
+// Quieter candidates are most likely open strings ringing sympathetically.
+const MIN_RELATIVE_BAND_ENERGY: f64 = 0.25;
+// A string band within this of an integer multiple of the pitch holds one
+// of its partials, not another string
+const HARMONIC_BAND_TOLERANCE_CENTS: f64 = 50.0;
+
+pub(crate) fn band_energy(data: &[f64], sample_rate: usize, freq: f64) -> f64 {
+    BAND_OFFSETS_CENTS
+        .iter()
+        .map(|c| dsp::goertzel_power(data, freq * 2f64.powf(c / 1200.0), sample_rate))
+        .fold(0.0, f64::max)
+}
+
+pub fn string_band_energies(tuning: &TuningHandle, data: &[f64], sample_rate: usize) -> Vec<(String, f64)> {
+    tuning
+        .strings()
+        .iter()
+        .map(|s| (s.name.clone(), band_energy(data, sample_rate, s.frequency)))
+        .collect()
+}
+
+// Whether `other` is within `tolerance_cents` of the 2nd or a higher
+// partial of `freq`
+fn is_partial_of(freq: f64, other: f64, tolerance_cents: f64) -> bool {
+    let ratio = other / freq;
+    let n = ratio.round();
+    n >= 2.0 && (1200.0 * (ratio / n).log2()).abs() <= tolerance_cents
+}
+
+// Energy around the detected frequency relative to the loudest string band,
+// 0..1. Low values mean some other string is ringing louder than the pitch
+// the detector locked onto. Bands on the pitch's own partials don't count:
+// E2 with a weak fundamental puts most of its energy on B3 and E4.
+pub fn relative_band_energy(tuning: &TuningHandle, data: &[f64], sample_rate: usize, freq: f64) -> f64 {
+    let own = band_energy(data, sample_rate, freq);
+    let loudest = tuning
+        .strings()
+        .iter()
+        .filter(|s| !is_partial_of(freq, s.frequency, HARMONIC_BAND_TOLERANCE_CENTS))
+        .map(|s| band_energy(data, sample_rate, s.frequency))
+        .fold(own, f64::max);
+    if loudest <= 0.0 {
+        return 0.0;
+    }
+    own / loudest
+}
+
+// Standard tuning variant for the web app
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = relative_band_energy)]
+pub fn relative_band_energy_js(data: &[f64], sample_rate: usize, freq: f64) -> f64 {
+    relative_band_energy(&TuningHandle::standard(), data, sample_rate, freq)
+}
+
+pub fn is_dominant_pitch(tuning: &TuningHandle, data: &[f64], sample_rate: usize, freq: f64) -> bool {
+    relative_band_energy(tuning, data, sample_rate, freq) >= MIN_RELATIVE_BAND_ENERGY
+}
+
+// Multi-string monitor: one narrow probe per string reporting whether it
+// rings and how far off it is. The cents resolution depends on the frame
+// length, a few thousand samples per probe is too coarse for fine tuning
+// so callers should feed around half a second of audio.
+const MONITOR_SEARCH_CENTS: f64 = 50.0;
+const MONITOR_STEP_CENTS: f64 = 5.0;
+// Amplitude (relative to full scale) below which a string counts as silent
+const MONITOR_MIN_AMPLITUDE: f64 = 0.01;
+// A string also counts as silent when it's this much quieter than the loudest
+const MONITOR_MIN_RELATIVE_AMPLITUDE: f64 = 0.1;
+// A component this close to a partial of a lower ringing string belongs to
+// that string; allows for the stretched partials of real strings
+const MONITOR_PARTIAL_TOLERANCE_CENTS: f64 = 15.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct StringStatus {
+    pub name: String,
+    pub frequency: f64,
+    pub ringing: bool,
+    pub amplitude: f64,
+    pub cents: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct StringMonitor(pub Vec<StringStatus>);
+
+// Scans +-50 cents around the target and returns the offset of the strongest
+// component (parabolically interpolated) and its amplitude.
+pub(crate) fn probe_string(data: &[f64], sample_rate: usize, target: f64) -> (f64, f64) {
+    let steps = (MONITOR_SEARCH_CENTS / MONITOR_STEP_CENTS) as i32;
+    let powers: Vec<f64> = (-steps..=steps)
+        .map(|i| {
+            let cents = i as f64 * MONITOR_STEP_CENTS;
+            dsp::goertzel_power(data, target * 2f64.powf(cents / 1200.0), sample_rate)
+        })
+        .collect();
+    let mut best = 0;
+    for (i, p) in powers.iter().enumerate() {
+        if *p > powers[best] {
+            best = i;
+        }
+    }
+    let mut offset = 0.0;
+    if best > 0 && best < powers.len() - 1 {
+        let (a, b, c) = (powers[best - 1], powers[best], powers[best + 1]);
+        let denom = a - 2.0 * b + c;
+        if denom != 0.0 {
+            offset = 0.5 * (a - c) / denom;
+        }
+    }
+    let cents = (best as f64 - steps as f64 + offset) * MONITOR_STEP_CENTS;
+    // Goertzel power is amplitude^2 / 4 for a sine on the probe frequency
+    let amplitude = (4.0 * powers[best]).sqrt();
+    (cents, amplitude)
+}
+
+pub fn monitor_strings(tuning: &TuningHandle, data: &[f64], sample_rate: usize) -> Vec<StringStatus> {
+    // Windowed, so a loud partial a semitone or two away doesn't leak into
+    // the probe of a silent string; the window halves the amplitudes
+    let mut windowed = data.to_vec();
+    dsp::apply_hann(&mut windowed);
+    let mut statuses: Vec<StringStatus> = tuning
+        .strings()
+        .iter()
+        .map(|s| {
+            let (cents, amplitude) = probe_string(&windowed, sample_rate, s.frequency);
+            StringStatus { name: s.name.clone(), frequency: s.frequency, ringing: false, amplitude: 2.0 * amplitude, cents }
+        })
+        .collect();
+
+    // Low E alone puts energy on B3 and E4 through its 3rd and 4th
+    // partials. Going up from the lowest string, a component on a partial of
+    // a lower string that rings is credited to that string. A string tuned
+    // exactly onto such a partial can't be told apart and is credited too.
+    let mut order: Vec<usize> = (0..statuses.len()).collect();
+    order.sort_by(|a, b| statuses[*a].frequency.total_cmp(&statuses[*b].frequency));
+    let mut explained = vec![false; statuses.len()];
+    let mut lower: Vec<f64> = vec![];
+    for i in order {
+        let status = &statuses[i];
+        let measured = status.frequency * 2f64.powf(status.cents / 1200.0);
+        explained[i] = lower.iter().any(|f| is_partial_of(*f, measured, MONITOR_PARTIAL_TOLERANCE_CENTS));
+        if !explained[i] && status.amplitude >= MONITOR_MIN_AMPLITUDE {
+            lower.push(measured);
+        }
+    }
+
+    let loudest = statuses
+        .iter()
+        .zip(explained.iter())
+        .filter(|(_, explained)| !**explained)
+        .map(|(s, _)| s.amplitude)
+        .fold(0.0, f64::max);
+    for (status, explained) in statuses.iter_mut().zip(explained) {
+        status.ringing = !explained
+            && status.amplitude >= MONITOR_MIN_AMPLITUDE
+            && status.amplitude >= loudest * MONITOR_MIN_RELATIVE_AMPLITUDE;
+    }
+    statuses
+}
+
+// Score from a strum analysed by monitor_strings, ringing strings weighted
+// by their amplitude
+pub fn monitor_score(statuses: &[StringStatus]) -> Option<TuningScore> {
+    let residuals: Vec<(f64, f64)> = statuses.iter().filter(|s| s.ringing).map(|s| (s.cents, s.amplitude)).collect();
+    aggregate_score(&residuals)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn monitor_score_js(data: &[f64], sample_rate: usize) -> Option<TuningScore> {
+    monitor_score(&monitor_strings(&TuningHandle::standard(), data, sample_rate))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn monitor_strings_js(data: &[f64], sample_rate: usize) -> StringMonitor {
+    StringMonitor(monitor_strings(&TuningHandle::standard(), data, sample_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::harmonic_tone;
+
+    const SAMPLE_RATE: usize = 48000;
+    // Half a second, as monitor_strings wants
+    const LEN: usize = 24000;
+
+    fn ringing(data: &[f64]) -> Vec<String> {
+        monitor_strings(&TuningHandle::standard(), data, SAMPLE_RATE)
+            .into_iter()
+            .filter(|s| s.ringing)
+            .map(|s| s.name)
+            .collect()
+    }
+
+    #[test]
+    fn low_e_partials_are_not_other_strings() {
+        let e2 = harmonic_tone(82.41, &[0.5, 0.3, 0.3, 0.25, 0.1], SAMPLE_RATE, LEN);
+        assert_eq!(ringing(&e2), vec!["E2"]);
+    }
+
+    #[test]
+    fn weak_fundamental_still_rings() {
+        let e2 = harmonic_tone(82.41, &[0.05, 0.3, 0.5, 0.4], SAMPLE_RATE, LEN);
+        assert_eq!(ringing(&e2), vec!["E2"]);
+    }
+
+    #[test]
+    fn strings_off_the_partials_ring_together() {
+        let mut data = harmonic_tone(82.41, &[0.5, 0.3, 0.3, 0.25], SAMPLE_RATE, LEN);
+        // G3 is no partial of E2, B3 is but sits 30 cents flat of it
+        let g3 = harmonic_tone(196.0, &[0.3, 0.1], SAMPLE_RATE, LEN);
+        let b3 = harmonic_tone(246.94 * 2f64.powf(-30.0 / 1200.0), &[0.3], SAMPLE_RATE, LEN);
+        for (i, x) in data.iter_mut().enumerate() {
+            *x += g3[i] + b3[i];
+        }
+        assert_eq!(ringing(&data), vec!["E2", "G3", "B3"]);
+        let statuses = monitor_strings(&TuningHandle::standard(), &data, SAMPLE_RATE);
+        let b3 = statuses.iter().find(|s| s.name == "B3").unwrap();
+        assert!((b3.cents + 30.0).abs() < 2.0, "B3 read {} cents", b3.cents);
+    }
+}
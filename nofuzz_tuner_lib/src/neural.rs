@@ -0,0 +1,132 @@
+// src/neural.rs
+//
+// CREPE-style neural pitch detector, behind the `neural` feature. The model
+// is not shipped: any ONNX export of CREPE (or a model trained the same way)
+// works, it sees 1024 samples at 16 kHz and scores 360 pitch bins of 20
+// cents. Far slower than YIN, but it keeps finding the pitch in noisy phone
+// recordings where YIN gives up.
+
+use tract_onnx::prelude::*;
+
+use crate::{fit_freq_max, PitchFindTrait, TuningHandle};
+
+const CREPE_SAMPLE_RATE: usize = 16000;
+const CREPE_FRAME_LEN: usize = 1024;
+// Bin 0 in cents above 10 Hz, and the bin width
+const CREPE_CENTS_OFFSET: f64 = 1997.379408437619;
+const CREPE_CENTS_PER_BIN: f64 = 20.0;
+// Bins either side of the strongest one averaged into the estimate
+const CREPE_AVERAGE_BINS: usize = 4;
+// Activation of the strongest bin below which the frame counts as unpitched
+const CREPE_MIN_CONFIDENCE: f64 = 0.5;
+
+pub struct NeuralPitchDetector {
+    model: TypedRunnableModel<TypedModel>,
+    freq_min: f64,
+    freq_max: f64,
+    sample_rate: usize,
+    confidence: Option<f64>,
+}
+
+impl NeuralPitchDetector {
+    pub fn from_onnx_file(path: &str, freq_min: f64, freq_max: f64, sample_rate: usize) -> Result<NeuralPitchDetector, String> {
+        let model = tract_onnx::onnx()
+            .model_for_path(path)
+            .map_err(|e| format!("can't load pitch model {}: {}", path, e))?;
+        NeuralPitchDetector::from_model(model, freq_min, freq_max, sample_rate)
+    }
+
+    // For the web app, which fetches the model itself
+    pub fn from_onnx_bytes(bytes: &[u8], freq_min: f64, freq_max: f64, sample_rate: usize) -> Result<NeuralPitchDetector, String> {
+        let model = tract_onnx::onnx()
+            .model_for_read(&mut std::io::Cursor::new(bytes))
+            .map_err(|e| format!("can't load pitch model: {}", e))?;
+        NeuralPitchDetector::from_model(model, freq_min, freq_max, sample_rate)
+    }
+
+    fn from_model(model: InferenceModel, freq_min: f64, freq_max: f64, sample_rate: usize) -> Result<NeuralPitchDetector, String> {
+        let model = model
+            .with_input_fact(0, f32::fact([1, CREPE_FRAME_LEN]).into())
+            .and_then(|m| m.into_optimized())
+            .and_then(|m| m.into_runnable())
+            .map_err(|e| format!("pitch model doesn't take {} samples: {}", CREPE_FRAME_LEN, e))?;
+        Ok(NeuralPitchDetector { model, freq_min, freq_max, sample_rate, confidence: None })
+    }
+
+    // Input samples that resample to one model frame
+    fn input_len(&self) -> usize {
+        (CREPE_FRAME_LEN as f64 * self.sample_rate as f64 / CREPE_SAMPLE_RATE as f64).ceil() as usize
+    }
+
+    // The end of the frame at 16 kHz, normalised to zero mean and unit
+    // variance as in training. None for digital silence.
+    fn model_input(&self, data: &[f64]) -> Option<Vec<f32>> {
+        let data = &data[data.len() - self.input_len()..];
+        let step = self.sample_rate as f64 / CREPE_SAMPLE_RATE as f64;
+        let frame: Vec<f64> = (0..CREPE_FRAME_LEN)
+            .map(|i| {
+                let pos = i as f64 * step;
+                let (index, frac) = (pos as usize, pos.fract());
+                let next = data.get(index + 1).copied().unwrap_or(data[index]);
+                data[index] * (1.0 - frac) + next * frac
+            })
+            .collect();
+        let mean = frame.iter().sum::<f64>() / frame.len() as f64;
+        let std = (frame.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / frame.len() as f64).sqrt();
+        if std < 1e-8 {
+            return None;
+        }
+        Some(frame.iter().map(|x| ((x - mean) / std) as f32).collect())
+    }
+
+    fn run(&self, input: Vec<f32>) -> TractResult<Vec<f32>> {
+        let input: Tensor = tract_ndarray::Array2::from_shape_vec((1, CREPE_FRAME_LEN), input)?.into();
+        let output = self.model.run(tvec!(input.into()))?;
+        Ok(output[0].to_array_view::<f32>()?.iter().copied().collect())
+    }
+}
+
+impl PitchFindTrait for NeuralPitchDetector {
+    fn maybe_find_pitch(&mut self, data: &[f64]) -> Option<f64> {
+        self.confidence = None;
+        if data.len() < self.input_len() {
+            return None;
+        }
+        let activations = self.run(self.model_input(data)?).ok()?;
+        let (best, peak) = activations.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))?;
+        let confidence = *peak as f64;
+        if confidence < CREPE_MIN_CONFIDENCE {
+            return None;
+        }
+        let near = best.saturating_sub(CREPE_AVERAGE_BINS)..(best + CREPE_AVERAGE_BINS + 1).min(activations.len());
+        let weights: f64 = activations[near.clone()].iter().map(|a| *a as f64).sum();
+        let bin = near.map(|i| i as f64 * activations[i] as f64).sum::<f64>() / weights;
+        let freq = 10.0 * 2f64.powf((CREPE_CENTS_OFFSET + CREPE_CENTS_PER_BIN * bin) / 1200.0);
+        self.confidence = Some(confidence);
+        if freq < self.freq_min || freq > self.freq_max {
+            return None;
+        }
+        Some(freq)
+    }
+
+    fn preferred_frame_len(&self) -> usize {
+        self.input_len().next_power_of_two()
+    }
+
+    fn accepts_frame_len(&self, len: usize) -> bool {
+        len >= self.input_len()
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: usize) -> Result<(), String> {
+        self.sample_rate = sample_rate;
+        Ok(())
+    }
+
+    fn fit_to_tuning(&mut self, tuning: &TuningHandle) {
+        self.freq_max = fit_freq_max(tuning, self.freq_max, self.sample_rate);
+    }
+
+    fn last_confidence(&self) -> Option<f64> {
+        self.confidence
+    }
+}
@@ -0,0 +1,198 @@
+// src/notes.rs
+//
+// Note names, MIDI numbers and frequencies in twelve-tone equal temperament.
+// Note names are scientific pitch notation: a letter, an optional # or b and
+// the octave, with C4 as middle C and A4 as the reference (usually 440 Hz).
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+pub const A4_HZ: f64 = 440.0;
+pub const A4_MIDI: i32 = 69;
+
+const SHARP_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+const FLAT_NAMES: [&str; 12] = ["C", "Db", "D", "Eb", "E", "F", "Gb", "G", "Ab", "A", "Bb", "B"];
+
+// How black keys are named: D#3 or Eb3
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub enum NoteSpelling {
+    Sharps,
+    Flats,
+}
+
+impl NoteSpelling {
+    pub fn from_name(name: &str) -> Result<NoteSpelling, String> {
+        match name {
+            "sharps" => Ok(NoteSpelling::Sharps),
+            "flats" => Ok(NoteSpelling::Flats),
+            _ => Err(format!("unknown note spelling '{}', expected sharps or flats", name)),
+        }
+    }
+
+    // The same note in this spelling; names that aren't notes are kept
+    pub fn spell(&self, name: &str) -> String {
+        match note_to_midi(name) {
+            Ok(midi) => midi_to_name(midi, *self == NoteSpelling::Flats),
+            Err(_) => name.to_string(),
+        }
+    }
+}
+
+const SOLFEGE_NAMES: [&str; 7] = ["Do", "Ré", "Mi", "Fa", "Sol", "La", "Si"];
+const LETTERS: &str = "CDEFGAB";
+
+// How note names are shown to the user. Names inside the library stay in
+// scientific pitch notation; this only renders them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub enum NoteNotation {
+    // E2, F#3, Bb1
+    #[default]
+    Scientific,
+    // Fixed do with the scientific octave: Mi2, Fa♯3, Si♭1
+    Solfege,
+    // Octave by case and marks, c is C3 and c′ middle C: E f♯ B♭,,
+    Helmholtz,
+}
+
+impl NoteNotation {
+    pub fn from_name(name: &str) -> Result<NoteNotation, String> {
+        match name {
+            "scientific" => Ok(NoteNotation::Scientific),
+            "solfege" => Ok(NoteNotation::Solfege),
+            "helmholtz" => Ok(NoteNotation::Helmholtz),
+            _ => Err(format!("unknown note notation '{}', expected scientific, solfege or helmholtz", name)),
+        }
+    }
+
+    // A scientific note name in this notation; names that aren't notes are
+    // kept. The spelling of the name (sharp or flat) carries over.
+    pub fn render(&self, name: &str) -> String {
+        let (Ok(midi), Some(letter)) = (note_to_midi(name), name.chars().next()) else {
+            return name.to_string();
+        };
+        let accidental = match name[1..].chars().next() {
+            Some('#') => "♯",
+            Some('b') => "♭",
+            _ => "",
+        };
+        // From the letter, so Cb4 and B#3 keep their written octave
+        let (class, _) = parse_pitch_class(name).unwrap_or((0, ""));
+        let octave = (midi - class) / 12 - 1;
+        match self {
+            NoteNotation::Scientific => name.to_string(),
+            NoteNotation::Solfege => {
+                let degree = LETTERS.find(letter).unwrap_or(0);
+                format!("{}{}{}", SOLFEGE_NAMES[degree], accidental, octave)
+            }
+            NoteNotation::Helmholtz if octave >= 3 => {
+                format!("{}{}{}", letter.to_ascii_lowercase(), accidental, "′".repeat((octave - 3) as usize))
+            }
+            NoteNotation::Helmholtz => format!("{}{}{}", letter, accidental, ",".repeat((2 - octave) as usize)),
+        }
+    }
+}
+
+// Closest equal tempered note to a frequency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Note {
+    pub name: String,
+    pub midi: i32,
+    pub freq: f64,
+    // Frequency given minus the note, in cents
+    pub cents: f64,
+}
+
+pub fn cents_between(freq: f64, target: f64) -> f64 {
+    1200.0 * (freq / target).log2()
+}
+
+// Pitch class of a note name without octave ("C" = 0, "F#" = 6, "Bb" = 10)
+pub fn pitch_class(name: &str) -> Result<i32, String> {
+    let (class, rest) = parse_pitch_class(name)?;
+    if !rest.is_empty() {
+        return Err(format!("'{}' is not a note name without octave", name));
+    }
+    Ok(class.rem_euclid(12))
+}
+
+// Letter and accidental, and what follows them
+fn parse_pitch_class(name: &str) -> Result<(i32, &str), String> {
+    let mut chars = name.chars();
+    let natural = match chars.next() {
+        Some('C') => 0,
+        Some('D') => 2,
+        Some('E') => 4,
+        Some('F') => 5,
+        Some('G') => 7,
+        Some('A') => 9,
+        Some('B') => 11,
+        _ => return Err(format!("'{}' is not a note name, expected e.g. E2, F#3 or Bb1", name)),
+    };
+    let rest = chars.as_str();
+    let (accidental, rest) = match rest.chars().next() {
+        Some('#') => (1, &rest[1..]),
+        Some('b') => (-1, &rest[1..]),
+        _ => (0, rest),
+    };
+    Ok((natural + accidental, rest))
+}
+
+// "E2" = 40, "A4" = 69
+pub fn note_to_midi(name: &str) -> Result<i32, String> {
+    let (class, octave) = parse_pitch_class(name)?;
+    let octave: i32 = octave
+        .parse()
+        .map_err(|_| format!("'{}' is not a note name, expected e.g. E2, F#3 or Bb1", name))?;
+    Ok(12 * (octave + 1) + class)
+}
+
+// Fractional MIDI numbers are allowed, 69.5 is a quarter tone above A4
+pub fn midi_to_freq(midi: f64, a4: f64) -> f64 {
+    a4 * 2f64.powf((midi - A4_MIDI as f64) / 12.0)
+}
+
+pub fn note_to_freq(name: &str, a4: f64) -> Result<f64, String> {
+    Ok(midi_to_freq(note_to_midi(name)? as f64, a4))
+}
+
+pub fn midi_to_name(midi: i32, flats: bool) -> String {
+    let names = if flats { FLAT_NAMES } else { SHARP_NAMES };
+    format!("{}{}", names[midi.rem_euclid(12) as usize], midi.div_euclid(12) - 1)
+}
+
+// Fractional MIDI note number, A4 = 440 Hz = 69.0
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn freq_to_midi(freq: f64) -> f64 {
+    A4_MIDI as f64 + 12.0 * (freq / A4_HZ).log2()
+}
+
+// Length of one period in samples
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn freq_to_period_samples(freq: f64, sample_rate: usize) -> f64 {
+    sample_rate as f64 / freq
+}
+
+// Closest note at A4 = 440 Hz, named with sharps
+pub fn freq_to_note(freq: f64) -> Note {
+    let midi = freq_to_midi(freq).round() as i32;
+    let target = midi_to_freq(midi as f64, A4_HZ);
+    Note { name: midi_to_name(midi, false), midi, freq: target, cents: cents_between(freq, target) }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = note_to_freq)]
+pub fn note_to_freq_js(name: &str, a4: f64) -> Result<f64, JsError> {
+    note_to_freq(name, a4).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = freq_to_note)]
+pub fn freq_to_note_js(freq: f64) -> Note {
+    freq_to_note(freq)
+}
@@ -0,0 +1,58 @@
+// src/onset.rs
+//
+// Pluck detection. The first ~50 ms after a pluck are mostly the pick noise
+// and an unsettled string, and detectors read wrong notes there. Onsets are
+// found from the energy derivative over short blocks, so an attack late in
+// a long frame is placed where it happened rather than at the frame start.
+
+// Block the energy is measured over, ~5 ms at 48 kHz
+const ONSET_BLOCK: usize = 256;
+// A block this much louder than the recent level starts an attack (6 dB)
+const ONSET_RISE_RATIO: f64 = 4.0;
+// Mean square below which blocks never count as an attack (-60 dBFS)
+const ONSET_MIN_ENERGY: f64 = 1e-6;
+// Weight of each block in the recent level
+const ONSET_FOLLOW: f64 = 0.2;
+// One attack, one onset: rises within this time of an onset are its tail
+const ONSET_REFRACTORY_SECONDS: f64 = 0.1;
+
+#[derive(Debug, Clone)]
+pub(crate) struct OnsetDetector {
+    // Seconds after an onset during which output is held back
+    hold_seconds: f64,
+    level: Option<f64>,
+    last_onset: Option<f64>,
+}
+
+impl OnsetDetector {
+    pub(crate) fn new(hold_seconds: f64) -> OnsetDetector {
+        OnsetDetector { hold_seconds, level: None, last_onset: None }
+    }
+
+    // Feeds the frame starting at stream time `start`. Returns the time of
+    // an onset found in it.
+    pub(crate) fn update(&mut self, data: &[f64], start: f64, sample_rate: usize) -> Option<f64> {
+        let mut onset = None;
+        for (i, block) in data.chunks(ONSET_BLOCK).enumerate() {
+            let energy = block.iter().map(|x| x * x).sum::<f64>() / block.len() as f64;
+            let t = start + (i * ONSET_BLOCK) as f64 / sample_rate as f64;
+            let rising = self.level.is_some_and(|level| energy > ONSET_RISE_RATIO * level) && energy > ONSET_MIN_ENERGY;
+            let refractory = self.last_onset.is_some_and(|last| t - last < ONSET_REFRACTORY_SECONDS);
+            if rising && !refractory {
+                self.last_onset = Some(t);
+                onset = Some(t);
+            }
+            self.level = Some(match self.level {
+                Some(level) => level + ONSET_FOLLOW * (energy - level),
+                None => energy,
+            });
+        }
+        onset
+    }
+
+    // Whether a frame starting at stream time `start` has samples from
+    // within the hold time after the last onset
+    pub(crate) fn in_attack(&self, start: f64) -> bool {
+        self.last_onset.is_some_and(|onset| start - onset < self.hold_seconds)
+    }
+}
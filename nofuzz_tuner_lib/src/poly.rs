@@ -0,0 +1,227 @@
+// src/poly.rs
+//
+// All strings at once: strum once and get every string's offset. Unlike
+// monitor_strings, which probes each string's fundamental on its own and is
+// fooled when one string's partial lands on another's fundamental (E2's
+// fourth partial is E4), this resolves the strum by iterative spectral
+// subtraction: the string whose harmonic series explains the most of the
+// spectrum is taken, its partials are subtracted, and the search repeats on
+// what is left.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::fft::magnitude_spectrum;
+use crate::{aggregate_score, dsp, TuningHandle, TuningScore};
+#[cfg(feature = "wasm")]
+use crate::InTuneSettings;
+
+// Candidates are searched this far either side of each string
+const POLY_SEARCH_CENTS: f64 = 50.0;
+const POLY_STEP_CENTS: f64 = 2.0;
+// Partials summed into a candidate's salience
+const POLY_HARMONICS: usize = 10;
+// Lowest partials used to pin down the frequency found on the grid
+const POLY_REFINE_HARMONICS: usize = 4;
+// Zero-padding factor, for peaks narrow enough to tell strings apart
+const POLY_PADDING: usize = 4;
+// A string rings when its fundamental's series is this loud (peak amplitude
+// of the strongest partial, full scale 1.0)...
+const POLY_MIN_AMPLITUDE: f64 = 0.01;
+// ... and at least this share of the strongest string's salience
+const POLY_MIN_RELATIVE_SALIENCE: f64 = 0.1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct PolyString {
+    pub name: String,
+    // Target of the string
+    pub target: f64,
+    // Measured frequency, None when the string wasn't found ringing
+    pub freq: Option<f64>,
+    pub cents: Option<f64>,
+    // Amplitude of its strongest partial
+    pub amplitude: f64,
+    pub in_tune: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct PolyPitchResult {
+    // In the order of the tuning's strings
+    pub strings: Vec<PolyString>,
+    // Over the strings found, weighted by amplitude
+    pub score: Option<TuningScore>,
+}
+
+// Analyses a frame of a strum. Frames of about 0.2 s and more are needed to
+// separate neighbouring low strings; shorter ones find fewer strings.
+pub fn detect_polyphonic(tuning: &TuningHandle, data: &[f64], sample_rate: usize, tolerance_cents: f64) -> PolyPitchResult {
+    let mut strings: Vec<PolyString> = tuning
+        .strings()
+        .iter()
+        .map(|s| PolyString { name: s.name.clone(), target: s.frequency, freq: None, cents: None, amplitude: 0.0, in_tune: false })
+        .collect();
+    if data.len() < 2 {
+        return PolyPitchResult { strings, score: None };
+    }
+
+    let mut windowed = data.to_vec();
+    dsp::apply_hann(&mut windowed);
+    let fft_len = data.len().next_power_of_two() * POLY_PADDING;
+    // A sine of amplitude a peaks at a * sum(window) / 2
+    let scale = 2.0 / (data.len() as f64 / 2.0);
+    let full: Vec<f64> = magnitude_spectrum(&windowed, fft_len).iter().map(|m| m * scale).collect();
+    let mut spectrum = full.clone();
+    let bin_hz = sample_rate as f64 / fft_len as f64;
+    // Half the main lobe of the padded Hann window
+    let lobe = 2 * POLY_PADDING;
+
+    let mut strongest: Option<f64> = None;
+    let mut open: Vec<usize> = (0..strings.len()).collect();
+    while !open.is_empty() {
+        let Some((index, freq, salience)) = open
+            .iter()
+            .filter_map(|&i| best_candidate(&spectrum, bin_hz, strings[i].target).map(|(f, s)| (i, f, s)))
+            .max_by(|a, b| a.2.total_cmp(&b.2))
+        else {
+            break;
+        };
+        if salience < POLY_MIN_RELATIVE_SALIENCE * strongest.unwrap_or(salience) {
+            break;
+        }
+        let amplitude = partials(&spectrum, bin_hz, freq).fold(0.0, f64::max);
+        if amplitude < POLY_MIN_AMPLITUDE {
+            break;
+        }
+        strongest.get_or_insert(salience);
+        let freq = refine(&spectrum, bin_hz, freq, lobe).unwrap_or(freq);
+        subtract_partials(&mut spectrum, bin_hz, freq, lobe);
+
+        strings[index].freq = Some(freq);
+        strings[index].amplitude = amplitude;
+        open.retain(|&i| i != index);
+    }
+
+    // Partials shared with strings found later pulled the early estimates;
+    // each string is measured again with everyone else taken out
+    let found: Vec<(usize, f64)> = strings.iter().enumerate().filter_map(|(i, s)| Some((i, s.freq?))).collect();
+    for &(index, freq) in &found {
+        let mut rest = full.clone();
+        for &(_, other) in found.iter().filter(|(i, _)| *i != index) {
+            subtract_partials(&mut rest, bin_hz, other, lobe);
+        }
+        let freq = refine(&rest, bin_hz, freq, lobe).unwrap_or(freq);
+        let string = &mut strings[index];
+        let cents = 1200.0 * (freq / string.target).log2();
+        string.freq = Some(freq);
+        string.cents = Some(cents);
+        string.in_tune = cents.abs() <= tolerance_cents;
+    }
+
+    let residuals: Vec<(f64, f64)> = strings.iter().filter_map(|s| Some((s.cents?, s.amplitude))).collect();
+    PolyPitchResult { score: aggregate_score(&residuals), strings }
+}
+
+// Magnitude at a fractional bin, linearly interpolated
+fn magnitude_at(spectrum: &[f64], bin: f64) -> f64 {
+    let index = bin.floor() as usize;
+    if index + 1 >= spectrum.len() {
+        return 0.0;
+    }
+    let frac = bin - index as f64;
+    spectrum[index] * (1.0 - frac) + spectrum[index + 1] * frac
+}
+
+// Magnitudes at the partials of `freq` below Nyquist
+fn partials(spectrum: &[f64], bin_hz: f64, freq: f64) -> impl Iterator<Item = f64> + '_ {
+    (1..=POLY_HARMONICS).map(move |h| magnitude_at(spectrum, h as f64 * freq / bin_hz))
+}
+
+// Partials weighted 1/h, so a candidate an octave below a ringing string
+// doesn't collect its partials at full weight
+fn salience(spectrum: &[f64], bin_hz: f64, freq: f64) -> f64 {
+    partials(spectrum, bin_hz, freq).enumerate().map(|(i, m)| m / (i + 1) as f64).sum()
+}
+
+// Frequency within the search window around `target` with the highest
+// salience, refined between grid steps, and that salience
+fn best_candidate(spectrum: &[f64], bin_hz: f64, target: f64) -> Option<(f64, f64)> {
+    let steps = (POLY_SEARCH_CENTS / POLY_STEP_CENTS) as i32;
+    let at = |step: i32| target * 2f64.powf(step as f64 * POLY_STEP_CENTS / 1200.0);
+    let saliences: Vec<f64> = (-steps..=steps).map(|i| salience(spectrum, bin_hz, at(i))).collect();
+    let (best, peak) = saliences.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))?;
+    if *peak <= 0.0 {
+        return None;
+    }
+    let mut offset = 0.0;
+    if best > 0 && best + 1 < saliences.len() {
+        let (a, b, c) = (saliences[best - 1], saliences[best], saliences[best + 1]);
+        let denom = a - 2.0 * b + c;
+        if denom < 0.0 {
+            offset = 0.5 * (a - c) / denom;
+        }
+    }
+    let step = best as f64 - steps as f64 + offset;
+    Some((target * 2f64.powf(step * POLY_STEP_CENTS / 1200.0), *peak))
+}
+
+// Partials resolved in the spectrum beyond the search grid's precision:
+// each of the lowest ones is located by a Gaussian fit through its peak
+// (exact for a Hann window's main lobe) and they vote for the fundamental,
+// weighted by magnitude
+fn refine(spectrum: &[f64], bin_hz: f64, freq: f64, lobe: usize) -> Option<f64> {
+    let (mut sum, mut weights) = (0.0, 0.0);
+    for h in 1..=POLY_REFINE_HARMONICS {
+        let centre = (h as f64 * freq / bin_hz).round() as usize;
+        let (from, to) = (centre.saturating_sub(lobe / 2).max(1), (centre + lobe / 2).min(spectrum.len() - 2));
+        if from > to {
+            break;
+        }
+        let peak = (from..=to).max_by(|a, b| spectrum[*a].total_cmp(&spectrum[*b]))?;
+        let (a, b, c) = (spectrum[peak - 1], spectrum[peak], spectrum[peak + 1]);
+        if a <= 0.0 || b <= 0.0 || c <= 0.0 || b < a || b < c {
+            continue;
+        }
+        let (a, b, c) = (a.ln(), b.ln(), c.ln());
+        let denom = a - 2.0 * b + c;
+        let offset = if denom < 0.0 { 0.5 * (a - c) / denom } else { 0.0 };
+        sum += spectrum[peak] * (peak as f64 + offset) * bin_hz / h as f64;
+        weights += spectrum[peak];
+    }
+    if weights > 0.0 { Some(sum / weights) } else { None }
+}
+
+// Removes the partials of `freq`. A partial is only taken down to the level
+// of its neighbours in the series (spectral smoothness), so a partial it
+// shares with another ringing string leaves that string's share behind.
+fn subtract_partials(spectrum: &mut [f64], bin_hz: f64, freq: f64, lobe: usize) {
+    let amplitudes: Vec<f64> = partials(spectrum, bin_hz, freq).collect();
+    for (i, observed) in amplitudes.iter().enumerate() {
+        if *observed <= 0.0 {
+            continue;
+        }
+        let neighbours = [i.checked_sub(1), Some(i + 1)]
+            .iter()
+            .flatten()
+            .filter_map(|&j| amplitudes.get(j))
+            .fold(0.0, |m: f64, a| m.max(*a));
+        let own = if neighbours > 0.0 { observed.min(neighbours) } else { *observed };
+        let keep = 1.0 - own / observed;
+        let centre = ((i + 1) as f64 * freq / bin_hz).round() as usize;
+        let (from, to) = (centre.saturating_sub(lobe), (centre + lobe).min(spectrum.len() - 1));
+        for m in spectrum[from..=to].iter_mut() {
+            *m *= keep;
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = detect_polyphonic)]
+pub fn detect_polyphonic_js(tuning: &str, data: &[f64], sample_rate: usize) -> Result<PolyPitchResult, JsError> {
+    let tuning = TuningHandle::resolve(tuning).map_err(|e| JsError::new(&e))?;
+    Ok(detect_polyphonic(&tuning, data, sample_rate, InTuneSettings::default().tolerance_cents))
+}
@@ -0,0 +1,478 @@
+// src/presets.rs
+//
+// Detection range presets. The frequency bounds and the frame length depend
+// on each other: YIN needs two periods of freq_min inside a frame, so
+// lowering freq_min on its own quietly stops the lowest notes from being
+// found. A preset sets them together.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::filters::FilterSpec;
+use crate::{IntervalReference, TuningHandle};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum RangePreset {
+    LowBass,
+    Guitar,
+    HighStrings,
+    Voice,
+    FullChromatic,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct RangeSettings {
+    pub freq_min: f64,
+    pub freq_max: f64,
+    pub frame_len: usize,
+}
+
+// When a reading counts as in tune: within `tolerance_cents` to turn green,
+// and it only turns back once it drifts past tolerance + hysteresis, so a
+// reading sitting on the edge doesn't flicker.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct InTuneSettings {
+    pub tolerance_cents: f64,
+    pub hysteresis_cents: f64,
+}
+
+impl InTuneSettings {
+    // Next in-tune state given the previous one
+    pub fn update(&self, was_in_tune: bool, cents: f64) -> bool {
+        let limit = if was_in_tune { self.tolerance_cents + self.hysteresis_cents } else { self.tolerance_cents };
+        cents.abs() <= limit
+    }
+}
+
+impl Default for InTuneSettings {
+    fn default() -> Self {
+        InTuneSettings { tolerance_cents: 3.0, hysteresis_cents: 2.0 }
+    }
+}
+
+impl RangePreset {
+    pub fn from_name(name: &str) -> Result<RangePreset, String> {
+        match name {
+            "low_bass" => Ok(RangePreset::LowBass),
+            "guitar" => Ok(RangePreset::Guitar),
+            "high_strings" => Ok(RangePreset::HighStrings),
+            "voice" => Ok(RangePreset::Voice),
+            "full_chromatic" => Ok(RangePreset::FullChromatic),
+            _ => Err(format!(
+                "unknown range preset '{}', expected one of: low_bass, guitar, high_strings, voice, full_chromatic",
+                name
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RangePreset::LowBass => "low_bass",
+            RangePreset::Guitar => "guitar",
+            RangePreset::HighStrings => "high_strings",
+            RangePreset::Voice => "voice",
+            RangePreset::FullChromatic => "full_chromatic",
+        }
+    }
+
+    // Narrowest preset whose range covers every string of the tuning, so a
+    // bass tuning gets a range (and frame length) that reaches B0 and a
+    // ukulele doesn't analyse the long frames guitar needs
+    pub fn for_tuning(tuning: &TuningHandle) -> RangePreset {
+        let (lowest, highest) = (tuning.lowest(), tuning.highest());
+        [RangePreset::Guitar, RangePreset::LowBass, RangePreset::HighStrings, RangePreset::Voice]
+            .into_iter()
+            .filter(|p| {
+                let (min, max) = p.bounds();
+                min <= lowest && highest <= max
+            })
+            .min_by(|a, b| {
+                let (a_min, a_max) = a.bounds();
+                let (b_min, b_max) = b.bounds();
+                (a_max / a_min).total_cmp(&(b_max / b_min))
+            })
+            .unwrap_or(RangePreset::FullChromatic)
+    }
+
+    pub fn bounds(&self) -> (f64, f64) {
+        match self {
+            RangePreset::LowBass => (25.0, 250.0),
+            RangePreset::Guitar => (60.0, 500.0),
+            RangePreset::HighStrings => (180.0, 1400.0),
+            RangePreset::Voice => (80.0, 1100.0),
+            RangePreset::FullChromatic => (27.5, 4200.0),
+        }
+    }
+
+    // Strings heard in ensembles need tighter tuning than voice, where a few
+    // cents of wobble is normal
+    pub fn in_tune(&self) -> InTuneSettings {
+        let (tolerance_cents, hysteresis_cents) = match self {
+            RangePreset::LowBass => (3.0, 2.0),
+            RangePreset::Guitar => (2.0, 1.5),
+            RangePreset::HighStrings => (1.0, 1.0),
+            RangePreset::Voice => (10.0, 5.0),
+            RangePreset::FullChromatic => (3.0, 2.0),
+        };
+        InTuneSettings { tolerance_cents, hysteresis_cents }
+    }
+
+    pub fn settings(&self, sample_rate: usize) -> RangeSettings {
+        let (freq_min, freq_max) = self.bounds();
+        RangeSettings { freq_min, freq_max, frame_len: min_frame_len(freq_min, sample_rate).next_power_of_two() }
+    }
+}
+
+// An instrument bundles its tuning with a detection range fitted around its
+// strings (and the frame length that range needs), its in-tune window and
+// how it is usually picked up, so picking "cello" is all a caller has to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum InstrumentProfile {
+    Guitar,
+    Bass,
+    Ukulele,
+    Violin,
+    Viola,
+    Cello,
+    Mandolin,
+    Banjo,
+    Voice,
+}
+
+impl InstrumentProfile {
+    pub fn from_name(name: &str) -> Result<InstrumentProfile, String> {
+        match name {
+            "guitar" => Ok(InstrumentProfile::Guitar),
+            "bass" => Ok(InstrumentProfile::Bass),
+            "ukulele" => Ok(InstrumentProfile::Ukulele),
+            "violin" => Ok(InstrumentProfile::Violin),
+            "viola" => Ok(InstrumentProfile::Viola),
+            "cello" => Ok(InstrumentProfile::Cello),
+            "mandolin" => Ok(InstrumentProfile::Mandolin),
+            "banjo" => Ok(InstrumentProfile::Banjo),
+            "voice" => Ok(InstrumentProfile::Voice),
+            _ => Err(format!(
+                "unknown instrument '{}', expected one of: guitar, bass, ukulele, violin, viola, cello, mandolin, banjo, voice",
+                name
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InstrumentProfile::Guitar => "guitar",
+            InstrumentProfile::Bass => "bass",
+            InstrumentProfile::Ukulele => "ukulele",
+            InstrumentProfile::Violin => "violin",
+            InstrumentProfile::Viola => "viola",
+            InstrumentProfile::Cello => "cello",
+            InstrumentProfile::Mandolin => "mandolin",
+            InstrumentProfile::Banjo => "banjo",
+            InstrumentProfile::Voice => "voice",
+        }
+    }
+
+    // Name of the default tuning in the tuning table
+    pub fn tuning(&self) -> &'static str {
+        match self {
+            InstrumentProfile::Guitar => "standard",
+            InstrumentProfile::Bass => "bass_standard",
+            InstrumentProfile::Ukulele => "ukulele-gcea",
+            InstrumentProfile::Violin => "violin",
+            InstrumentProfile::Viola => "viola",
+            InstrumentProfile::Cello => "cello",
+            InstrumentProfile::Mandolin => "mandolin-gdae",
+            InstrumentProfile::Banjo => "banjo-open-g",
+            InstrumentProfile::Voice => "chromatic",
+        }
+    }
+
+    // Somewhat below the lowest open string, up to where the highest one's
+    // second harmonic still fits
+    pub fn bounds(&self) -> (f64, f64) {
+        match self {
+            InstrumentProfile::Guitar => (60.0, 500.0),
+            // Down to a five string's B0
+            InstrumentProfile::Bass => (25.0, 250.0),
+            InstrumentProfile::Ukulele => (180.0, 1000.0),
+            InstrumentProfile::Violin => (150.0, 1400.0),
+            InstrumentProfile::Viola => (100.0, 1000.0),
+            InstrumentProfile::Cello => (50.0, 500.0),
+            InstrumentProfile::Mandolin => (150.0, 1400.0),
+            // The drone string (G4) sits above the rest
+            InstrumentProfile::Banjo => (110.0, 900.0),
+            InstrumentProfile::Voice => (80.0, 1100.0),
+        }
+    }
+
+    // Bowed strings are tuned by ear against fifths, which needs a tighter
+    // window than plucked instruments; voices always waver a little
+    pub fn in_tune(&self) -> InTuneSettings {
+        let (tolerance_cents, hysteresis_cents) = match self {
+            InstrumentProfile::Violin | InstrumentProfile::Viola => (1.0, 1.0),
+            InstrumentProfile::Cello => (1.5, 1.0),
+            InstrumentProfile::Guitar
+            | InstrumentProfile::Ukulele
+            | InstrumentProfile::Mandolin
+            | InstrumentProfile::Banjo => (2.0, 1.5),
+            InstrumentProfile::Bass => (3.0, 2.0),
+            InstrumentProfile::Voice => (10.0, 5.0),
+        };
+        InTuneSettings { tolerance_cents, hysteresis_cents }
+    }
+
+    pub fn settings(&self, sample_rate: usize) -> RangeSettings {
+        let (freq_min, freq_max) = self.bounds();
+        RangeSettings { freq_min, freq_max, frame_len: min_frame_len(freq_min, sample_rate).next_power_of_two() }
+    }
+
+    // Acoustic instruments are usually heard through a microphone; guitars
+    // and basses could be anything, so they get no filters by default
+    pub fn input_kind(&self) -> Option<InputKind> {
+        match self {
+            InstrumentProfile::Guitar | InstrumentProfile::Bass => None,
+            _ => Some(InputKind::Microphone),
+        }
+    }
+
+    // What double stops are compared against
+    pub fn interval_reference(&self) -> IntervalReference {
+        match self {
+            InstrumentProfile::Violin | InstrumentProfile::Viola | InstrumentProfile::Cello | InstrumentProfile::Voice => {
+                IntervalReference::Just
+            }
+            // Frets are laid out in equal temperament
+            _ => IntervalReference::Equal,
+        }
+    }
+}
+
+// How the instrument reaches the input. Each kind comes with the
+// preprocessing and gating it usually needs, so new users don't have to
+// work out filters themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum InputKind {
+    Microphone,
+    PiezoPickup,
+    MagneticPickup,
+    LineDI,
+}
+
+// Low enough to keep a five string bass's B0 (31 Hz) intact
+const RUMBLE_CUTOFF_HZ: f64 = 20.0;
+// Piezos exaggerate the body's low-mid resonance ("quack")
+const PIEZO_RESONANCE_HZ: f64 = 250.0;
+const PIEZO_RESONANCE_CUT_DB: f64 = -6.0;
+
+impl InputKind {
+    pub fn from_name(name: &str) -> Result<InputKind, String> {
+        match name {
+            "microphone" => Ok(InputKind::Microphone),
+            "piezo" => Ok(InputKind::PiezoPickup),
+            "magnetic" => Ok(InputKind::MagneticPickup),
+            "line_di" => Ok(InputKind::LineDI),
+            _ => Err(format!("unknown input kind '{}', expected one of: microphone, piezo, magnetic, line_di", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputKind::Microphone => "microphone",
+            InputKind::PiezoPickup => "piezo",
+            InputKind::MagneticPickup => "magnetic",
+            InputKind::LineDI => "line_di",
+        }
+    }
+
+    // Filters run on every frame before analysis
+    pub fn filters(&self) -> Vec<FilterSpec> {
+        let rumble = FilterSpec::Highpass { fc: RUMBLE_CUTOFF_HZ, q: std::f64::consts::FRAC_1_SQRT_2 };
+        match self {
+            InputKind::Microphone | InputKind::MagneticPickup => vec![rumble],
+            InputKind::PiezoPickup => vec![
+                rumble,
+                FilterSpec::Peaking { fc: PIEZO_RESONANCE_HZ, q: 1.0, gain_db: PIEZO_RESONANCE_CUT_DB },
+            ],
+            // A DI signal is already clean
+            InputKind::LineDI => vec![],
+        }
+    }
+
+    // Gate on the measured noise floor; a DI has no room noise to gate
+    pub fn auto_gate(&self) -> bool {
+        !matches!(self, InputKind::LineDI)
+    }
+}
+
+// Two periods of the lowest frequency
+pub fn min_frame_len(freq_min: f64, sample_rate: usize) -> usize {
+    2 * (sample_rate as f64 / freq_min).ceil() as usize
+}
+
+// freq_max raised to the top of the tuning's preset when a string sits above
+// it, e.g. mandolin E5 over the guitar default, kept below Nyquist. Only
+// freq_max moves: a lower freq_min would need longer frames than the caller
+// is sending.
+pub fn fit_freq_max(tuning: &TuningHandle, freq_max: f64, sample_rate: usize) -> f64 {
+    if tuning.highest() <= freq_max {
+        return freq_max;
+    }
+    let (_, preset_max) = RangePreset::for_tuning(tuning).bounds();
+    preset_max.min(0.99 * sample_rate as f64 / 2.0).max(freq_max)
+}
+
+pub fn validate_range(freq_min: f64, freq_max: f64, frame_len: usize, sample_rate: usize) -> Result<(), String> {
+    if !(freq_min > 0.0 && freq_min < freq_max) {
+        return Err(format!("freq_min ({}) must be positive and below freq_max ({})", freq_min, freq_max));
+    }
+    if freq_max >= sample_rate as f64 / 2.0 {
+        return Err(format!("freq_max ({}) must be below the Nyquist frequency ({})", freq_max, sample_rate / 2));
+    }
+    let needed = min_frame_len(freq_min, sample_rate);
+    if frame_len < needed {
+        return Err(format!(
+            "frames of {} samples are too short for freq_min {} Hz at {} Hz, need at least {}",
+            frame_len, freq_min, sample_rate, needed
+        ));
+    }
+    Ok(())
+}
+
+// Range settings that fit the named tuning
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn tuning_range_settings(tuning: &str, sample_rate: usize) -> Result<RangeSettings, JsError> {
+    let tuning = TuningHandle::resolve(tuning).map_err(|e| JsError::new(&e))?;
+    Ok(RangePreset::for_tuning(&tuning).settings(sample_rate))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn instrument_settings(name: &str, sample_rate: usize) -> Result<RangeSettings, JsError> {
+    let instrument = InstrumentProfile::from_name(name).map_err(|e| JsError::new(&e))?;
+    Ok(instrument.settings(sample_rate))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn instrument_in_tune(name: &str) -> Result<InTuneSettings, JsError> {
+    let instrument = InstrumentProfile::from_name(name).map_err(|e| JsError::new(&e))?;
+    Ok(instrument.in_tune())
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn range_preset_in_tune(name: &str) -> Result<InTuneSettings, JsError> {
+    let preset = RangePreset::from_name(name).map_err(|e| JsError::new(&e))?;
+    Ok(preset.in_tune())
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn range_preset_settings(name: &str, sample_rate: usize) -> Result<RangeSettings, JsError> {
+    let preset = RangePreset::from_name(name).map_err(|e| JsError::new(&e))?;
+    Ok(preset.settings(sample_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{cents, harmonic_tone};
+    use crate::{AcfPitchDetector, PitchTracker};
+
+    const SAMPLE_RATE: usize = 48000;
+
+    #[test]
+    fn ukulele_gets_high_strings_range() {
+        for name in ["ukulele-gcea", "ukulele-low-g"] {
+            let tuning = TuningHandle::resolve(name).unwrap();
+            assert_eq!(RangePreset::for_tuning(&tuning), RangePreset::HighStrings, "{}", name);
+        }
+    }
+
+    // Synthetic plucks for every string; manual_testing has no ukulele
+    // recordings, only generated tones at a few of its pitches
+    #[test]
+    fn ukulele_strings_are_found_with_their_preset() {
+        for name in ["ukulele-gcea", "ukulele-low-g"] {
+            let tuning = TuningHandle::resolve(name).unwrap();
+            let settings = RangePreset::for_tuning(&tuning).settings(SAMPLE_RATE);
+            for (index, string) in tuning.strings().iter().enumerate() {
+                let detector = AcfPitchDetector::new(settings.freq_min, settings.freq_max, SAMPLE_RATE);
+                let mut tracker = PitchTracker::with_detector(Box::new(detector), tuning.clone(), SAMPLE_RATE);
+                let frame = harmonic_tone(string.frequency, &[1.0, 0.6, 0.3, 0.1], SAMPLE_RATE, settings.frame_len);
+                let result = (0..8).filter_map(|_| tracker.process(&frame)).last().expect("a reading");
+                assert_eq!(result.tuning_to.note, string.name, "{}", name);
+                assert_eq!(result.tuning_to.string_index, index, "{}", name);
+                assert!(cents(result.freq, string.frequency).abs() < 5.0, "{} {} read {} Hz", name, string.name, result.freq);
+            }
+        }
+    }
+
+    // Generated tones from manual_testing (named after their frequency)
+    // through the preset's range and frame length. 247.wav is a C string
+    // left a semitone flat.
+    #[test]
+    fn ukulele_preset_reads_recorded_tones() {
+        let cases = [
+            ("ukulele-low-g", "196", "G3", 0),
+            ("ukulele-low-g", "247", "C4", 1),
+            ("ukulele-low-g", "330", "E4", 2),
+            ("ukulele-gcea", "247", "C4", 1),
+            ("ukulele-gcea", "330", "E4", 2),
+        ];
+        for (name, recording, note, index) in cases {
+            let path = format!("{}/../manual_testing/{}.wav", env!("CARGO_MANIFEST_DIR"), recording);
+            let bytes = std::fs::read(&path).expect("recording");
+            let (samples, sample_rate) = crate::wav::decode_wav(&bytes).expect("wav");
+            let sample_rate = sample_rate as usize;
+            let tuning = TuningHandle::resolve(name).unwrap();
+            let settings = RangePreset::for_tuning(&tuning).settings(sample_rate);
+            let detector = AcfPitchDetector::new(settings.freq_min, settings.freq_max, sample_rate);
+            let mut tracker = PitchTracker::with_detector(Box::new(detector), tuning, sample_rate);
+            let result = samples
+                .chunks_exact(settings.frame_len)
+                .take(8)
+                .filter_map(|frame| tracker.process(frame))
+                .last()
+                .expect("a reading");
+            let expected: f64 = recording.parse().unwrap();
+            assert_eq!(result.tuning_to.note, note, "{} {}.wav", name, recording);
+            assert_eq!(result.tuning_to.string_index, index, "{} {}.wav", name, recording);
+            assert!(cents(result.freq, expected).abs() < 5.0, "{} {}.wav read {} Hz", name, recording, result.freq);
+        }
+    }
+
+    #[test]
+    fn freq_max_is_raised_only_for_strings_above_it() {
+        let fit = |name: &str, freq_max: f64, sample_rate: usize| {
+            fit_freq_max(&TuningHandle::resolve(name).unwrap(), freq_max, sample_rate)
+        };
+        assert_eq!(fit("standard", 500.0, SAMPLE_RATE), 500.0);
+        // The G4 drone fits the guitar range
+        assert_eq!(fit("banjo-open-g", 500.0, SAMPLE_RATE), 500.0);
+        assert_eq!(fit("mandolin-gdae", 500.0, SAMPLE_RATE), 1400.0);
+        assert_eq!(fit("mandolin-gdae", 2000.0, SAMPLE_RATE), 2000.0);
+        assert!(fit("mandolin-gdae", 500.0, 2000) < 1000.0);
+    }
+
+    #[test]
+    fn banjo_drone_is_found_first_in_string_order() {
+        let tuning = TuningHandle::resolve("banjo-open-g").unwrap();
+        let settings = RangePreset::for_tuning(&tuning).settings(SAMPLE_RATE);
+        let detector = AcfPitchDetector::new(settings.freq_min, settings.freq_max, SAMPLE_RATE);
+        let mut tracker = PitchTracker::with_detector(Box::new(detector), tuning, SAMPLE_RATE);
+        let frame = harmonic_tone(392.0, &[1.0, 0.4, 0.2], SAMPLE_RATE, settings.frame_len);
+        let result = (0..8).filter_map(|_| tracker.process(&frame)).last().expect("a reading");
+        assert_eq!(result.tuning_to.note, "G4");
+        assert_eq!(result.tuning_to.string_index, 0);
+    }
+}
@@ -0,0 +1,237 @@
+// src/probe.rs
+//
+// Which detector suits this instrument and room? Every compiled-in detector
+// sees the same frames for a few seconds while the user plays a sustained
+// note; the one that finds a pitch most often, holds it steadiest and agrees
+// with the others wins.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+#[cfg(feature = "wasm")]
+use js_sys::Float64Array;
+
+use crate::{AcfPitchDetector, Config, PitchFindTrait};
+#[cfg(feature = "yin")]
+use crate::YinPitchDetector;
+#[cfg(feature = "mcleod")]
+use crate::McleodPitchDetector;
+#[cfg(feature = "fft-detector")]
+use crate::FftPitchDetector;
+
+// Floor for the detection rate in the score, so a detector that found one
+// lucky frame can't win on stability alone
+const MIN_SCORED_RATE: f64 = 0.05;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DetectorScore {
+    // Name as used in Config::pitch_detection
+    pub detector: String,
+    // Share of frames with a pitch in range, 0..1
+    pub detection_rate: f64,
+    // Median change between consecutive readings
+    pub stability_cents: f64,
+    // Median distance from what the detectors agree on per frame
+    pub agreement_cents: f64,
+    // Lower is better
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DetectorScores(pub Vec<DetectorScore>);
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct DetectorProbe {
+    candidates: Vec<(String, Box<dyn PitchFindTrait>)>,
+    // One row per frame, one reading per candidate
+    readings: Vec<Vec<Option<f64>>>,
+    freq_min: f64,
+    freq_max: f64,
+    sample_rate: usize,
+    samples_needed: usize,
+    samples_seen: usize,
+}
+
+impl DetectorProbe {
+    pub fn new(
+        candidates: Vec<(String, Box<dyn PitchFindTrait>)>,
+        freq_min: f64,
+        freq_max: f64,
+        sample_rate: usize,
+        seconds: f64,
+    ) -> DetectorProbe {
+        DetectorProbe {
+            candidates,
+            readings: vec![],
+            freq_min,
+            freq_max,
+            sample_rate,
+            samples_needed: (seconds.max(0.0) * sample_rate as f64) as usize,
+            samples_seen: 0,
+        }
+    }
+
+    // Every detector compiled in, set up from the config like the CLI does
+    #[allow(clippy::vec_init_then_push)]
+    pub fn from_config(config: &Config, frame_len: usize, sample_rate: usize, seconds: f64) -> DetectorProbe {
+        let freq_max = config.fitted_freq_max(sample_rate);
+        let mut candidates: Vec<(String, Box<dyn PitchFindTrait>)> = vec![];
+        #[cfg(feature = "yin")]
+        candidates.push((
+            "yin".to_string(),
+            Box::new(YinPitchDetector::new(config.threshold, config.freq_min, freq_max, sample_rate)),
+        ));
+        #[cfg(feature = "mcleod")]
+        candidates.push((
+            "mcleod".to_string(),
+            Box::new(McleodPitchDetector::new(
+                frame_len,
+                frame_len / 2,
+                sample_rate,
+                config.power_threshold,
+                config.clarity_threshold,
+            )),
+        ));
+        #[cfg(feature = "fft-detector")]
+        candidates.push((
+            "fft".to_string(),
+            Box::new(FftPitchDetector::new(sample_rate, config.freq_min, freq_max)),
+        ));
+        candidates.push(("acf".to_string(), Box::new(AcfPitchDetector::new(config.freq_min, freq_max, sample_rate))));
+        #[cfg(not(feature = "mcleod"))]
+        let _ = frame_len;
+        DetectorProbe::new(candidates, config.freq_min, freq_max, sample_rate, seconds)
+    }
+
+    // Runs every detector on the frame. Returns true once enough audio has
+    // been probed.
+    pub fn add_frame(&mut self, data: &[f64]) -> bool {
+        let (freq_min, freq_max) = (self.freq_min, self.freq_max);
+        let row = self
+            .candidates
+            .iter_mut()
+            .map(|(_, detector)| {
+                if !detector.accepts_frame_len(data.len()) {
+                    return None;
+                }
+                detector.maybe_find_pitch(data).filter(|f| f.is_finite() && *f >= freq_min && *f <= freq_max)
+            })
+            .collect();
+        self.readings.push(row);
+        self.samples_seen += data.len();
+        self.is_done()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.samples_seen >= self.samples_needed
+    }
+
+    pub fn seconds_probed(&self) -> f64 {
+        self.samples_seen as f64 / self.sample_rate as f64
+    }
+
+    // Scores of all candidates, best first
+    pub fn scores(&self) -> Vec<DetectorScore> {
+        let consensus: Vec<Option<f64>> = self
+            .readings
+            .iter()
+            .map(|row| median(row.iter().flatten().copied().collect()))
+            .collect();
+        let mut scores: Vec<DetectorScore> = self
+            .candidates
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| {
+                let column: Vec<Option<f64>> = self.readings.iter().map(|row| row[i]).collect();
+                let found = column.iter().flatten().count();
+                let detection_rate = if column.is_empty() { 0.0 } else { found as f64 / column.len() as f64 };
+                let steps = column
+                    .windows(2)
+                    .filter_map(|w| Some(cents(w[0]?, w[1]?).abs()))
+                    .collect();
+                let deviations = column
+                    .iter()
+                    .zip(consensus.iter())
+                    .filter_map(|(f, c)| Some(cents((*c)?, (*f)?).abs()))
+                    .collect();
+                let stability_cents = median(steps).unwrap_or(f64::INFINITY);
+                let agreement_cents = median(deviations).unwrap_or(f64::INFINITY);
+                let score = (stability_cents + agreement_cents) / detection_rate.max(MIN_SCORED_RATE);
+                DetectorScore { detector: name.clone(), detection_rate, stability_cents, agreement_cents, score }
+            })
+            .collect();
+        scores.sort_by(|a, b| a.score.total_cmp(&b.score));
+        scores
+    }
+
+    // Best detector, None when none of them found a steady pitch
+    pub fn recommendation(&self) -> Option<DetectorScore> {
+        self.scores().into_iter().next().filter(|s| s.score.is_finite())
+    }
+}
+
+// Probes frames from any source (a channel fed by an audio callback, a
+// file) until `seconds` of audio went through, then recommends a detector
+pub fn probe_best_detector<I>(config: &Config, sample_rate: usize, seconds: f64, frames: I) -> Option<DetectorScore>
+where
+    I: IntoIterator,
+    I::Item: AsRef<[f64]>,
+{
+    let mut frames = frames.into_iter().peekable();
+    let frame_len = frames.peek().map_or(0, |f| f.as_ref().len());
+    let mut probe = DetectorProbe::from_config(config, frame_len, sample_rate, seconds);
+    for frame in frames {
+        if probe.add_frame(frame.as_ref()) {
+            break;
+        }
+    }
+    probe.recommendation()
+}
+
+fn cents(from: f64, to: f64) -> f64 {
+    1200.0 * (to / from).log2()
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    Some(values[values.len() / 2])
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl DetectorProbe {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new_js(config: Config, frame_len: usize, sample_rate: usize, seconds: f64) -> DetectorProbe {
+        DetectorProbe::from_config(&config, frame_len, sample_rate, seconds)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add_frame)]
+    pub fn add_frame_js(&mut self, data: &Float64Array) -> bool {
+        self.add_frame(&data.to_vec())
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = is_done)]
+    pub fn is_done_js(&self) -> bool {
+        self.is_done()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = scores)]
+    pub fn scores_js(&self) -> DetectorScores {
+        DetectorScores(self.scores())
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = recommendation)]
+    pub fn recommendation_js(&self) -> Option<DetectorScore> {
+        self.recommendation()
+    }
+}
@@ -0,0 +1,179 @@
+// src/resample.rs
+//
+// Streaming sample rate conversion, so audio at 22.05, 32, 88.2 or 96 kHz
+// can be analysed at the rate the detector and filters were set up for.
+// Band-limited interpolation: every output sample is a Blackman windowed
+// sinc sum over the input around its position, with the cutoff lowered
+// below the output Nyquist frequency when downsampling. Any ratio works;
+// the cost is about 2 * RESAMPLE_ZEROS multiplies per output sample, more
+// when downsampling.
+
+use std::f64::consts::PI;
+
+// Zero crossings of the sinc on each side of the output position
+const RESAMPLE_ZEROS: f64 = 16.0;
+// Passband edge as a share of the lower of the two Nyquist frequencies
+const RESAMPLE_CUTOFF: f64 = 0.95;
+
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    input_rate: usize,
+    output_rate: usize,
+    // Input samples per output sample
+    step: f64,
+    // Cutoff relative to the input Nyquist frequency
+    cutoff: f64,
+    // Input samples on each side of the output position
+    half_width: usize,
+    // Unconsumed input, starting with half_width zeros of lead-in
+    history: Vec<f64>,
+    // Position of the next output sample in `history`
+    pos: f64,
+}
+
+impl Resampler {
+    pub fn new(input_rate: usize, output_rate: usize) -> Result<Resampler, String> {
+        if input_rate == 0 || output_rate == 0 {
+            return Err("sample rates must be positive".to_string());
+        }
+        let step = input_rate as f64 / output_rate as f64;
+        let cutoff = RESAMPLE_CUTOFF * (1.0 / step).min(1.0);
+        let half_width = (RESAMPLE_ZEROS / cutoff).ceil() as usize;
+        Ok(Resampler {
+            input_rate,
+            output_rate,
+            step,
+            cutoff,
+            half_width,
+            history: vec![0.0; half_width],
+            pos: half_width as f64,
+        })
+    }
+
+    pub fn input_rate(&self) -> usize {
+        self.input_rate
+    }
+
+    pub fn output_rate(&self) -> usize {
+        self.output_rate
+    }
+
+    // Delay through the filter in seconds
+    pub fn latency(&self) -> f64 {
+        self.half_width as f64 / self.input_rate as f64
+    }
+
+    // Converts the next block of the stream. The output length follows the
+    // rate ratio but varies by a sample from block to block.
+    pub fn process(&mut self, input: &[f64]) -> Vec<f64> {
+        self.history.extend_from_slice(input);
+        let mut output = Vec::with_capacity((input.len() as f64 / self.step).ceil() as usize + 1);
+        while self.pos + (self.half_width as f64) < self.history.len() as f64 {
+            output.push(self.sample_at(self.pos));
+            self.pos += self.step;
+        }
+        let consumed = (self.pos.floor() as usize).saturating_sub(self.half_width);
+        self.history.drain(..consumed);
+        self.pos -= consumed as f64;
+        output
+    }
+
+    // Forgets the stream, e.g. after a gap in the input
+    pub fn reset(&mut self) {
+        self.history = vec![0.0; self.half_width];
+        self.pos = self.half_width as f64;
+    }
+
+    fn sample_at(&self, pos: f64) -> f64 {
+        let centre = pos.floor() as usize;
+        let first = centre + 1 - self.half_width;
+        let last = (centre + self.half_width).min(self.history.len() - 1);
+        (first..=last)
+            .map(|i| {
+                let distance = pos - i as f64;
+                self.history[i] * self.cutoff * sinc(self.cutoff * distance) * blackman(distance / self.half_width as f64)
+            })
+            .sum()
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-12 { 1.0 } else { (PI * x).sin() / (PI * x) }
+}
+
+// Blackman window centred on 0, zero outside -1..1
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        return 0.0;
+    }
+    0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f64, rate: usize, len: usize) -> Vec<f64> {
+        (0..len).map(|i| (2.0 * PI * freq * i as f64 / rate as f64).sin()).collect()
+    }
+
+    // Streams `input` through in blocks of `block` samples
+    fn resample(resampler: &mut Resampler, input: &[f64], block: usize) -> Vec<f64> {
+        input.chunks(block).flat_map(|chunk| resampler.process(chunk)).collect()
+    }
+
+    #[test]
+    fn output_follows_the_rate_ratio() {
+        for (input_rate, output_rate) in [(44100, 48000), (96000, 48000), (22050, 44100), (48000, 44100)] {
+            let mut resampler = Resampler::new(input_rate, output_rate).unwrap();
+            let input = vec![0.0; input_rate];
+            let output = resample(&mut resampler, &input, 1000);
+            // One second in, one second out, less what the filter holds back
+            let expected = output_rate as f64 * (1.0 - resampler.latency());
+            assert!((output.len() as f64 - expected).abs() <= 2.0, "{} -> {}: {} samples", input_rate, output_rate, output.len());
+        }
+    }
+
+    #[test]
+    fn block_lengths_vary_by_at_most_one_sample() {
+        let mut resampler = Resampler::new(44100, 48000).unwrap();
+        // Past the lead-in
+        resampler.process(&vec![0.0; 4096]);
+        let exact = 1024.0 * 48000.0 / 44100.0;
+        for _ in 0..50 {
+            let len = resampler.process(&vec![0.0; 1024]).len() as f64;
+            assert!((len - exact).abs() < 1.0, "{} samples, expected about {}", len, exact);
+        }
+    }
+
+    #[test]
+    fn pitch_and_timing_survive_resampling() {
+        for (input_rate, output_rate) in [(44100, 48000), (96000, 48000), (32000, 44100)] {
+            let freq = 196.0;
+            let mut resampler = Resampler::new(input_rate, output_rate).unwrap();
+            let output = resample(&mut resampler, &sine(freq, input_rate, input_rate), 512);
+            // Away from the start, the output is the same sine sampled at the
+            // new rate. Output sample n sits at time n / output_rate of the
+            // input; the latency is the look-ahead it waits for, not a shift.
+            let error = (output_rate / 4..output.len())
+                .map(|n| (output[n] - (2.0 * PI * freq * n as f64 / output_rate as f64).sin()).abs())
+                .fold(0.0_f64, f64::max);
+            assert!(error < 1e-3, "{} -> {}: error {}", input_rate, output_rate, error);
+        }
+    }
+
+    #[test]
+    fn downsampling_removes_what_no_longer_fits() {
+        // 30 kHz is above the 24 kHz Nyquist frequency of the output
+        let mut resampler = Resampler::new(96000, 48000).unwrap();
+        let output = resample(&mut resampler, &sine(30000.0, 96000, 96000), 1024);
+        let peak = output[12000..].iter().fold(0.0_f64, |peak, x| peak.max(x.abs()));
+        assert!(peak < 0.01, "aliased peak {}", peak);
+    }
+
+    #[test]
+    fn zero_rates_are_rejected() {
+        assert!(Resampler::new(0, 48000).is_err());
+        assert!(Resampler::new(48000, 0).is_err());
+    }
+}
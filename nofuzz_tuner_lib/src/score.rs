@@ -0,0 +1,33 @@
+// src/score.rs
+//
+// One number for the whole instrument: RMS of the remaining per-string
+// deviations, weighted by how much each string was played, so a string
+// only brushed once doesn't count as much as the ones tuned carefully.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+// Below this RMS deviation the instrument counts as practically in tune
+pub const PRACTICALLY_IN_TUNE_CENTS: f64 = 3.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TuningScore {
+    pub rms_cents: f64,
+    // Strings that contributed
+    pub strings: usize,
+    pub practically_in_tune: bool,
+}
+
+// `residuals` are (cents, weight) per string; None when nothing was measured
+pub fn aggregate_score(residuals: &[(f64, f64)]) -> Option<TuningScore> {
+    let used: Vec<&(f64, f64)> = residuals.iter().filter(|(c, w)| c.is_finite() && *w > 0.0).collect();
+    let total: f64 = used.iter().map(|(_, w)| w).sum();
+    if used.is_empty() || total <= 0.0 {
+        return None;
+    }
+    let mean_square = used.iter().map(|(c, w)| w * c * c).sum::<f64>() / total;
+    let rms_cents = mean_square.sqrt();
+    Some(TuningScore { rms_cents, strings: used.len(), practically_in_tune: rms_cents <= PRACTICALLY_IN_TUNE_CENTS })
+}
@@ -0,0 +1,140 @@
+// src/segment.rs
+//
+// Note segmentation: turns the frame-by-frame results of a PitchTracker into
+// notes with a start, a duration and an average offset, e.g. "E2 for 1.8 s,
+// -4 cents on average", for practice logs and file analysis. Short dropouts
+// within a note (a frame or two without a reading) don't split it.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::PitchResult;
+
+// Unpitched stretches shorter than this don't end the note
+const DEFAULT_RELEASE_SECONDS: f64 = 0.15;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum NoteEvent {
+    // First reading of a note at stream time `time`
+    NoteOn { note: String, label: String, time: f64 },
+    // Every further reading of the note, with the running figures
+    Sustained { note: String, duration: f64, mean_cents: f64 },
+    // The note stopped or another one began
+    NoteOff { note: String, label: String, start: f64, duration: f64, mean_cents: f64, frames: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct NoteEvents(pub Vec<NoteEvent>);
+
+#[derive(Debug, Clone)]
+struct OpenNote {
+    note: String,
+    label: String,
+    start: f64,
+    // Stream time of the latest reading
+    last: f64,
+    cents_sum: f64,
+    frames: usize,
+}
+
+impl OpenNote {
+    fn duration(&self) -> f64 {
+        self.last - self.start
+    }
+
+    fn mean_cents(&self) -> f64 {
+        self.cents_sum / self.frames as f64
+    }
+
+    fn off(self) -> NoteEvent {
+        let (duration, mean_cents) = (self.duration(), self.mean_cents());
+        NoteEvent::NoteOff { note: self.note, label: self.label, start: self.start, duration, mean_cents, frames: self.frames }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct NoteTracker {
+    release_seconds: f64,
+    current: Option<OpenNote>,
+}
+
+impl NoteTracker {
+    // Feeds the outcome of one frame ending at stream time `time` (see
+    // PitchTracker::stream_time), None for frames without a reading
+    pub fn update(&mut self, time: f64, result: Option<&PitchResult>) -> Vec<NoteEvent> {
+        let mut events = vec![];
+        let Some(result) = result else {
+            if self.current.as_ref().is_some_and(|n| time - n.last >= self.release_seconds) {
+                events.extend(self.current.take().map(OpenNote::off));
+            }
+            return events;
+        };
+        let t = &result.tuning_to;
+        match self.current.as_mut() {
+            Some(open) if open.note == t.note => {
+                open.last = time;
+                open.cents_sum += t.cents;
+                open.frames += 1;
+                events.push(NoteEvent::Sustained { note: open.note.clone(), duration: open.duration(), mean_cents: open.mean_cents() });
+            }
+            _ => {
+                events.extend(self.current.take().map(OpenNote::off));
+                self.current = Some(OpenNote {
+                    note: t.note.clone(),
+                    label: t.label.clone(),
+                    start: time,
+                    last: time,
+                    cents_sum: t.cents,
+                    frames: 1,
+                });
+                events.push(NoteEvent::NoteOn { note: t.note.clone(), label: t.label.clone(), time });
+            }
+        }
+        events
+    }
+
+    // Ends the note still open, at the end of a file or when the input stops
+    pub fn finish(&mut self) -> Option<NoteEvent> {
+        self.current.take().map(OpenNote::off)
+    }
+
+    // Note currently open
+    pub fn note(&self) -> Option<&str> {
+        self.current.as_ref().map(|n| n.note.as_str())
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl NoteTracker {
+    // `release_ms`: how long a note may go without a reading before it ends,
+    // null for the default 150 ms
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(release_ms: Option<f64>) -> NoteTracker {
+        let release_seconds = release_ms.map_or(DEFAULT_RELEASE_SECONDS, |ms| ms.max(0.0) / 1000.0);
+        NoteTracker { release_seconds, current: None }
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = update)]
+    pub fn update_js(&mut self, time: f64, result: Option<PitchResult>) -> NoteEvents {
+        NoteEvents(self.update(time, result.as_ref()))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = finish)]
+    pub fn finish_js(&mut self) -> NoteEvents {
+        NoteEvents(self.finish().into_iter().collect())
+    }
+}
+
+impl Default for NoteTracker {
+    fn default() -> Self {
+        NoteTracker::new(None)
+    }
+}
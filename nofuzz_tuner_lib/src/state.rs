@@ -0,0 +1,234 @@
+// src/state.rs
+//
+// Everything a user sets up in the tuner, in one serializable value: the
+// web app keeps it in localStorage and the CLI in a settings file, so the
+// next launch starts with the same tuning, input filters, calibration and
+// noise floor instead of the defaults.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use crate::filters::{FilterChain, FilterStage};
+use crate::tunings::check_tuning;
+use crate::{
+    custom_tunings, register_tuning, InTuneSettings, InputKind, NoteSpelling, PitchTracker, StringInfo, Temperament,
+    TuningHandle,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TunerState {
+    pub tuning: String,
+    // Tunings registered at runtime, see register_tuning; restored before
+    // `tuning` is looked up
+    #[serde(default)]
+    pub custom_tunings: BTreeMap<String, Vec<StringInfo>>,
+    #[serde(default)]
+    pub input_kind: Option<InputKind>,
+    // Filters in the order they run, including hum notches found earlier
+    #[serde(default)]
+    pub filters: Vec<FilterStage>,
+    #[serde(default)]
+    pub calibration_ppm: f64,
+    // Background level in LUFS when the state was taken
+    #[serde(default)]
+    pub noise_floor: Option<f64>,
+    #[serde(default)]
+    pub auto_gate: bool,
+    #[serde(default)]
+    pub in_tune: InTuneSettings,
+    #[serde(default)]
+    pub temperament: Temperament,
+    // Intended offset in cents per string name
+    #[serde(default)]
+    pub string_offsets: Vec<(String, f64)>,
+    #[serde(default)]
+    pub note_spelling: Option<NoteSpelling>,
+}
+
+impl TunerState {
+    pub fn snapshot(tracker: &PitchTracker) -> TunerState {
+        let custom_tunings = custom_tunings()
+            .into_iter()
+            .filter_map(|name| TuningHandle::resolve(&name).ok().map(|t| (name, t.strings().to_vec())))
+            .collect();
+        TunerState {
+            tuning: tracker.tuning().name().to_string(),
+            custom_tunings,
+            input_kind: tracker.input_kind(),
+            filters: tracker.list_filters().to_vec(),
+            calibration_ppm: tracker.calibration_ppm(),
+            noise_floor: tracker.noise_floor(),
+            auto_gate: tracker.auto_gate(),
+            in_tune: tracker.in_tune_settings(),
+            temperament: tracker.temperament().clone(),
+            string_offsets: tracker.string_offsets().to_vec(),
+            note_spelling: tracker.note_spelling(),
+        }
+    }
+
+    // Registers the custom tunings and applies the rest to `tracker`.
+    // Everything is checked first; on error neither changes.
+    pub fn restore(&self, tracker: &mut PitchTracker) -> Result<(), String> {
+        for (name, strings) in &self.custom_tunings {
+            check_tuning(name, strings)?;
+        }
+        let tuning = match self.custom_tunings.get(&self.tuning) {
+            Some(strings) => TuningHandle::from_strings(&self.tuning, strings.clone()),
+            None => TuningHandle::resolve(&self.tuning)?,
+        };
+        let specs: Vec<_> = self.filters.iter().map(|stage| stage.spec).collect();
+        FilterChain::from_specs(&specs, tracker.sample_rate())?;
+
+        for (name, strings) in &self.custom_tunings {
+            register_tuning(name, strings.clone())?;
+        }
+        // The input kind brings its own filters and gate, both replaced below
+        tracker.set_input_kind(self.input_kind);
+        tracker.set_filters(&specs)?;
+        for (index, stage) in self.filters.iter().enumerate() {
+            tracker.set_filter_enabled(index, stage.enabled)?;
+        }
+        tracker.set_auto_gate(self.auto_gate);
+        tracker.set_noise_floor(self.noise_floor);
+        tracker.set_calibration_ppm(self.calibration_ppm);
+        tracker.set_in_tune(self.in_tune);
+        tracker.set_temperament(self.temperament.clone());
+        for (string, _) in tracker.string_offsets().to_vec() {
+            tracker.set_string_offset(&string, 0.0);
+        }
+        for (string, cents) in &self.string_offsets {
+            tracker.set_string_offset(string, *cents);
+        }
+        tracker.set_note_spelling(self.note_spelling);
+        tracker.set_tuning(tuning);
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    pub fn from_json(text: &str) -> Result<TunerState, String> {
+        serde_json::from_str(text).map_err(|e| format!("invalid tuner state JSON: {}", e))
+    }
+
+    pub fn to_yaml(&self) -> Result<String, String> {
+        serde_yaml::to_string(self).map_err(|e| e.to_string())
+    }
+
+    pub fn from_yaml(text: &str) -> Result<TunerState, String> {
+        serde_yaml::from_str(text).map_err(|e| format!("invalid tuner state YAML: {}", e))
+    }
+
+    // .json files are read as JSON, anything else as YAML
+    pub fn load_from_file(path: &str) -> Result<TunerState, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("can't read tuner state {}: {}", path, e))?;
+        if path.ends_with(".json") { TunerState::from_json(&text) } else { TunerState::from_yaml(&text) }
+    }
+
+    pub fn save_to_file(&self, path: &str) -> Result<(), String> {
+        let text = if path.ends_with(".json") { self.to_json()? } else { self.to_yaml()? };
+        std::fs::write(path, text).map_err(|e| format!("can't write tuner state {}: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::FilterSpec;
+    use crate::AcfPitchDetector;
+
+    const SAMPLE_RATE: usize = 44100;
+
+    fn tracker() -> PitchTracker {
+        let detector = AcfPitchDetector::new(60.0, 1000.0, SAMPLE_RATE);
+        PitchTracker::with_detector(Box::new(detector), TuningHandle::standard(), SAMPLE_RATE)
+    }
+
+    // Other tests register tunings concurrently, so the registry part of a
+    // snapshot can differ between two calls
+    fn settings(tracker: &PitchTracker) -> TunerState {
+        TunerState { custom_tunings: BTreeMap::new(), ..TunerState::snapshot(tracker) }
+    }
+
+    fn strings(notes: &[(&str, f64)]) -> Vec<StringInfo> {
+        notes.iter().map(|(name, frequency)| StringInfo { name: name.to_string(), frequency: *frequency }).collect()
+    }
+
+    #[test]
+    fn state_round_trips_onto_a_new_tracker() {
+        register_tuning("state-test-open-a", strings(&[("E2", 82.41), ("A2", 110.0), ("E3", 164.81), ("A3", 220.0)]))
+            .unwrap();
+        let mut tracker = tracker();
+        tracker.set_tuning(TuningHandle::resolve("state-test-open-a").unwrap());
+        tracker.set_input_kind(Some(InputKind::PiezoPickup));
+        tracker.set_filters(&[FilterSpec::Highpass { fc: 70.0, q: 0.707 }, FilterSpec::Notch { fc: 50.0, q: 10.0 }])
+            .unwrap();
+        tracker.set_filter_enabled(1, false).unwrap();
+        tracker.set_calibration_ppm(-350.0);
+        tracker.set_noise_floor(Some(-62.0));
+        tracker.set_auto_gate(true);
+        tracker.set_in_tune(InTuneSettings { tolerance_cents: 5.0, hysteresis_cents: 1.0 });
+        tracker.set_temperament(Temperament::Just { tonic: 9 });
+        tracker.set_string_offset("E2", -2.0);
+        tracker.set_note_spelling(Some(NoteSpelling::Flats));
+        let state = TunerState::snapshot(&tracker);
+
+        let json = state.to_json().unwrap();
+        let restored_state = TunerState::from_json(&json).unwrap();
+        assert_eq!(restored_state, state);
+        let mut restored = self::tracker();
+        restored_state.restore(&mut restored).unwrap();
+
+        assert_eq!(settings(&restored), TunerState { custom_tunings: BTreeMap::new(), ..state });
+        assert!(restored_state.custom_tunings.contains_key("state-test-open-a"));
+        assert_eq!(restored.tuning().name(), "state-test-open-a");
+        assert_eq!(restored.list_filters(), tracker.list_filters());
+        assert!((restored.noise_floor().unwrap() + 62.0).abs() < 1e-9);
+        assert_eq!(restored.gate(), tracker.gate());
+        assert_eq!(
+            restored.tuning().strings().iter().map(|s| s.frequency).collect::<Vec<_>>(),
+            tracker.tuning().strings().iter().map(|s| s.frequency).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn custom_tunings_are_restored_from_the_state() {
+        let state = TunerState::from_yaml(
+            "tuning: state-test-nashville\n\
+             custom_tunings:\n  state-test-nashville:\n    - {name: E3, frequency: 164.81}\n    - {name: A3, frequency: 220.0}\n",
+        )
+        .unwrap();
+        let mut tracker = tracker();
+        state.restore(&mut tracker).unwrap();
+        assert_eq!(tracker.tuning().name(), "state-test-nashville");
+        assert!(TuningHandle::resolve("state-test-nashville").is_ok());
+        assert!(custom_tunings().iter().any(|name| name == "state-test-nashville"));
+        // Defaults for everything the file leaves out
+        assert_eq!(tracker.calibration_ppm(), 0.0);
+        assert!(tracker.list_filters().is_empty());
+    }
+
+    #[test]
+    fn bad_state_leaves_the_tracker_alone() {
+        let mut tracker = tracker();
+        tracker.set_calibration_ppm(120.0);
+        let before = settings(&tracker);
+
+        let mut unknown_tuning = before.clone();
+        unknown_tuning.tuning = "state-test-no-such-tuning".to_string();
+        unknown_tuning.calibration_ppm = 0.0;
+        assert!(unknown_tuning.restore(&mut tracker).is_err());
+
+        let mut bad_filter = before.clone();
+        bad_filter.filters = vec![FilterStage { spec: FilterSpec::FirLowpass { fc: 1000.0, taps: 64 }, enabled: true }];
+        bad_filter.calibration_ppm = 0.0;
+        assert!(bad_filter.restore(&mut tracker).is_err());
+
+        assert_eq!(settings(&tracker), before);
+    }
+}
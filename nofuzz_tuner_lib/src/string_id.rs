@@ -0,0 +1,103 @@
+// src/string_id.rs
+//
+// Which physical string is ringing. The closest target is the wrong answer
+// once a string is far off, e.g. a fresh low E string sitting near G2 is
+// closer to A2. Three cues are combined as log-likelihoods: distance to each
+// string's target, continuity with the string found on the previous frame
+// (turning a peg glides, it doesn't jump), and the timbre of the frame
+// against each string's harmonic profile, learned while that string was
+// played close to its target.
+
+use crate::dsp;
+use crate::TuningHandle;
+
+// Partials in a harmonic profile
+const STRING_ID_HARMONICS: usize = 6;
+// Spread of the distance cue; strings further than the maximum are out
+const STRING_ID_SIGMA_CENTS: f64 = 150.0;
+const STRING_ID_MAX_CENTS: f64 = 600.0;
+// Bonus for the string of the previous frame while the pitch glides
+const STRING_ID_CONTINUITY: f64 = 2.0;
+// Penalty per unit of L1 distance (0..2) between harmonic profiles
+const STRING_ID_TIMBRE_WEIGHT: f64 = 4.0;
+// Profiles are learned from frames this close to the string's target...
+const STRING_ID_LEARN_CENTS: f64 = 30.0;
+// ... with this weight for the newest frame
+const STRING_ID_LEARN_RATE: f64 = 0.2;
+
+type Profile = [f64; STRING_ID_HARMONICS];
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StringIdentifier {
+    // Per string of the tuning, None until heard near its target
+    profiles: Vec<Option<Profile>>,
+    last: Option<usize>,
+}
+
+impl StringIdentifier {
+    // Forgets everything, for a new tuning whose table means other strings
+    pub(crate) fn reset(&mut self) {
+        self.profiles.clear();
+        self.last = None;
+    }
+
+    pub(crate) fn last(&self) -> Option<usize> {
+        self.last
+    }
+
+    // Index into the tuning's strings of the string most likely ringing at
+    // `freq`. `gliding` says the pitch moved on smoothly from the last frame.
+    pub(crate) fn identify(&mut self, tuning: &TuningHandle, data: &[f64], sample_rate: usize, freq: f64, gliding: bool) -> Option<usize> {
+        let strings = tuning.strings();
+        self.profiles.resize(strings.len(), None);
+        let profile = harmonic_profile(data, sample_rate, freq);
+        let best = strings
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| {
+                let cents = 1200.0 * (freq / s.frequency).log2();
+                if cents.abs() > STRING_ID_MAX_CENTS {
+                    return None;
+                }
+                let mut score = -0.5 * (cents / STRING_ID_SIGMA_CENTS).powi(2);
+                if gliding && self.last == Some(i) {
+                    score += STRING_ID_CONTINUITY;
+                }
+                if let (Some(own), Some(frame)) = (self.profiles[i].as_ref(), profile.as_ref()) {
+                    let distance: f64 = own.iter().zip(frame.iter()).map(|(a, b)| (a - b).abs()).sum();
+                    score -= STRING_ID_TIMBRE_WEIGHT * distance;
+                }
+                Some((i, cents, score))
+            })
+            .max_by(|a, b| a.2.total_cmp(&b.2));
+        self.last = best.map(|(i, _, _)| i);
+        if let (Some((i, cents, _)), Some(frame)) = (best, profile) {
+            if cents.abs() <= STRING_ID_LEARN_CENTS {
+                let own = self.profiles[i].get_or_insert(frame);
+                for (a, b) in own.iter_mut().zip(frame.iter()) {
+                    *a += STRING_ID_LEARN_RATE * (b - *a);
+                }
+            }
+        }
+        self.last
+    }
+}
+
+// Amplitudes of the first partials of `freq`, normalised to sum to 1;
+// None when they are silent
+fn harmonic_profile(data: &[f64], sample_rate: usize, freq: f64) -> Option<Profile> {
+    let mut profile = [0.0; STRING_ID_HARMONICS];
+    let nyquist = sample_rate as f64 / 2.0;
+    for (h, amplitude) in profile.iter_mut().enumerate() {
+        let partial = (h + 1) as f64 * freq;
+        if partial < nyquist {
+            *amplitude = dsp::goertzel_power(data, partial, sample_rate).sqrt();
+        }
+    }
+    let total: f64 = profile.iter().sum();
+    if total <= 0.0 || !total.is_finite() {
+        return None;
+    }
+    profile.iter_mut().for_each(|a| *a /= total);
+    Some(profile)
+}
@@ -0,0 +1,113 @@
+// src/temperament.rs
+//
+// Targets other than twelve-tone equal temperament. A temperament is a
+// table of offsets in cents from equal temperament per pitch class, counted
+// from a tonic; tunings are defined in equal temperament and shifted by the
+// offset of each string's pitch class.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use crate::notes::{freq_to_midi, pitch_class};
+use crate::{StringInfo, TuningHandle};
+
+// 5-limit just intonation over the tonic, cents above it
+const JUST_CENTS: [f64; 12] =
+    [0.0, 111.73, 203.91, 315.64, 386.31, 498.04, 590.22, 701.96, 813.69, 884.36, 1017.60, 1088.27];
+// Quarter-comma meantone, fifths of 696.58 cents, Eb to G# around the tonic
+const MEANTONE_CENTS: [f64; 12] =
+    [0.0, 76.05, 193.16, 310.26, 386.31, 503.42, 579.47, 696.58, 772.63, 889.74, 1006.84, 1082.89];
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum Temperament {
+    #[default]
+    Equal,
+    // Pitch class of the tonic, 0 = C
+    Just { tonic: usize },
+    QuarterCommaMeantone { tonic: usize },
+    // Cents above the tonic for each of the 12 pitch classes
+    Custom { tonic: usize, cents: Vec<f64> },
+}
+
+impl Temperament {
+    // equal, just or meantone; tonic is a note name without octave ("G", "Eb")
+    pub fn from_name(name: &str, tonic: &str) -> Result<Temperament, String> {
+        let tonic = pitch_class(tonic)? as usize;
+        match name {
+            "equal" => Ok(Temperament::Equal),
+            "just" => Ok(Temperament::Just { tonic }),
+            "meantone" => Ok(Temperament::QuarterCommaMeantone { tonic }),
+            _ => Err(format!("unknown temperament '{}', expected one of: equal, just, meantone", name)),
+        }
+    }
+
+    pub fn custom(tonic: usize, cents: Vec<f64>) -> Result<Temperament, String> {
+        if cents.len() != 12 || cents.iter().any(|c| !c.is_finite()) {
+            return Err(format!("a temperament needs 12 finite cent values, got {:?}", cents));
+        }
+        Ok(Temperament::Custom { tonic: tonic % 12, cents })
+    }
+
+    // Offset from equal temperament of a pitch class (0 = C), in cents
+    pub fn offset_cents(&self, pitch_class: usize) -> f64 {
+        let (tonic, table): (usize, &[f64]) = match self {
+            Temperament::Equal => return 0.0,
+            Temperament::Just { tonic } => (*tonic, &JUST_CENTS),
+            Temperament::QuarterCommaMeantone { tonic } => (*tonic, &MEANTONE_CENTS),
+            Temperament::Custom { tonic, cents } => (*tonic, cents),
+        };
+        let degree = (pitch_class + 12 - tonic % 12) % 12;
+        table[degree] - 100.0 * degree as f64
+    }
+
+    // Target of a string given at its equal tempered frequency
+    pub fn temper(&self, freq: f64) -> f64 {
+        let pitch_class = (freq_to_midi(freq).round() as i64).rem_euclid(12) as usize;
+        freq * 2f64.powf(self.offset_cents(pitch_class) / 1200.0)
+    }
+}
+
+// Stiff strings (heavy gauges, pianos) are inharmonic: partial n sits at
+// n * f0 * sqrt(1 + B n^2) instead of n * f0. Detectors follow the partials
+// as much as the fundamental, so the pitch heard and measured is sharp of
+// f0. The stretch averages the first partials, weighted 1/n.
+const STRETCH_PARTIALS: usize = 4;
+
+// Cents a string with inharmonicity coefficient `b` reads above its f0
+pub fn inharmonic_stretch_cents(b: f64) -> f64 {
+    if b <= 0.0 {
+        return 0.0;
+    }
+    let (sum, weights) = (1..=STRETCH_PARTIALS).fold((0.0, 0.0), |(sum, weights), n| {
+        let n = n as f64;
+        (sum + (1.0 + b * n * n).sqrt() / n, weights + 1.0 / n)
+    });
+    1200.0 * (sum / weights).log2()
+}
+
+impl TuningHandle {
+    // The same strings with targets moved to the temperament
+    pub fn with_temperament(&self, temperament: &Temperament) -> TuningHandle {
+        let strings = self
+            .strings()
+            .iter()
+            .map(|s| StringInfo { name: s.name.clone(), frequency: temperament.temper(s.frequency) })
+            .collect();
+        TuningHandle::from_strings(self.name(), strings)
+    }
+
+    // Targets of the named strings moved by (string name, cents) offsets
+    pub fn with_offsets(&self, offsets: &[(String, f64)]) -> TuningHandle {
+        let strings = self
+            .strings()
+            .iter()
+            .map(|s| {
+                let cents: f64 = offsets.iter().filter(|(name, _)| *name == s.name).map(|(_, c)| c).sum();
+                StringInfo { name: s.name.clone(), frequency: s.frequency * 2f64.powf(cents / 1200.0) }
+            })
+            .collect();
+        TuningHandle::from_strings(self.name(), strings)
+    }
+}
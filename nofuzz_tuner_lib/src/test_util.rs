@@ -0,0 +1,22 @@
+// src/test_util.rs
+//
+// Synthetic signals for the unit tests.
+
+// `len` samples of a note at `freq` with the given partial amplitudes,
+// the first being the fundamental
+pub(crate) fn harmonic_tone(freq: f64, partials: &[f64], sample_rate: usize, len: usize) -> Vec<f64> {
+    (0..len)
+        .map(|i| {
+            let t = i as f64 / sample_rate as f64;
+            partials
+                .iter()
+                .enumerate()
+                .map(|(h, a)| a * (2.0 * std::f64::consts::PI * (h + 1) as f64 * freq * t).sin())
+                .sum()
+        })
+        .collect()
+}
+
+pub(crate) fn cents(freq: f64, reference: f64) -> f64 {
+    1200.0 * (freq / reference).log2()
+}
@@ -0,0 +1,2174 @@
+// src/tracker.rs
+//
+// Streaming tracker: runs a detector over consecutive frames, smooths the
+// result and maps it onto the selected tuning. State survives tuning
+// changes, so switching from standard to another tuning mid-stream keeps the
+// smoothed pitch and only re-targets it.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+#[cfg(feature = "wasm")]
+use js_sys::Float64Array;
+
+use crate::agc::Agc;
+use crate::calibration::{apply_ppm, Calibration, ClockDrift, RateCheck};
+use crate::envelope::{EnvelopeFollower, NoteEnvelope};
+use crate::hum::{HumProbe, HumReport};
+use crate::filters::{DcBlocker, FilterChain, FilterSpec, FilterStage};
+#[cfg(feature = "wasm")]
+use crate::filters::{FilterSpecs, FilterStages};
+#[cfg(feature = "wasm")]
+use crate::TunerState;
+use crate::load::{now_seconds, HopController};
+use crate::onset::OnsetDetector;
+use crate::resample::Resampler;
+use crate::score::{aggregate_score, TuningScore};
+use crate::string_id::StringIdentifier;
+use crate::temperament::inharmonic_stretch_cents;
+use crate::wav::encode_wav;
+use crate::{correct_octave, dsp, freq_to_midi, freq_to_period_samples, is_dominant_pitch, probe_string, InTuneSettings, InputKind, KWeighting, NoiseFloor, NoteNotation, NoteSpelling, SILENCE_LUFS, OctavePolicy, PitchFindTrait, Temperament, TuningHandle, TuningTo};
+#[cfg(feature = "yin")]
+use crate::{InstrumentProfile, YinPitchDetector};
+
+// YIN threshold of the instrument presets, as in the default config
+#[cfg(feature = "yin")]
+const DEFAULT_YIN_THRESHOLD: f64 = 0.1;
+// Weight of the newest frame in the exponential moving average
+const DEFAULT_SMOOTHING: f64 = 0.3;
+// A jump larger than this starts a new note instead of smoothing across it
+const RESET_JUMP_CENTS: f64 = 100.0;
+// Narrowband tracking gives up once the pitch wanders this far from the
+// string it locked onto
+const LOCK_RANGE_CENTS: f64 = 200.0;
+// ... or when the tracked component carries less than this share of the
+// frame's amplitude
+const LOCK_MIN_AMPLITUDE_RATIO: f64 = 0.5;
+// Multi-resolution: the analysis window spans this many periods of the note
+// being tracked, from samples kept for up to MULTIRES_MAX_SECONDS
+const MULTIRES_PERIODS: f64 = 16.0;
+const MULTIRES_MAX_SECONDS: f64 = 0.25;
+// ... and the smoothing weight scales with sqrt(freq / this), so low notes
+// average over more frames than high ones
+const MULTIRES_REFERENCE_HZ: f64 = 196.0;
+// Dynamic block size: the window doubles while the note tracked is below
+// the first frequency and goes back to the frame above the second
+const DYNAMIC_BLOCK_GROW_HZ: f64 = 100.0;
+const DYNAMIC_BLOCK_SHRINK_HZ: f64 = 105.0;
+const DYNAMIC_BLOCK_FACTOR: usize = 2;
+
+// How the analysis window follows the note, see set_multi_resolution and
+// set_dynamic_block_size
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WindowSizing {
+    MultiResolution,
+    DynamicBlock { grown: bool },
+}
+
+// Kalman smoothing defaults, in cents squared: drift of a string between
+// frames and spread of single readings
+const DEFAULT_KALMAN_PROCESS_NOISE: f64 = 0.25;
+const DEFAULT_KALMAN_MEASUREMENT_NOISE: f64 = 4.0;
+// Readings further than this many standard deviations from the prediction
+// are outliers and skipped...
+const KALMAN_GATE_SIGMAS: f64 = 3.0;
+// ... unless this many arrive in a row, which is a new note
+const KALMAN_MAX_OUTLIERS: usize = 3;
+
+// Constant-pitch Kalman filter in cents
+#[derive(Debug, Clone, Copy)]
+struct Kalman {
+    process_noise: f64,
+    measurement_noise: f64,
+    // Variance of the estimate in cents squared
+    variance: f64,
+    outliers: usize,
+}
+
+impl Kalman {
+    fn update(&mut self, prev: Option<f64>, freq: f64) -> f64 {
+        let Some(prev) = prev else {
+            self.variance = self.measurement_noise;
+            self.outliers = 0;
+            return freq;
+        };
+        let innovation = 1200.0 * (freq / prev).log2();
+        let predicted = self.variance + self.process_noise;
+        let spread = predicted + self.measurement_noise;
+        if innovation * innovation > KALMAN_GATE_SIGMAS * KALMAN_GATE_SIGMAS * spread {
+            self.outliers += 1;
+            if self.outliers < KALMAN_MAX_OUTLIERS {
+                self.variance = predicted;
+                return prev;
+            }
+            self.variance = self.measurement_noise;
+            self.outliers = 0;
+            return freq;
+        }
+        self.outliers = 0;
+        let gain = predicted / spread;
+        self.variance = (1.0 - gain) * predicted;
+        prev * 2f64.powf(gain * innovation / 1200.0)
+    }
+}
+
+// Exponential moving average over the detected frequency, or a Kalman
+// filter or running median when one is set
+#[derive(Debug, Clone)]
+struct Smoother {
+    alpha: f64,
+    // Weight of the last update after scaling
+    last_alpha: f64,
+    kalman: Option<Kalman>,
+    // Window length and the latest readings, oldest first
+    median: Option<(usize, VecDeque<f64>)>,
+    value: Option<f64>,
+}
+
+impl Smoother {
+    fn new(alpha: f64) -> Smoother {
+        let alpha = alpha.clamp(f64::EPSILON, 1.0);
+        Smoother { alpha, last_alpha: alpha, kalman: None, median: None, value: None }
+    }
+
+    // Starts over from the next reading
+    fn reset(&mut self) {
+        self.value = None;
+        if let Some((_, window)) = self.median.as_mut() {
+            window.clear();
+        }
+    }
+
+    // Kalman variance of the value in cents squared
+    fn variance(&self) -> Option<f64> {
+        self.value?;
+        self.kalman.map(|k| k.variance)
+    }
+
+    // Frames a step in pitch lags behind, (1 - a) / a for a moving average
+    fn group_delay_frames(&self) -> f64 {
+        if let Some(k) = self.kalman {
+            let predicted = k.variance + k.process_noise;
+            let gain = predicted / (predicted + k.measurement_noise);
+            return (1.0 - gain) / gain;
+        }
+        if let Some((len, _)) = self.median.as_ref() {
+            return (*len - 1) as f64 / 2.0;
+        }
+        (1.0 - self.last_alpha) / self.last_alpha
+    }
+
+    fn update(&mut self, freq: f64) -> f64 {
+        self.update_scaled(freq, 1.0)
+    }
+
+    // Update with the weight scaled by `scale`, capped at 1. The Kalman
+    // filter and the median don't use a weight.
+    fn update_scaled(&mut self, freq: f64, scale: f64) -> f64 {
+        if let Some(kalman) = self.kalman.as_mut() {
+            let value = kalman.update(self.value, freq);
+            self.value = Some(value);
+            return value;
+        }
+        if let Some((len, window)) = self.median.as_mut() {
+            if window.len() == *len {
+                window.pop_front();
+            }
+            window.push_back(freq);
+            let mut sorted: Vec<f64> = window.iter().copied().collect();
+            sorted.sort_by(f64::total_cmp);
+            let mid = sorted.len() / 2;
+            let value = if sorted.len().is_multiple_of(2) { (sorted[mid - 1] * sorted[mid]).sqrt() } else { sorted[mid] };
+            self.value = Some(value);
+            return value;
+        }
+        let alpha = (self.alpha * scale).min(1.0);
+        self.last_alpha = alpha;
+        let value = match self.value {
+            Some(prev) if (1200.0 * (freq / prev).log2()).abs() < RESET_JUMP_CENTS => {
+                prev + alpha * (freq - prev)
+            }
+            _ => freq,
+        };
+        self.value = Some(value);
+        value
+    }
+}
+
+// A consumer's own view on the detection stream, with its own smoothing.
+// Created with PitchTracker::add_view; smoothing 1.0 gives raw values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResultView(usize);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct PitchResult {
+    // Smoothed frequency
+    pub freq: f64,
+    // The latest frame's detection before smoothing, for plotting the
+    // scatter under the needle
+    pub raw_freq: f64,
+    pub tuning_to: TuningTo,
+    // How sure the detector was of the latest frame, 0..1; UIs can dim or
+    // skip readings below e.g. 0.5
+    pub confidence: f64,
+    // Variance of freq in cents squared with Kalman smoothing, None
+    // otherwise; its square root makes a stability indicator
+    pub variance: Option<f64>,
+    // Stream time in seconds at the end of the audio the reading is from
+    pub time: f64,
+    // Seconds a change in pitch takes to show in freq: the analysis window,
+    // the group delay of the smoothing and any resampling. Line the needle
+    // up with the audio by drawing it this much later.
+    pub latency: f64,
+    // Physical string most likely ringing, an index into the tuning's
+    // strings. Unlike tuning_to.string_index it follows a string tuned far
+    // off its target, so UIs can highlight the right peg.
+    pub string_index: Option<usize>,
+}
+
+impl PitchResult {
+    // e.g. 64.23 for a slightly sharp E4
+    pub fn midi(&self) -> f64 {
+        freq_to_midi(self.freq)
+    }
+
+    pub fn period_samples(&self, sample_rate: usize) -> f64 {
+        freq_to_period_samples(self.freq, sample_rate)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub enum TrackerEvent {
+    // `envelope` is how the previous note had decayed when it was replaced
+    NoteChanged { from: Option<String>, to: String, envelope: Option<NoteEnvelope> },
+    InTune { note: String, cents: f64 },
+    OutOfTune { note: String, cents: f64 },
+    // Audio arrives at a different rate than the tracker was set up for, so
+    // every reading is off by the same interval. Usually a device switch;
+    // recreate the audio context or call set_sample_rate. Only checked for
+    // live input, see set_live_input.
+    SampleRateMismatch { expected: usize, observed: f64 },
+    // A pluck at stream time `time`, reported with onset suppression on
+    Onset { time: f64 },
+    // Mains hum was found at the start of the stream and notches at
+    // `notches` Hz were added to the filters, see set_auto_hum_notch
+    HumNotched { mains_hz: f64, notches: Vec<f64> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TrackerEvents(pub Vec<TrackerEvent>);
+
+// What the last analysed frame contained
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub enum SignalState {
+    // Below the loudness gate
+    Silent,
+    // Note has died away past the decay gate
+    Decayed,
+    // Within the hold time after a pluck, see set_onset_hold
+    Attack,
+    // Loud enough but no usable pitch
+    Unpitched,
+    Pitched,
+}
+
+// Everything an immediate-mode UI draws, read in one call so the values
+// belong to the same frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TunerSnapshot {
+    pub signal: SignalState,
+    // K-weighted loudness in LUFS
+    pub level: f64,
+    pub tuning: String,
+    pub note: Option<String>,
+    // The note in the tracker's notation, see TuningTo::label
+    pub label: Option<String>,
+    // Position of the note in the tuning's table, see TuningTo::string_index
+    pub string_index: Option<usize>,
+    pub freq: Option<f64>,
+    pub cents: Option<f64>,
+    pub in_tune: bool,
+}
+
+// Average and peak-hold cents over a user triggered measurement window, for
+// setup work where a steady number beats a moving needle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct MeasurementSummary {
+    pub note: Option<String>,
+    pub frames: usize,
+    pub average_cents: f64,
+    // Largest deviation seen, keeping its sign
+    pub peak_cents: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CentsMeasurement {
+    note: Option<String>,
+    frames: usize,
+    sum: f64,
+    peak: f64,
+}
+
+impl CentsMeasurement {
+    fn add(&mut self, tuning_to: &TuningTo) {
+        self.note = Some(tuning_to.note.clone());
+        self.frames += 1;
+        self.sum += tuning_to.cents;
+        if tuning_to.cents.abs() > self.peak.abs() {
+            self.peak = tuning_to.cents;
+        }
+    }
+
+    fn summary(&self) -> MeasurementSummary {
+        let average_cents = if self.frames > 0 { self.sum / self.frames as f64 } else { 0.0 };
+        MeasurementSummary { note: self.note.clone(), frames: self.frames, average_cents, peak_cents: self.peak }
+    }
+}
+
+// Debug hook: a sine mixed into every analysed frame (not into the input
+// device) so UI pipelines can be demonstrated with a known pitch.
+#[derive(Debug, Clone)]
+struct ToneInjection {
+    freq: f64,
+    amplitude: f64,
+    phase: f64,
+}
+
+impl ToneInjection {
+    fn mix_into(&mut self, data: &mut [f64], sample_rate: usize) {
+        let step = 2.0 * std::f64::consts::PI * self.freq / sample_rate as f64;
+        for x in data.iter_mut() {
+            *x += self.amplitude * self.phase.sin();
+            self.phase = (self.phase + step) % (2.0 * std::f64::consts::PI);
+        }
+    }
+}
+
+// Keeps the reported note from flip-flopping when the pitch sits between two
+// strings: a new note has to beat the current one by `margin_cents` for
+// `frames` consecutive frames before the label changes.
+const DEFAULT_NOTE_MARGIN_CENTS: f64 = 5.0;
+const DEFAULT_NOTE_HOLD_FRAMES: usize = 2;
+
+#[derive(Debug, Clone)]
+struct NoteHysteresis {
+    margin_cents: f64,
+    frames: usize,
+    pending: Option<(String, usize)>,
+}
+
+impl NoteHysteresis {
+    fn should_switch(&mut self, current: &TuningTo, candidate: &TuningTo) -> bool {
+        if current.cents.abs() - candidate.cents.abs() <= self.margin_cents {
+            self.pending = None;
+            return false;
+        }
+        let count = match self.pending.take() {
+            Some((note, count)) if note == candidate.note => count + 1,
+            _ => 1,
+        };
+        self.pending = Some((candidate.note.clone(), count));
+        count >= self.frames
+    }
+}
+
+// Cheap follow-up tracking after a full detection identified the string:
+// a Goertzel scan around the last frequency instead of running the detector.
+#[derive(Debug, Clone, Copy)]
+struct NarrowbandLock {
+    target: f64,
+    freq: f64,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct PitchTracker {
+    detector: Box<dyn PitchFindTrait>,
+    tuning: TuningHandle,
+    // The tuning as set, before the temperament moved its targets
+    untempered: TuningHandle,
+    temperament: Temperament,
+    // Inharmonicity coefficient B per string name
+    inharmonicity: Vec<(String, f64)>,
+    // Intended offset in cents per string name
+    string_offsets: Vec<(String, f64)>,
+    // Respells the tuning's note names, None keeps them as written
+    spelling: Option<NoteSpelling>,
+    notation: NoteNotation,
+    sample_rate: usize,
+    // Converts input at another rate to sample_rate, see set_input_rate
+    resampler: Option<Resampler>,
+    smoother: Smoother,
+    views: Vec<Smoother>,
+    note: Option<String>,
+    events: Vec<TrackerEvent>,
+    measurement: Option<CentsMeasurement>,
+    injection: Option<ToneInjection>,
+    input_kind: Option<InputKind>,
+    // Runs ahead of everything else, see set_dc_blocker
+    dc_blocker: Option<DcBlocker>,
+    filters: FilterChain,
+    // Measuring the hum at the start of the stream, see set_auto_hum_notch
+    hum_probe: Option<HumProbe>,
+    hum: Option<HumReport>,
+    // Applied to the analysis window only; levels and gates see the input
+    agc: Option<Agc>,
+    capture: Option<Vec<f64>>,
+    k_weighting: KWeighting,
+    level: f64,
+    gate_lufs: Option<f64>,
+    samples_seen: u64,
+    // (stream time in seconds, smoothed freq) of the last two results
+    previous: Option<(f64, f64)>,
+    latest: Option<(f64, f64)>,
+    // Detection of the last pitched frame, before smoothing
+    raw: Option<f64>,
+    confidence: f64,
+    envelope: EnvelopeFollower,
+    decay_gate_db: Option<f64>,
+    settle_seconds: Option<f64>,
+    settled: bool,
+    onset: Option<OnsetDetector>,
+    hop: Option<HopController>,
+    frame_len: usize,
+    // Samples in the last analysis window, see set_multi_resolution
+    window_len: usize,
+    narrowband: bool,
+    lock: Option<NarrowbandLock>,
+    // None analyses each frame as it comes
+    window_sizing: Option<WindowSizing>,
+    // Recent samples to take longer windows from
+    history: Vec<f64>,
+    octave_correction: bool,
+    dominant_pitch_gate: bool,
+    // Target mode: index of the string the user picked
+    target_string: Option<usize>,
+    in_tune_settings: InTuneSettings,
+    in_tune: bool,
+    calibration: Option<Calibration>,
+    calibration_ppm: f64,
+    // Wall clock checks of the input pace, None unless set_live_input
+    clock_checks: Option<(ClockDrift, RateCheck)>,
+    signal: SignalState,
+    noise_floor: NoiseFloor,
+    auto_gate: bool,
+    octave_policy: OctavePolicy,
+    hysteresis: NoteHysteresis,
+    string_id: StringIdentifier,
+    refine_lag: bool,
+    // Per note: latest cents and number of frames played
+    residuals: Vec<(String, f64, f64)>,
+}
+
+impl PitchTracker {
+    pub fn with_detector(detector: Box<dyn PitchFindTrait>, tuning: TuningHandle, sample_rate: usize) -> PitchTracker {
+        PitchTracker {
+            detector,
+            untempered: tuning.clone(),
+            tuning,
+            temperament: Temperament::Equal,
+            inharmonicity: vec![],
+            string_offsets: vec![],
+            spelling: None,
+            notation: NoteNotation::Scientific,
+            sample_rate,
+            resampler: None,
+            smoother: Smoother::new(DEFAULT_SMOOTHING),
+            views: vec![],
+            note: None,
+            events: vec![],
+            measurement: None,
+            injection: None,
+            input_kind: None,
+            dc_blocker: None,
+            filters: FilterChain::new(sample_rate),
+            hum_probe: None,
+            hum: None,
+            agc: None,
+            capture: None,
+            k_weighting: KWeighting::new(sample_rate),
+            level: SILENCE_LUFS,
+            gate_lufs: None,
+            samples_seen: 0,
+            previous: None,
+            latest: None,
+            raw: None,
+            confidence: 0.0,
+            envelope: EnvelopeFollower::default(),
+            decay_gate_db: None,
+            settle_seconds: None,
+            settled: true,
+            onset: None,
+            hop: None,
+            frame_len: 0,
+            window_len: 0,
+            narrowband: false,
+            window_sizing: None,
+            history: vec![],
+            octave_correction: false,
+            dominant_pitch_gate: false,
+            target_string: None,
+            lock: None,
+            in_tune_settings: InTuneSettings::default(),
+            in_tune: false,
+            calibration: None,
+            calibration_ppm: 0.0,
+            clock_checks: None,
+            signal: SignalState::Silent,
+            noise_floor: NoiseFloor::default(),
+            auto_gate: false,
+            octave_policy: OctavePolicy::Exact,
+            hysteresis: NoteHysteresis {
+                margin_cents: DEFAULT_NOTE_MARGIN_CENTS,
+                frames: DEFAULT_NOTE_HOLD_FRAMES,
+                pending: None,
+            },
+            string_id: StringIdentifier::default(),
+            refine_lag: false,
+            residuals: vec![],
+        }
+    }
+
+    pub fn detector(&self) -> &dyn PitchFindTrait {
+        self.detector.as_ref()
+    }
+
+    pub fn process(&mut self, data: &[f64]) -> Option<PitchResult> {
+        let (input_len, input_rate) = (data.len(), self.input_rate());
+        // Resampled first, so everything after runs at sample_rate
+        let mut buffer = self.resampler.as_mut().map(|resampler| resampler.process(data));
+        if self.dc_blocker.is_some() || self.injection.is_some() || self.filters.is_active() {
+            let buffer = buffer.get_or_insert_with(|| data.to_vec());
+            if let Some(dc) = self.dc_blocker.as_mut() {
+                dc.process_frame(buffer);
+            }
+            if let Some(tone) = self.injection.as_mut() {
+                tone.mix_into(buffer, self.sample_rate);
+            }
+            self.filters.process_frame(buffer);
+        }
+        let data = buffer.as_deref().unwrap_or(data);
+        let start = self.stream_time();
+        self.samples_seen += data.len() as u64;
+        self.frame_len = data.len();
+        if let Some(time) = self.onset.as_mut().and_then(|o| o.update(data, start, self.sample_rate)) {
+            self.events.push(TrackerEvent::Onset { time });
+        }
+        let stream_time = self.stream_time();
+        if let Some((drift, rate_check)) = self.clock_checks.as_mut() {
+            let wall = now_seconds();
+            drift.observe(wall, stream_time);
+            if let Some(observed) = rate_check.observe(wall, input_len, input_rate) {
+                self.events.push(TrackerEvent::SampleRateMismatch { expected: input_rate, observed });
+            }
+        }
+        if let Some(capture) = self.capture.as_mut() {
+            capture.extend_from_slice(data);
+        }
+        if let Some(report) = self.hum_probe.as_mut().and_then(|probe| probe.push(data, self.sample_rate)) {
+            self.hum_probe = None;
+            self.add_hum_notches(&report);
+            self.hum = Some(report);
+        }
+
+        if let Some(calibration) = self.calibration.as_mut() {
+            calibration.add_frame(data, self.sample_rate);
+        }
+
+        self.level = self.k_weighting.frame_loudness(data);
+        if self.gate().is_some_and(|gate| self.level < gate) {
+            self.noise_floor.update(self.level, data.len() as f64 / self.sample_rate as f64);
+            self.signal = SignalState::Silent;
+            return None;
+        }
+        self.envelope.update(data, data.len() as f64 / self.sample_rate as f64);
+        if let (Some(gate), Some(env)) = (self.decay_gate_db, self.envelope.envelope()) {
+            if env.decay_db() > gate {
+                self.signal = SignalState::Decayed;
+                return None;
+            }
+        }
+        if self.onset.as_ref().is_some_and(|o| o.in_attack(start)) {
+            // The average starts over once the attack has passed
+            self.smoother.reset();
+            self.signal = SignalState::Attack;
+            return None;
+        }
+
+        if self.hop.as_mut().is_some_and(|hop| !hop.should_analyse()) {
+            return None;
+        }
+        let started = now_seconds();
+        let mut window = self.analysis_window(data);
+        if let Some(agc) = self.agc.as_mut() {
+            agc.process_frame(window.get_or_insert_with(|| data.to_vec()), data.len() as f64 / self.sample_rate as f64);
+        }
+        self.window_len = window.as_ref().map_or(data.len(), |w| w.len());
+        let freq = self.detect(window.as_deref().unwrap_or(data));
+        if let Some(hop) = self.hop.as_mut() {
+            hop.record(now_seconds() - started, data.len() as f64 / self.sample_rate as f64);
+        }
+        let Some(freq) = freq else {
+            self.noise_floor.update(self.level, data.len() as f64 / self.sample_rate as f64);
+            self.signal = SignalState::Unpitched;
+            return None;
+        };
+        self.signal = SignalState::Pitched;
+        self.raw = Some(freq);
+        let gliding = self.smoother.value.is_some_and(|prev| (1200.0 * (freq / prev).log2()).abs() < RESET_JUMP_CENTS);
+        self.string_id.identify(&self.tuning, data, self.sample_rate, freq, gliding);
+
+        for view in self.views.iter_mut() {
+            view.update(freq);
+        }
+        if let Some(settle) = self.settle_seconds {
+            let settled = self.envelope.envelope().is_some_and(|env| env.since_peak >= settle);
+            if settled && !self.settled {
+                // First frame past the attack: the average starts over
+                // without the sharp transient
+                self.smoother.reset();
+            }
+            self.settled = settled;
+        }
+        let scale = match self.window_sizing {
+            Some(WindowSizing::MultiResolution) => (freq / MULTIRES_REFERENCE_HZ).sqrt(),
+            _ => 1.0,
+        };
+        let smoothed = self.smoother.update_scaled(freq, scale);
+        self.previous = self.latest;
+        self.latest = Some((self.stream_time(), smoothed));
+        let result = self.evaluate(smoothed, true);
+        if let Some(m) = self.measurement.as_mut().filter(|_| self.settled) {
+            m.add(&result.tuning_to);
+        }
+        let t = &result.tuning_to;
+        match self.residuals.iter_mut().find(|(note, _, _)| *note == t.note) {
+            Some(entry) => {
+                entry.1 = t.cents;
+                entry.2 += 1.0;
+            }
+            None => self.residuals.push((t.note.clone(), t.cents, 1.0)),
+        }
+        Some(result)
+    }
+
+    // With window sizing on, a window sized to the note being tracked:
+    // longer than the frame for low notes and, with multi-resolution,
+    // shorter for high ones when the detector accepts it. None analyses the
+    // frame, as for new notes.
+    fn analysis_window(&mut self, data: &[f64]) -> Option<Vec<f64>> {
+        let sizing = self.window_sizing?;
+        self.history.extend_from_slice(data);
+        let keep = ((MULTIRES_MAX_SECONDS * self.sample_rate as f64) as usize).max(DYNAMIC_BLOCK_FACTOR * data.len());
+        let excess = self.history.len().saturating_sub(keep);
+        self.history.drain(..excess);
+        let freq = self.smoother.value?;
+        let wanted = match sizing {
+            WindowSizing::MultiResolution => (MULTIRES_PERIODS * self.sample_rate as f64 / freq).ceil() as usize,
+            WindowSizing::DynamicBlock { grown } => {
+                let grown = if grown { freq <= DYNAMIC_BLOCK_SHRINK_HZ } else { freq < DYNAMIC_BLOCK_GROW_HZ };
+                self.window_sizing = Some(WindowSizing::DynamicBlock { grown });
+                if grown { DYNAMIC_BLOCK_FACTOR * data.len() } else { data.len() }
+            }
+        };
+        let wanted = wanted.min(self.history.len());
+        if wanted == data.len() || !self.detector.accepts_frame_len(wanted) {
+            return None;
+        }
+        Some(self.history[self.history.len() - wanted..].to_vec())
+    }
+
+    fn detect(&mut self, data: &[f64]) -> Option<f64> {
+        if let Some(target) = self.target_string.and_then(|i| self.tuning.strings().get(i)).map(|s| s.frequency) {
+            return self.track_target(target, data);
+        }
+        if let Some(lock) = self.lock {
+            if let Some(freq) = self.track_narrowband(lock, data) {
+                self.lock = Some(NarrowbandLock { freq, ..lock });
+                self.confidence = dsp::periodicity(data, self.sample_rate, freq);
+                return Some(freq);
+            }
+            self.lock = None;
+        }
+
+        let mut raw = self.detector.maybe_find_pitch(data)?;
+        self.confidence = self.detector.last_confidence().unwrap_or_else(|| dsp::periodicity(data, self.sample_rate, raw));
+        if self.refine_lag {
+            raw = dsp::refine_pitch(data, self.sample_rate, raw).unwrap_or(raw);
+        }
+        let mut freq = apply_ppm(raw, self.calibration_ppm);
+        if self.octave_correction {
+            freq = correct_octave(&self.tuning, data, self.sample_rate, freq);
+        }
+        if !self.tuning.in_range(freq) {
+            return None;
+        }
+        if self.dominant_pitch_gate && !is_dominant_pitch(&self.tuning, data, self.sample_rate, freq) {
+            return None;
+        }
+        if self.narrowband {
+            let target = self.tuning.find_closest_note(freq).freq;
+            self.lock = Some(NarrowbandLock { target, freq });
+        }
+        Some(freq)
+    }
+
+    fn track_narrowband(&self, lock: NarrowbandLock, data: &[f64]) -> Option<f64> {
+        // Probe the raw signal, where the pitch still carries the clock error
+        let raw = lock.freq * (1.0 + self.calibration_ppm / 1e6);
+        let (cents, amplitude) = probe_string(data, self.sample_rate, raw);
+        let freq = lock.freq * 2f64.powf(cents / 1200.0);
+        // A pure sine has amplitude sqrt(2) * rms
+        let lost = amplitude < LOCK_MIN_AMPLITUDE_RATIO * std::f64::consts::SQRT_2 * dsp::rms(data)
+            || (1200.0 * (freq / lock.target).log2()).abs() > LOCK_RANGE_CENTS;
+        if lost { None } else { Some(freq) }
+    }
+
+    // Goertzel filters around the chosen string only, no detector: nothing
+    // outside +-50 cents of it can be reported, so no octave errors
+    fn track_target(&mut self, target: f64, data: &[f64]) -> Option<f64> {
+        let raw = target * (1.0 + self.calibration_ppm / 1e6);
+        let (cents, amplitude) = probe_string(data, self.sample_rate, raw);
+        // Share of the frame in the band, as for the narrowband lock
+        let share = amplitude / (std::f64::consts::SQRT_2 * dsp::rms(data));
+        if !share.is_finite() || share < LOCK_MIN_AMPLITUDE_RATIO {
+            return None;
+        }
+        self.confidence = share.min(1.0);
+        Some(target * 2f64.powf(cents / 1200.0))
+    }
+
+    // Starts measuring the chain against a reference tone of `reference_hz`
+    // (e.g. a 440 Hz tuning fork) that the user now plays
+    pub fn start_calibration(&mut self, reference_hz: f64) {
+        self.calibration = Some(Calibration::new(reference_hz));
+    }
+
+    // Ends the measurement and applies the correction. Returns the ppm
+    // offset found, or an error when the reference wasn't heard clearly.
+    pub fn finish_calibration(&mut self) -> Result<f64, String> {
+        let calibration = self.calibration.take().ok_or("no calibration in progress")?;
+        let ppm = calibration.finish()?;
+        self.calibration_ppm = ppm;
+        Ok(ppm)
+    }
+
+    // Off by default. For audio captured live, which arrives at the pace it
+    // is recorded: the tracker then compares the input with the wall clock,
+    // reporting SampleRateMismatch events and the clock drift. Files and
+    // other input processed faster than real time must leave it off, or
+    // every run looks like a rate mismatch.
+    pub fn set_live_input(&mut self, enabled: bool) {
+        self.clock_checks = enabled.then(Default::default);
+    }
+
+    // Drift of the audio clock against the wall clock in ppm, available after
+    // about half a minute of live input. Use it to line up stream timestamps
+    // with wall clock time in long recordings.
+    pub fn clock_drift_ppm(&self) -> Option<f64> {
+        self.clock_checks.as_ref().and_then(|(drift, _)| drift.ppm())
+    }
+
+    // Capture rate of live input measured against the wall clock over the
+    // last few seconds, to compare with the rate the stream reports
+    pub fn observed_sample_rate(&self) -> Option<f64> {
+        self.clock_checks.as_ref().and_then(|(_, rate_check)| rate_check.observed())
+    }
+
+    // Rate the detector, filters and stream time run at
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    // Rate of the frames handed to process
+    pub fn input_rate(&self) -> usize {
+        self.resampler.as_ref().map_or(self.sample_rate, |r| r.input_rate())
+    }
+
+    // Accepts input at `input_rate` and resamples it to the tracker's own
+    // rate before anything else, for devices at 22.05, 32, 88.2 or 96 kHz
+    // when the detector is set up for 44.1 or 48 kHz. Frames arrive at the
+    // detector input_rate / sample_rate times shorter or longer, give or
+    // take a sample, so McLeod (fixed frame length) can't be used this way.
+    // Readings come about 0.35 ms later.
+    pub fn set_input_rate(&mut self, input_rate: usize) -> Result<(), String> {
+        self.resampler = match input_rate {
+            rate if rate == self.sample_rate => None,
+            rate => Some(Resampler::new(rate, self.sample_rate)?),
+        };
+        if let Some((_, rate_check)) = self.clock_checks.as_mut() {
+            *rate_check = RateCheck::default();
+        }
+        Ok(())
+    }
+
+    // Switches to a new input rate without losing the note, smoothing,
+    // settings or stream time. Fails, leaving the tracker unchanged, when
+    // the detector can't be re-planned. Ends resampling, see set_input_rate
+    // to keep the detector's rate instead.
+    pub fn set_sample_rate(&mut self, sample_rate: usize) -> Result<(), String> {
+        if sample_rate == 0 {
+            return Err("sample rate must be positive".to_string());
+        }
+        self.detector.set_sample_rate(sample_rate)?;
+        self.resampler = None;
+        // Keep stream time continuous across the switch
+        self.samples_seen = (self.stream_time() * sample_rate as f64).round() as u64;
+        self.sample_rate = sample_rate;
+        self.k_weighting = KWeighting::new(sample_rate);
+        self.noise_floor.reset();
+        self.filters.set_sample_rate(sample_rate);
+        if self.dc_blocker.is_some() {
+            self.dc_blocker = Some(DcBlocker::new(sample_rate));
+        }
+        if self.hum_probe.is_some() {
+            self.hum_probe = Some(HumProbe::new(sample_rate));
+        }
+        if self.clock_checks.is_some() {
+            self.clock_checks = Some(Default::default());
+        }
+        self.lock = None;
+        self.history.clear();
+        Ok(())
+    }
+
+    // Applies a previously measured correction, e.g. from a config file
+    pub fn set_calibration_ppm(&mut self, ppm: f64) {
+        self.calibration_ppm = ppm;
+    }
+
+    pub fn calibration_ppm(&self) -> f64 {
+        self.calibration_ppm
+    }
+
+    // Refines each detection with a fractional-lag fit of the difference
+    // function, for sub-cent readings during setup work
+    pub fn set_lag_refinement(&mut self, enabled: bool) {
+        self.refine_lag = enabled;
+    }
+
+    // Smooths with a constant-pitch Kalman filter instead of the moving
+    // average: readings are weighed by how much the estimate already knows,
+    // single wild readings (octave errors) are skipped and the variance of
+    // the estimate comes with each result. Noises are in cents squared, per
+    // frame for the process; None takes 0.25 and 4. Replaces median
+    // smoothing; disabled goes back to the moving average.
+    pub fn set_kalman(&mut self, enabled: bool, process_noise: Option<f64>, measurement_noise: Option<f64>) {
+        self.smoother.kalman = enabled.then(|| Kalman {
+            process_noise: process_noise.unwrap_or(DEFAULT_KALMAN_PROCESS_NOISE).max(f64::EPSILON),
+            measurement_noise: measurement_noise.unwrap_or(DEFAULT_KALMAN_MEASUREMENT_NOISE).max(f64::EPSILON),
+            variance: 0.0,
+            outliers: 0,
+        });
+        if enabled {
+            self.smoother.median = None;
+        }
+        self.smoother.reset();
+    }
+
+    // Smooths with the median of the last `frames` readings instead of the
+    // moving average. A single octave error then doesn't move the reading at
+    // all, where it would restart the average twice; a new note shows after
+    // half the window. None goes back to the moving average.
+    pub fn set_median_smoothing(&mut self, frames: Option<usize>) {
+        self.smoother.median = frames.map(|n| (n.max(1), VecDeque::with_capacity(n.max(1))));
+        if frames.is_some() {
+            self.smoother.kalman = None;
+        }
+        self.smoother.reset();
+    }
+
+    // Variance of the smoothed pitch in cents squared, Kalman smoothing only
+    pub fn pitch_variance(&self) -> Option<f64> {
+        self.smoother.variance()
+    }
+
+    // After the first full detection of a note, follow it with a narrowband
+    // scan and only go back to the detector when the lock is lost. Cheaper
+    // and steadier while fine tuning a single string.
+    pub fn set_narrowband(&mut self, enabled: bool) {
+        self.narrowband = enabled;
+        if !enabled {
+            self.lock = None;
+        }
+    }
+
+    // Sizes the analysis window to the note being tracked, about 16 periods:
+    // E2 gets a window longer than the frame for resolution, E4 a shorter
+    // one for latency, and low notes are smoothed more than high ones.
+    // Needs a detector that accepts varying frame lengths (not McLeod).
+    // Replaces the dynamic block size.
+    pub fn set_multi_resolution(&mut self, enabled: bool) {
+        let on = matches!(self.window_sizing, Some(WindowSizing::MultiResolution));
+        if enabled != on {
+            self.set_window_sizing(enabled.then_some(WindowSizing::MultiResolution));
+        }
+    }
+
+    // Doubles the analysis window, e.g. 4096 to 8192 samples, while the note
+    // tracked is below 100 Hz and goes back to the frame above 105 Hz: the
+    // low strings get the resolution, the others keep their latency.
+    // Replaces multi-resolution.
+    pub fn set_dynamic_block_size(&mut self, enabled: bool) {
+        let on = matches!(self.window_sizing, Some(WindowSizing::DynamicBlock { .. }));
+        if enabled != on {
+            self.set_window_sizing(enabled.then_some(WindowSizing::DynamicBlock { grown: false }));
+        }
+    }
+
+    fn set_window_sizing(&mut self, sizing: Option<WindowSizing>) {
+        self.window_sizing = sizing;
+        self.history.clear();
+    }
+
+    // Checks every detection against the frame's spectrum and moves it an
+    // octave when its partials say so, see correct_octave
+    pub fn set_octave_correction(&mut self, enabled: bool) {
+        self.octave_correction = enabled;
+    }
+
+    // Off by default. Drops a detection when another string of the tuning
+    // rings louder than it, most likely an open string picked up
+    // sympathetically; see is_dominant_pitch
+    pub fn set_dominant_pitch_gate(&mut self, enabled: bool) {
+        self.dominant_pitch_gate = enabled;
+    }
+
+    // Target mode: once the user has said which string they are tuning,
+    // only the band around it is measured, at a fraction of the detector's
+    // cost. The string must be within 50 cents. None goes back to detection.
+    pub fn set_target_string(&mut self, index: Option<usize>) -> Result<(), String> {
+        if let Some(i) = index.filter(|i| *i >= self.tuning.strings().len()) {
+            return Err(format!("tuning '{}' has no string {}", self.tuning.name(), i));
+        }
+        self.target_string = index;
+        self.lock = None;
+        Ok(())
+    }
+
+    pub fn target_string(&self) -> Option<usize> {
+        self.target_string
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    // Limits analysis to `budget` of real time (0.5 = half a core) by
+    // skipping frames when detection is too slow; None analyses every frame
+    pub fn set_cpu_budget(&mut self, budget: Option<f64>) {
+        self.hop = budget.map(HopController::new);
+    }
+
+    // Frames handed to process per actual analysis, 1 unless under pressure
+    pub fn hop_frames(&self) -> usize {
+        self.hop.as_ref().map_or(1, |hop| hop.hop_frames())
+    }
+
+    // Effective analyses per second at the current frame length and hop
+    pub fn analysis_rate(&self) -> f64 {
+        if self.frame_len == 0 {
+            return 0.0;
+        }
+        self.sample_rate as f64 / (self.frame_len * self.hop_frames()) as f64
+    }
+
+    // Seconds of audio processed so far, measured at the end of the last frame
+    pub fn stream_time(&self) -> f64 {
+        self.samples_seen as f64 / self.sample_rate as f64
+    }
+
+    // Estimate for display timestamp `t` (stream time, seconds) between the
+    // last two analysis results, so fast UIs don't show the needle stepping
+    // at the analysis rate. Interpolation happens in cents and doesn't
+    // extrapolate: render about one analysis interval behind to stay smooth.
+    pub fn interpolate_at(&self, t: f64) -> Option<PitchResult> {
+        let (t1, f1) = self.latest?;
+        let freq = match self.previous {
+            Some((t0, f0)) if t1 > t0 && (1200.0 * (f1 / f0).log2()).abs() < RESET_JUMP_CENTS => {
+                let alpha = ((t - t0) / (t1 - t0)).clamp(0.0, 1.0);
+                f0 * (f1 / f0).powf(alpha)
+            }
+            _ => f1,
+        };
+        Some(self.result(freq, t, self.latency()))
+    }
+
+    // Algorithmic delay of the readings in seconds, see PitchResult::latency
+    pub fn latency(&self) -> f64 {
+        self.latency_with(&self.smoother)
+    }
+
+    fn latency_with(&self, smoother: &Smoother) -> f64 {
+        let window = self.window_len as f64 / self.sample_rate as f64;
+        let rate = self.analysis_rate();
+        let smoothing = if rate > 0.0 { smoother.group_delay_frames() / rate } else { 0.0 };
+        let resampling = self.resampler.as_ref().map_or(0.0, |r| r.latency());
+        window + smoothing + resampling + self.filters.latency()
+    }
+
+    // K-weighted loudness of the last frame in LUFS, for level meters
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+
+    // Frames quieter than `gate` LUFS are skipped; None disables the gate.
+    // A manual gate overrides the automatic one.
+    pub fn set_gate(&mut self, gate: Option<f64>) {
+        self.gate_lufs = gate;
+    }
+
+    // Sets up the preprocessing and gating that suit the input, see
+    // InputKind. None removes the filters and leaves the gate alone.
+    pub fn set_input_kind(&mut self, kind: Option<InputKind>) {
+        self.input_kind = kind;
+        let specs = kind.map_or(vec![], |kind| kind.filters());
+        self.filters = FilterChain::from_specs(&specs, self.sample_rate).unwrap_or_else(|_| FilterChain::new(self.sample_rate));
+        if let Some(kind) = kind {
+            self.auto_gate = kind.auto_gate();
+        }
+    }
+
+    pub fn input_kind(&self) -> Option<InputKind> {
+        self.input_kind
+    }
+
+    // Removes any DC offset from the input before the filters, the level
+    // measurement and detection. A 5 Hz one-pole highpass; worth enabling
+    // without an input kind, whose rumble filter takes care of it otherwise.
+    pub fn set_dc_blocker(&mut self, enabled: bool) {
+        self.dc_blocker = enabled.then(|| DcBlocker::new(self.sample_rate));
+    }
+
+    // Replaces the filters run on every frame before analysis, e.g. the
+    // InputKind's with a notch for mains hum added. Nothing changes on error.
+    // The filters are the tracker's, not the detector's, so they work with
+    // any detector; the calls below address stages by list_filters index.
+    pub fn set_filters(&mut self, specs: &[FilterSpec]) -> Result<(), String> {
+        self.filters = FilterChain::from_specs(specs, self.sample_rate)?;
+        Ok(())
+    }
+
+    // Filters in the order they run, with whether each is enabled
+    pub fn list_filters(&self) -> &[FilterStage] {
+        self.filters.stages()
+    }
+
+    // Takes out the filter at `index` of list_filters, e.g. a bandpass
+    // around a string no longer being tuned
+    pub fn remove_filter(&mut self, index: usize) -> Result<FilterSpec, String> {
+        self.filters.remove(index)
+    }
+
+    pub fn clear_filters(&mut self) {
+        self.filters.clear();
+    }
+
+    // Scales each analysis window towards `target_rms` (a linear amplitude,
+    // e.g. DEFAULT_AGC_TARGET_RMS) so quiet recordings give the detector
+    // as much to work with as loud ones; None turns it off. Frames below
+    // the gate never reach it, so the gain isn't raised on silence.
+    pub fn set_agc(&mut self, target_rms: Option<f64>) {
+        self.agc = target_rms.map(Agc::new);
+    }
+
+    // Gain the AGC applied to the last window in dB
+    pub fn agc_gain_db(&self) -> Option<f64> {
+        self.agc.as_ref().map(Agc::gain_db)
+    }
+
+    // Measures mains hum over the first second of input from now on and
+    // adds notches for it to the filters, reported as a HumNotched event.
+    // Best started with the strings muted; a ringing string during that
+    // second can hide the hum. Disabling stops a measurement in progress
+    // but keeps notches already added.
+    pub fn set_auto_hum_notch(&mut self, enabled: bool) {
+        self.hum_probe = enabled.then(|| HumProbe::new(self.sample_rate));
+    }
+
+    // Outcome of the last hum measurement
+    pub fn hum_report(&self) -> Option<&HumReport> {
+        self.hum.as_ref()
+    }
+
+    fn add_hum_notches(&mut self, report: &HumReport) {
+        let mut added = vec![];
+        for spec in report.notch_filters() {
+            if self.filters.stages().iter().any(|stage| stage.spec == spec) {
+                continue;
+            }
+            if self.filters.push(spec).is_ok() {
+                if let FilterSpec::Notch { fc, .. } = spec {
+                    added.push(fc);
+                }
+            }
+        }
+        if let Some(mains_hz) = report.mains_hz.filter(|_| !added.is_empty()) {
+            self.events.push(TrackerEvent::HumNotched { mains_hz, notches: added });
+        }
+    }
+
+    // Bypasses or restores the filter at `index` without losing its place,
+    // e.g. to toggle a hum notch from the UI
+    pub fn set_filter_enabled(&mut self, index: usize, enabled: bool) -> Result<(), String> {
+        self.filters.set_enabled(index, enabled)
+    }
+
+    // Derives the gate from the noise floor measured between notes
+    pub fn set_auto_gate(&mut self, enabled: bool) {
+        self.auto_gate = enabled;
+    }
+
+    pub fn auto_gate(&self) -> bool {
+        self.auto_gate
+    }
+
+    // Gate in effect, manual or automatic
+    pub fn gate(&self) -> Option<f64> {
+        match self.gate_lufs {
+            Some(gate) => Some(gate),
+            None if self.auto_gate => self.noise_floor.gate(),
+            None => None,
+        }
+    }
+
+    // Estimated background level in LUFS
+    pub fn noise_floor(&self) -> Option<f64> {
+        self.noise_floor.floor()
+    }
+
+    // Starts from a floor measured earlier so the automatic gate works
+    // from the first frame; None measures from scratch
+    pub fn set_noise_floor(&mut self, floor: Option<f64>) {
+        self.noise_floor = floor.map_or_else(NoiseFloor::default, NoiseFloor::from_floor);
+    }
+
+    // Envelope of the note currently ringing
+    pub fn envelope(&self) -> Option<NoteEnvelope> {
+        self.envelope.envelope()
+    }
+
+    // Stops detecting once the note has decayed more than `db` below its
+    // attack peak, where the remaining signal is mostly noise. None disables.
+    pub fn set_decay_gate(&mut self, db: Option<f64>) {
+        self.decay_gate_db = db;
+    }
+
+    // Fresh plucks read sharp while the string tension settles. With a
+    // settle time the reading restarts once the note is `ms` past its attack
+    // peak and measurements skip the frames before, which agrees better with
+    // strobe tuners. None reports from the attack on.
+    pub fn set_settle_time(&mut self, ms: Option<f64>) {
+        self.settle_seconds = ms.map(|ms| ms.max(0.0) / 1000.0);
+        self.settled = self.settle_seconds.is_none();
+    }
+
+    // Suppresses readings for `ms` after each pluck, found from the rise in
+    // short-block energy; 50 ms covers the pick noise of most plucks. None
+    // turns onset detection off.
+    pub fn set_onset_hold(&mut self, ms: Option<f64>) {
+        self.onset = ms.map(|ms| OnsetDetector::new(ms.max(0.0) / 1000.0));
+    }
+
+    // False during the attack of a note when a settle time is set
+    pub fn is_settled(&self) -> bool {
+        self.settled
+    }
+
+    pub fn add_view(&mut self, smoothing: f64) -> ResultView {
+        self.views.push(Smoother::new(smoothing));
+        ResultView(self.views.len() - 1)
+    }
+
+    // Latest result as seen through a view, None before its first detection
+    pub fn view(&self, view: ResultView) -> Option<PitchResult> {
+        let smoother = self.views.get(view.0)?;
+        let (time, _) = self.latest?;
+        Some(self.result(smoother.value?, time, self.latency_with(smoother)))
+    }
+
+    // Mixes a sine of `freq` Hz into the analysis path; amplitude is
+    // relative to full scale.
+    pub fn inject_tone(&mut self, freq: f64, amplitude: f64) {
+        self.injection = Some(ToneInjection { freq, amplitude, phase: 0.0 });
+    }
+
+    pub fn clear_injection(&mut self) {
+        self.injection = None;
+    }
+
+    // Keeps a copy of every frame handed to the detector, after any
+    // preprocessing, until stop_capture is called.
+    pub fn start_capture(&mut self) {
+        self.capture = Some(vec![]);
+    }
+
+    pub fn stop_capture(&mut self) {
+        self.capture = None;
+    }
+
+    // Samples captured since the last call
+    pub fn take_captured(&mut self) -> Vec<f64> {
+        self.capture.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    // Starts (or restarts) accumulating cents readings
+    pub fn start_measurement(&mut self) {
+        self.measurement = Some(CentsMeasurement::default());
+    }
+
+    // Ends the measurement window and returns what was collected
+    pub fn stop_measurement(&mut self) -> Option<MeasurementSummary> {
+        self.measurement.take().map(|m| m.summary())
+    }
+
+    // Running summary of the current window, if one is open
+    pub fn measurement(&self) -> Option<MeasurementSummary> {
+        self.measurement.as_ref().map(|m| m.summary())
+    }
+
+    // Swaps the target table and re-targets the current smoothed pitch.
+    // Leaves target mode, the string picked may not exist in the new table.
+    // The detector's freq_max is raised when a string of the new tuning sits
+    // above it, e.g. switching to mandolin from the guitar range.
+    pub fn set_tuning(&mut self, tuning: TuningHandle) {
+        self.detector.fit_to_tuning(&tuning);
+        self.untempered = tuning;
+        self.target_string = None;
+        self.string_id.reset();
+        self.retarget();
+    }
+
+    // Recomputes the targets from the tuning as set
+    fn retarget(&mut self) {
+        let spell = |name: &String| self.spelling.map_or(name.clone(), |s| s.spell(name));
+        let offsets: Vec<(String, f64)> = self
+            .inharmonicity
+            .iter()
+            .map(|(name, b)| (spell(name), inharmonic_stretch_cents(*b)))
+            .chain(self.string_offsets.iter().map(|(name, cents)| (spell(name), *cents)))
+            .collect();
+        let spelled = match self.spelling {
+            Some(spelling) => self.untempered.with_spelling(spelling),
+            None => self.untempered.clone(),
+        };
+        self.tuning = spelled.with_temperament(&self.temperament).with_offsets(&offsets);
+        self.lock = None;
+        if let Some(smoothed) = self.smoother.value {
+            self.evaluate(smoothed, false);
+        }
+    }
+
+    // Measures against tempered targets, e.g. just intonation over the key
+    // of the piece. Applies to the current and any later tuning.
+    pub fn set_temperament(&mut self, temperament: Temperament) {
+        self.temperament = temperament;
+        self.retarget();
+    }
+
+    // Tunes the named string this many cents off its nominal pitch, as
+    // compensated nut systems (e.g. Buzz Feiten) specify; 0 removes it
+    pub fn set_string_offset(&mut self, string: &str, cents: f64) {
+        self.string_offsets.retain(|(name, _)| name != string);
+        if cents != 0.0 {
+            self.string_offsets.push((string.to_string(), cents));
+        }
+        self.retarget();
+    }
+
+    pub fn string_offsets(&self) -> &[(String, f64)] {
+        &self.string_offsets
+    }
+
+    // Stretches the target of the named string by the inharmonicity model,
+    // see inharmonic_stretch_cents. Typical B is 1e-5 for light guitar
+    // strings up to 1e-3 for heavy bass strings; 0 removes the stretch.
+    pub fn set_inharmonicity(&mut self, string: &str, b: f64) {
+        self.inharmonicity.retain(|(name, _)| name != string);
+        if b > 0.0 {
+            self.inharmonicity.push((string.to_string(), b));
+        }
+        self.retarget();
+    }
+
+    // Names notes with sharps or flats throughout, whatever the tuning
+    // table uses; chromatic mode is spelled with sharps by default
+    pub fn set_note_spelling(&mut self, spelling: Option<NoteSpelling>) {
+        self.spelling = spelling;
+        self.note = self.note.as_deref().map(|note| self.spelling.map_or(note.to_string(), |s| s.spell(note)));
+        self.retarget();
+    }
+
+    pub fn note_spelling(&self) -> Option<NoteSpelling> {
+        self.spelling
+    }
+
+    // Notation of TuningTo::label in results and of the snapshot's label
+    pub fn set_note_notation(&mut self, notation: NoteNotation) {
+        self.notation = notation;
+    }
+
+    pub fn note_notation(&self) -> NoteNotation {
+        self.notation
+    }
+
+    pub fn temperament(&self) -> &Temperament {
+        &self.temperament
+    }
+
+    // Usually taken from the preset, see RangePreset::in_tune
+    pub fn set_in_tune(&mut self, settings: InTuneSettings) {
+        self.in_tune_settings = settings;
+    }
+
+    pub fn in_tune_settings(&self) -> InTuneSettings {
+        self.in_tune_settings
+    }
+
+    // Green-light state of the current note, with hysteresis applied
+    pub fn is_in_tune(&self) -> bool {
+        self.in_tune
+    }
+
+    // A new note must beat the current one by `margin_cents` for `frames`
+    // frames in a row; frames = 1 and margin 0 switch immediately
+    pub fn set_note_hysteresis(&mut self, margin_cents: f64, frames: usize) {
+        self.hysteresis = NoteHysteresis { margin_cents: margin_cents.max(0.0), frames: frames.max(1), pending: None };
+    }
+
+    pub fn set_octave_policy(&mut self, policy: OctavePolicy) {
+        self.octave_policy = policy;
+        if let Some(smoothed) = self.smoother.value {
+            self.evaluate(smoothed, false);
+        }
+    }
+
+    pub fn tuning(&self) -> &TuningHandle {
+        &self.tuning
+    }
+
+    pub fn last_result(&self) -> Option<PitchResult> {
+        let smoothed = self.smoother.value?;
+        let (time, _) = self.latest?;
+        Some(self.result(smoothed, time, self.latency()))
+    }
+
+    pub fn snapshot(&self) -> TunerSnapshot {
+        let result = self.last_result();
+        let note = result.as_ref().map(|r| r.tuning_to.note.clone());
+        let label = result.as_ref().map(|r| r.tuning_to.label.clone());
+        let string_index = result.as_ref().map(|r| r.tuning_to.string_index);
+        TunerSnapshot {
+            signal: self.signal,
+            level: self.level,
+            tuning: self.tuning.name().to_string(),
+            note,
+            label,
+            string_index,
+            freq: result.as_ref().map(|r| r.freq),
+            cents: result.as_ref().map(|r| r.tuning_to.cents),
+            in_tune: self.in_tune,
+        }
+    }
+
+    // Aggregate score over every note played since the last reset, using
+    // each note's latest reading weighted by how long it was played
+    pub fn score(&self) -> Option<TuningScore> {
+        let residuals: Vec<(f64, f64)> = self.residuals.iter().map(|(_, cents, frames)| (*cents, *frames)).collect();
+        aggregate_score(&residuals)
+    }
+
+    // Starts a new tuning pass
+    pub fn reset_score(&mut self) {
+        self.residuals.clear();
+    }
+
+    pub fn drain_events(&mut self) -> Vec<TrackerEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    // Closest note, except that the current note is kept while the
+    // hysteresis hasn't let go of it
+    fn closest(&self, freq: f64) -> TuningTo {
+        let candidate = self.tuning.find_closest_note_with(freq, self.octave_policy);
+        let mut tuning_to = self
+            .note
+            .as_deref()
+            .filter(|note| *note != candidate.note)
+            .and_then(|note| self.tuning.tuning_to_note(freq, note, self.octave_policy))
+            .unwrap_or(candidate);
+        tuning_to.label = self.notation.render(&tuning_to.note);
+        tuning_to
+    }
+
+    // `sticky` applies note hysteresis; re-targeting after a tuning or
+    // policy change switches right away
+    fn evaluate(&mut self, smoothed: f64, sticky: bool) -> PitchResult {
+        let candidate = self.tuning.find_closest_note_with(smoothed, self.octave_policy);
+        let current = self.note.as_deref().and_then(|n| self.tuning.tuning_to_note(smoothed, n, self.octave_policy));
+        let mut tuning_to = match current {
+            Some(current) if sticky && current.note != candidate.note && !self.hysteresis.should_switch(&current, &candidate) => current,
+            _ => {
+                self.hysteresis.pending = None;
+                candidate
+            }
+        };
+        if self.note.as_deref() != Some(tuning_to.note.as_str()) {
+            self.events.push(TrackerEvent::NoteChanged {
+                from: self.note.take(),
+                to: tuning_to.note.clone(),
+                envelope: self.envelope.envelope(),
+            });
+            self.envelope.restart();
+            self.note = Some(tuning_to.note.clone());
+            self.in_tune = false;
+        }
+        let in_tune = self.in_tune_settings.update(self.in_tune, tuning_to.cents);
+        if in_tune != self.in_tune {
+            let (note, cents) = (tuning_to.note.clone(), tuning_to.cents);
+            self.events.push(if in_tune { TrackerEvent::InTune { note, cents } } else { TrackerEvent::OutOfTune { note, cents } });
+            self.in_tune = in_tune;
+        }
+        tuning_to.label = self.notation.render(&tuning_to.note);
+        PitchResult {
+            freq: smoothed,
+            raw_freq: self.raw.unwrap_or(smoothed),
+            tuning_to,
+            confidence: self.confidence,
+            variance: self.smoother.variance(),
+            time: self.stream_time(),
+            latency: self.latency(),
+            string_index: self.string_id.last(),
+        }
+    }
+
+    fn result(&self, freq: f64, time: f64, latency: f64) -> PitchResult {
+        PitchResult {
+            freq,
+            raw_freq: self.raw.unwrap_or(freq),
+            tuning_to: self.closest(freq),
+            confidence: self.confidence,
+            variance: self.smoother.variance(),
+            time,
+            latency,
+            string_index: self.string_id.last(),
+        }
+    }
+}
+
+#[cfg(feature = "yin")]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl PitchTracker {
+    // YIN based tracker on standard tuning
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(threshold: f64, freq_min: f64, freq_max: f64, sample_rate: usize) -> PitchTracker {
+        let yin = YinPitchDetector::new(threshold, freq_min, freq_max, sample_rate);
+        PitchTracker::with_detector(Box::new(yin), TuningHandle::standard(), sample_rate)
+    }
+
+    // guitar, bass, ukulele, violin, viola, cello, mandolin, banjo or voice
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = for_instrument)]
+    pub fn for_instrument_js(name: &str, sample_rate: usize) -> Result<PitchTracker, JsError> {
+        let instrument = InstrumentProfile::from_name(name).map_err(|e| JsError::new(&e))?;
+        PitchTracker::for_instrument(instrument, sample_rate).map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(feature = "yin")]
+impl PitchTracker {
+    // YIN tracker set up for the instrument: tuning, range, in-tune window
+    // and input filters. Feed it frames of
+    // instrument.settings(sample_rate).frame_len samples.
+    pub fn for_instrument(instrument: InstrumentProfile, sample_rate: usize) -> Result<PitchTracker, String> {
+        let settings = instrument.settings(sample_rate);
+        let tuning = TuningHandle::resolve(instrument.tuning())?;
+        let yin = YinPitchDetector::new(DEFAULT_YIN_THRESHOLD, settings.freq_min, settings.freq_max, sample_rate);
+        let mut tracker = PitchTracker::with_detector(Box::new(yin), tuning, sample_rate);
+        tracker.set_in_tune(instrument.in_tune());
+        tracker.set_input_kind(instrument.input_kind());
+        Ok(tracker)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl PitchTracker {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = process)]
+    pub fn process_js(&mut self, data: &Float64Array) -> Option<PitchResult> {
+        self.process(&data.to_vec())
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_tuning)]
+    pub fn set_tuning_js(&mut self, name: &str) -> Result<(), JsError> {
+        let tuning = TuningHandle::resolve(name).map_err(|e| JsError::new(&e))?;
+        self.set_tuning(tuning);
+        Ok(())
+    }
+
+    // Settings to keep across launches, e.g. JSON.stringify'd into
+    // localStorage; see TunerState
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = save_state)]
+    pub fn save_state_js(&self) -> TunerState {
+        TunerState::snapshot(self)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = restore_state)]
+    pub fn restore_state_js(&mut self, state: TunerState) -> Result<(), JsError> {
+        state.restore(self).map_err(|e| JsError::new(&e))
+    }
+
+    // Weight of the newest frame, 0 < smoothing <= 1
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn set_smoothing(&mut self, smoothing: f64) {
+        self.smoother.alpha = smoothing.clamp(f64::EPSILON, 1.0);
+        self.smoother.last_alpha = self.smoother.alpha;
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_kalman)]
+    pub fn set_kalman_js(&mut self, enabled: bool, process_noise: Option<f64>, measurement_noise: Option<f64>) {
+        self.set_kalman(enabled, process_noise, measurement_noise);
+    }
+
+    // Window in frames, null for the moving average
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_median_smoothing)]
+    pub fn set_median_smoothing_js(&mut self, frames: Option<usize>) {
+        self.set_median_smoothing(frames);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = pitch_variance)]
+    pub fn pitch_variance_js(&self) -> Option<f64> {
+        self.pitch_variance()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = stream_time)]
+    pub fn stream_time_js(&self) -> f64 {
+        self.stream_time()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = interpolate_at)]
+    pub fn interpolate_at_js(&self, t: f64) -> Option<PitchResult> {
+        self.interpolate_at(t)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = latency)]
+    pub fn latency_js(&self) -> f64 {
+        self.latency()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = level)]
+    pub fn level_js(&self) -> f64 {
+        self.level()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_gate)]
+    pub fn set_gate_js(&mut self, gate: Option<f64>) {
+        self.set_gate(gate);
+    }
+
+    // Share of real time analysis may use, e.g. 0.5; null analyses every frame
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_cpu_budget)]
+    pub fn set_cpu_budget_js(&mut self, budget: Option<f64>) {
+        self.set_cpu_budget(budget);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = hop_frames)]
+    pub fn hop_frames_js(&self) -> usize {
+        self.hop_frames()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = analysis_rate)]
+    pub fn analysis_rate_js(&self) -> f64 {
+        self.analysis_rate()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = start_calibration)]
+    pub fn start_calibration_js(&mut self, reference_hz: f64) {
+        self.start_calibration(reference_hz);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = finish_calibration)]
+    pub fn finish_calibration_js(&mut self) -> Result<f64, JsError> {
+        self.finish_calibration().map_err(|e| JsError::new(&e))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_calibration_ppm)]
+    pub fn set_calibration_ppm_js(&mut self, ppm: f64) {
+        self.set_calibration_ppm(ppm);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = calibration_ppm)]
+    pub fn calibration_ppm_js(&self) -> f64 {
+        self.calibration_ppm()
+    }
+
+    // Enable for microphone input, leave off for file analysis
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_live_input)]
+    pub fn set_live_input_js(&mut self, enabled: bool) {
+        self.set_live_input(enabled);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = clock_drift_ppm)]
+    pub fn clock_drift_ppm_js(&self) -> Option<f64> {
+        self.clock_drift_ppm()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = observed_sample_rate)]
+    pub fn observed_sample_rate_js(&self) -> Option<f64> {
+        self.observed_sample_rate()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_sample_rate)]
+    pub fn set_sample_rate_js(&mut self, sample_rate: usize) -> Result<(), JsError> {
+        self.set_sample_rate(sample_rate).map_err(|e| JsError::new(&e))
+    }
+
+    // The AudioContext's rate when the tracker was made for another one
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_input_rate)]
+    pub fn set_input_rate_js(&mut self, input_rate: usize) -> Result<(), JsError> {
+        self.set_input_rate(input_rate).map_err(|e| JsError::new(&e))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_lag_refinement)]
+    pub fn set_lag_refinement_js(&mut self, enabled: bool) {
+        self.set_lag_refinement(enabled);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_multi_resolution)]
+    pub fn set_multi_resolution_js(&mut self, enabled: bool) {
+        self.set_multi_resolution(enabled);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_dynamic_block_size)]
+    pub fn set_dynamic_block_size_js(&mut self, enabled: bool) {
+        self.set_dynamic_block_size(enabled);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_octave_correction)]
+    pub fn set_octave_correction_js(&mut self, enabled: bool) {
+        self.set_octave_correction(enabled);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_dominant_pitch_gate)]
+    pub fn set_dominant_pitch_gate_js(&mut self, enabled: bool) {
+        self.set_dominant_pitch_gate(enabled);
+    }
+
+    // Index into the tuning's strings, null for normal detection
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_target_string)]
+    pub fn set_target_string_js(&mut self, index: Option<usize>) -> Result<(), JsError> {
+        self.set_target_string(index).map_err(|e| JsError::new(&e))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_narrowband)]
+    pub fn set_narrowband_js(&mut self, enabled: bool) {
+        self.set_narrowband(enabled);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = is_locked)]
+    pub fn is_locked_js(&self) -> bool {
+        self.is_locked()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_note_hysteresis)]
+    pub fn set_note_hysteresis_js(&mut self, margin_cents: f64, frames: usize) {
+        self.set_note_hysteresis(margin_cents, frames);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_octave_policy)]
+    pub fn set_octave_policy_js(&mut self, name: &str) -> Result<(), JsError> {
+        let policy = OctavePolicy::from_name(name).map_err(|e| JsError::new(&e))?;
+        self.set_octave_policy(policy);
+        Ok(())
+    }
+
+    // equal, just or meantone over `tonic` ("C", "G", "Eb")
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_temperament)]
+    pub fn set_temperament_js(&mut self, name: &str, tonic: &str) -> Result<(), JsError> {
+        let temperament = Temperament::from_name(name, tonic).map_err(|e| JsError::new(&e))?;
+        self.set_temperament(temperament);
+        Ok(())
+    }
+
+    // Cents above the tonic for each of the 12 pitch classes, tonic 0 = C
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen]
+    pub fn set_temperament_cents(&mut self, tonic: usize, cents: Vec<f64>) -> Result<(), JsError> {
+        let temperament = Temperament::custom(tonic, cents).map_err(|e| JsError::new(&e))?;
+        self.set_temperament(temperament);
+        Ok(())
+    }
+
+    // "sharps", "flats" or null to keep the tuning's own names
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_note_spelling)]
+    pub fn set_note_spelling_js(&mut self, name: Option<String>) -> Result<(), JsError> {
+        let spelling = name.map(|n| NoteSpelling::from_name(&n)).transpose().map_err(|e| JsError::new(&e))?;
+        self.set_note_spelling(spelling);
+        Ok(())
+    }
+
+    // "scientific", "solfege" or "helmholtz"
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_note_notation)]
+    pub fn set_note_notation_js(&mut self, name: &str) -> Result<(), JsError> {
+        self.set_note_notation(NoteNotation::from_name(name).map_err(|e| JsError::new(&e))?);
+        Ok(())
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_string_offset)]
+    pub fn set_string_offset_js(&mut self, string: &str, cents: f64) {
+        self.set_string_offset(string, cents);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_inharmonicity)]
+    pub fn set_inharmonicity_js(&mut self, string: &str, b: f64) {
+        self.set_inharmonicity(string, b);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_in_tune)]
+    pub fn set_in_tune_js(&mut self, settings: InTuneSettings) {
+        self.set_in_tune(settings);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = is_in_tune)]
+    pub fn is_in_tune_js(&self) -> bool {
+        self.is_in_tune()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = envelope)]
+    pub fn envelope_js(&self) -> Option<NoteEnvelope> {
+        self.envelope()
+    }
+
+    // microphone, piezo, magnetic or line_di; None removes the filters
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_input_kind)]
+    pub fn set_input_kind_js(&mut self, name: Option<String>) -> Result<(), JsError> {
+        let kind = name.map(|n| InputKind::from_name(&n)).transpose().map_err(|e| JsError::new(&e))?;
+        self.set_input_kind(kind);
+        Ok(())
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_filters)]
+    pub fn set_filters_js(&mut self, specs: FilterSpecs) -> Result<(), JsError> {
+        self.set_filters(&specs.0).map_err(|e| JsError::new(&e))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = list_filters)]
+    pub fn list_filters_js(&self) -> FilterStages {
+        FilterStages(self.list_filters().to_vec())
+    }
+
+    // The removed filter
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = remove_filter)]
+    pub fn remove_filter_js(&mut self, index: usize) -> Result<FilterSpec, JsError> {
+        self.remove_filter(index).map_err(|e| JsError::new(&e))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = clear_filters)]
+    pub fn clear_filters_js(&mut self) {
+        self.clear_filters();
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_filter_enabled)]
+    pub fn set_filter_enabled_js(&mut self, index: usize, enabled: bool) -> Result<(), JsError> {
+        self.set_filter_enabled(index, enabled).map_err(|e| JsError::new(&e))
+    }
+
+    // Target RMS, e.g. 0.1; null turns the AGC off
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_agc)]
+    pub fn set_agc_js(&mut self, target_rms: Option<f64>) {
+        self.set_agc(target_rms);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = agc_gain_db)]
+    pub fn agc_gain_db_js(&self) -> Option<f64> {
+        self.agc_gain_db()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_dc_blocker)]
+    pub fn set_dc_blocker_js(&mut self, enabled: bool) {
+        self.set_dc_blocker(enabled);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_auto_hum_notch)]
+    pub fn set_auto_hum_notch_js(&mut self, enabled: bool) {
+        self.set_auto_hum_notch(enabled);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = hum_report)]
+    pub fn hum_report_js(&self) -> Option<HumReport> {
+        self.hum_report().cloned()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_auto_gate)]
+    pub fn set_auto_gate_js(&mut self, enabled: bool) {
+        self.set_auto_gate(enabled);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = gate)]
+    pub fn gate_js(&self) -> Option<f64> {
+        self.gate()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = noise_floor)]
+    pub fn noise_floor_js(&self) -> Option<f64> {
+        self.noise_floor()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_decay_gate)]
+    pub fn set_decay_gate_js(&mut self, db: Option<f64>) {
+        self.set_decay_gate(db);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_settle_time)]
+    pub fn set_settle_time_js(&mut self, ms: Option<f64>) {
+        self.set_settle_time(ms);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_onset_hold)]
+    pub fn set_onset_hold_js(&mut self, ms: Option<f64>) {
+        self.set_onset_hold(ms);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = is_settled)]
+    pub fn is_settled_js(&self) -> bool {
+        self.is_settled()
+    }
+
+    // Returns an id to pass to view_result
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add_view)]
+    pub fn add_view_js(&mut self, smoothing: f64) -> usize {
+        self.add_view(smoothing).0
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn view_result(&self, id: usize) -> Option<PitchResult> {
+        self.view(ResultView(id))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = inject_tone)]
+    pub fn inject_tone_js(&mut self, freq: f64, amplitude: f64) {
+        self.inject_tone(freq, amplitude);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = clear_injection)]
+    pub fn clear_injection_js(&mut self) {
+        self.clear_injection();
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = start_capture)]
+    pub fn start_capture_js(&mut self) {
+        self.start_capture();
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = stop_capture)]
+    pub fn stop_capture_js(&mut self) {
+        self.stop_capture();
+    }
+
+    // Captured audio since the last call as WAV file bytes
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn take_captured_wav(&mut self) -> Vec<u8> {
+        let samples = self.take_captured();
+        encode_wav(&samples, self.sample_rate as u32)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = start_measurement)]
+    pub fn start_measurement_js(&mut self) {
+        self.start_measurement();
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = stop_measurement)]
+    pub fn stop_measurement_js(&mut self) -> Option<MeasurementSummary> {
+        self.stop_measurement()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = measurement)]
+    pub fn measurement_js(&self) -> Option<MeasurementSummary> {
+        self.measurement()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = snapshot)]
+    pub fn snapshot_js(&self) -> TunerSnapshot {
+        self.snapshot()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = score)]
+    pub fn score_js(&self) -> Option<TuningScore> {
+        self.score()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = reset_score)]
+    pub fn reset_score_js(&mut self) {
+        self.reset_score();
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = take_events)]
+    pub fn take_events_js(&mut self) -> TrackerEvents {
+        TrackerEvents(self.drain_events())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{cents, harmonic_tone};
+    use crate::AcfPitchDetector;
+
+    const SAMPLE_RATE: usize = 48000;
+    const E2: f64 = 82.41;
+
+    fn tracker() -> PitchTracker {
+        tracker_with_range(1000.0)
+    }
+
+    fn tracker_with_range(freq_max: f64) -> PitchTracker {
+        let detector = AcfPitchDetector::new(60.0, freq_max, SAMPLE_RATE);
+        PitchTracker::with_detector(Box::new(detector), TuningHandle::standard(), SAMPLE_RATE)
+    }
+
+    // Low E through a small speaker: little fundamental, most of the energy
+    // on the 3rd and 4th partials, which fall on the B3 and E4 bands
+    fn weak_fundamental_e2() -> Vec<f64> {
+        harmonic_tone(E2, &[0.1, 0.5, 1.0, 0.8], SAMPLE_RATE, 4096)
+    }
+
+    #[test]
+    fn dominant_pitch_gate_is_off_by_default() {
+        let freq = tracker().detect(&weak_fundamental_e2()).expect("E2 detected");
+        assert!(cents(freq, E2).abs() < 10.0, "got {} Hz", freq);
+    }
+
+    #[test]
+    fn dominant_pitch_gate_keeps_weak_fundamental_e2() {
+        let mut tracker = tracker();
+        tracker.set_dominant_pitch_gate(true);
+        let freq = tracker.detect(&weak_fundamental_e2()).expect("E2 detected");
+        assert!(cents(freq, E2).abs() < 10.0, "got {} Hz", freq);
+    }
+
+    #[test]
+    fn dominant_pitch_gate_drops_sympathetic_string() {
+        // Faint E2 under a loud D3, which is no partial of E2
+        let mut data = harmonic_tone(E2, &[0.05], SAMPLE_RATE, 4096);
+        let d3 = harmonic_tone(146.83, &[1.0], SAMPLE_RATE, 4096);
+        data.iter_mut().zip(d3.iter()).for_each(|(a, b)| *a += b);
+        assert!(!is_dominant_pitch(&TuningHandle::standard(), &data, SAMPLE_RATE, E2));
+        assert!(is_dominant_pitch(&TuningHandle::standard(), &data, SAMPLE_RATE, 146.83));
+    }
+
+    const A2: f64 = 110.0;
+    const D3: f64 = 146.83;
+    // The closest string is picked in Hz, so D3 only takes over from A2
+    // past 128.4 Hz, where it is already about 36 cents closer
+    const MARGIN_CENTS: f64 = 40.0;
+
+    // Pitch between A2 and D3 (500 cents apart) that is `lead` cents closer
+    // to D3 than to A2
+    fn d3_ahead_by(lead: f64) -> f64 {
+        A2 * 2f64.powf((500.0 + lead) / 2.0 / 1200.0)
+    }
+
+    fn note_after(tracker: &mut PitchTracker, freqs: &[f64]) -> String {
+        freqs.iter().map(|&freq| tracker.evaluate(freq, true).tuning_to.note).last().expect("a frame")
+    }
+
+    fn tracker_on_a2(frames: usize) -> PitchTracker {
+        let mut tracker = tracker();
+        tracker.set_note_hysteresis(MARGIN_CENTS, frames);
+        assert_eq!(note_after(&mut tracker, &[A2]), "A2");
+        tracker
+    }
+
+    #[test]
+    fn note_hysteresis_keeps_note_just_under_the_margin() {
+        let freq = d3_ahead_by(MARGIN_CENTS - 1.0);
+        assert_eq!(TuningHandle::standard().find_closest_note(freq).note, "D3");
+        let mut tracker = tracker_on_a2(1);
+        assert_eq!(note_after(&mut tracker, &[freq; 10]), "A2");
+    }
+
+    #[test]
+    fn note_hysteresis_switches_just_over_the_margin() {
+        let mut tracker = tracker_on_a2(1);
+        assert_eq!(note_after(&mut tracker, &[d3_ahead_by(MARGIN_CENTS + 1.0)]), "D3");
+    }
+
+    #[test]
+    fn note_hysteresis_holds_for_one_frame_short() {
+        let mut tracker = tracker_on_a2(3);
+        assert_eq!(note_after(&mut tracker, &[d3_ahead_by(100.0); 2]), "A2");
+        assert_eq!(note_after(&mut tracker, &[d3_ahead_by(100.0)]), "D3");
+    }
+
+    #[test]
+    fn note_hysteresis_restarts_count_when_candidate_falls_back() {
+        let mut tracker = tracker_on_a2(3);
+        let ahead = d3_ahead_by(100.0);
+        let behind = d3_ahead_by(MARGIN_CENTS - 1.0);
+        assert_eq!(note_after(&mut tracker, &[ahead, ahead, behind, ahead, ahead]), "A2");
+        assert_eq!(note_after(&mut tracker, &[ahead]), "D3");
+    }
+
+    #[test]
+    fn kalman_converges_on_a_steady_pitch() {
+        let mut tracker = tracker();
+        tracker.set_kalman(true, None, None);
+        // Readings scattered 3 cents either side of A2
+        let reading = |i: usize| A2 * 2f64.powf([3.0, -3.0, 1.5, -1.5][i % 4] / 1200.0);
+        let mut variances = vec![];
+        let mut smoothed = 0.0;
+        for i in 0..40 {
+            smoothed = tracker.smoother.update(reading(i));
+            variances.push(tracker.pitch_variance().unwrap());
+        }
+        assert!(cents(smoothed, A2).abs() < 1.0, "{} cents off", cents(smoothed, A2));
+        // Variance starts at the measurement noise and falls to a steady state
+        assert_eq!(variances[0], DEFAULT_KALMAN_MEASUREMENT_NOISE);
+        assert!(variances.windows(2).all(|w| w[1] <= w[0] + 1e-12));
+        assert!(variances[39] < DEFAULT_KALMAN_MEASUREMENT_NOISE / 2.0);
+        assert!((variances[39] - variances[38]).abs() < 1e-6);
+        // Once settled a single reading moves it much less than the reading moved
+        let before = tracker.smoother.value.unwrap();
+        let after = tracker.smoother.update(A2 * 2f64.powf(6.0 / 1200.0));
+        assert!(cents(after, before) < 3.0);
+    }
+
+    #[test]
+    fn kalman_restarts_on_note_change() {
+        let mut tracker = tracker();
+        tracker.set_kalman(true, None, None);
+        // Only the Kalman filter holds the note back
+        tracker.set_note_hysteresis(0.0, 1);
+        let frame = |freq: f64| harmonic_tone(freq, &[1.0, 0.5, 0.25], SAMPLE_RATE, 4096);
+        for _ in 0..20 {
+            tracker.process(&frame(A2));
+        }
+        assert!(tracker.pitch_variance().unwrap() < DEFAULT_KALMAN_MEASUREMENT_NOISE / 2.0);
+        // A new note is an outlier at first, the estimate stays on A2 ...
+        for _ in 1..KALMAN_MAX_OUTLIERS {
+            let result = tracker.process(&frame(D3)).expect("pitched");
+            assert_eq!(result.tuning_to.note, "A2");
+        }
+        // ... until enough arrive in a row, then it restarts on D3 without
+        // gliding over from A2
+        let result = tracker.process(&frame(D3)).expect("pitched");
+        assert_eq!(result.tuning_to.note, "D3");
+        assert!(result.tuning_to.cents.abs() < 5.0, "{} cents", result.tuning_to.cents);
+        assert_eq!(tracker.pitch_variance(), Some(DEFAULT_KALMAN_MEASUREMENT_NOISE));
+    }
+
+    #[test]
+    fn set_tuning_raises_detector_range_to_reach_high_strings() {
+        // E5 on a tracker left at the guitar range
+        let mut tracker = tracker_with_range(500.0);
+        tracker.set_tuning(TuningHandle::resolve("mandolin-gdae").unwrap());
+        let e5 = harmonic_tone(659.26, &[1.0, 0.4, 0.2], SAMPLE_RATE, 4096);
+        let freq = tracker.detect(&e5).expect("E5 detected");
+        assert!(cents(freq, 659.26).abs() < 10.0, "got {} Hz", freq);
+    }
+    // The DC blocker, injected tone and filters see the same input whether
+    // or not it was resampled first
+    #[test]
+    fn preprocessing_applies_after_resampling() {
+        for input_rate in [SAMPLE_RATE, 44100] {
+            let mut tracker = tracker();
+            tracker.set_input_rate(input_rate).unwrap();
+            tracker.set_dc_blocker(true);
+            tracker.set_filters(&[FilterSpec::Lowpass { fc: 1000.0, q: 0.707 }]).unwrap();
+            tracker.inject_tone(110.0, 0.5);
+            tracker.start_capture();
+            let offset: Vec<f64> = vec![0.5; input_rate];
+            for frame in offset.chunks(4096) {
+                tracker.process(frame);
+            }
+            let captured = tracker.take_captured();
+            assert!((captured.len() as f64 - SAMPLE_RATE as f64).abs() < 4096.0, "{} samples", captured.len());
+            // Offset removed, the injected A2 left and detected
+            let tail = &captured[captured.len() / 2..];
+            let mean = tail.iter().sum::<f64>() / tail.len() as f64;
+            assert!(mean.abs() < 0.01, "mean {} at {} Hz", mean, input_rate);
+            let result = tracker.last_result().expect("A2 detected");
+            assert!(cents(result.freq, 110.0).abs() < 10.0, "got {} Hz at {} Hz", result.freq, input_rate);
+        }
+    }
+}
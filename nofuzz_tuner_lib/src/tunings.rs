@@ -0,0 +1,634 @@
+// src/tunings.rs
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use lazy_static::lazy_static;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::notes::{cents_between, freq_to_midi, midi_to_freq, midi_to_name, note_to_freq, NoteSpelling, A4_HZ};
+
+pub const DEFAULT_TUNING: &str = "standard";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct StringInfo {
+    pub name: String,
+    pub frequency: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct GuitarStrings(pub Vec<StringInfo>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct StringMatch {
+    pub name: String,
+    pub frequency: f64,
+    pub distance: f64,
+}
+
+// The string a frequency is closest to and how far off it is
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TuningTo {
+    pub note: String,
+    // The note as shown to the user, see NoteNotation; same as note unless
+    // the tracker renders another notation
+    pub label: String,
+    pub freq: f64,
+    // Detected minus target, in Hz
+    pub distance: f64,
+    pub cents: f64,
+    pub direction: TuneDirection,
+    // Position of the string in the tuning's table
+    pub string_index: usize,
+}
+
+// Closer than this counts as on pitch; nobody can turn a peg finer
+const ON_PITCH_CENTS: f64 = 0.5;
+
+// Which way the string has to go
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub enum TuneDirection {
+    // Flat, tighten the string
+    Up,
+    // Sharp, loosen the string
+    Down,
+    OnPitch,
+}
+
+impl TuneDirection {
+    pub fn from_cents(cents: f64) -> TuneDirection {
+        if cents.abs() < ON_PITCH_CENTS {
+            TuneDirection::OnPitch
+        } else if cents < 0.0 {
+            TuneDirection::Up
+        } else {
+            TuneDirection::Down
+        }
+    }
+}
+
+impl TuningTo {
+    fn new(note: String, freq: f64, distance: f64, cents: f64, string_index: usize) -> TuningTo {
+        TuningTo { label: note.clone(), note, freq, distance, cents, direction: TuneDirection::from_cents(cents), string_index }
+    }
+}
+
+// One row of a multi-tuning comparison
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TuningComparison {
+    pub tuning: String,
+    pub tuning_to: TuningTo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TuningComparisons(pub Vec<TuningComparison>);
+
+// How the octave of a detection is treated when matching it to a string.
+// Harmonic-rich sources often make detectors report the 2nd harmonic; for
+// tuning only the cents matter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub enum OctavePolicy {
+    // Closest string at the detected octave
+    #[default]
+    Exact,
+    // Note name without octave, cents folded into one octave
+    PitchClass,
+    // Folded onto the closest string by pitch class, reported at its octave
+    SnapToString,
+}
+
+impl OctavePolicy {
+    pub fn from_name(name: &str) -> Result<OctavePolicy, String> {
+        match name {
+            "exact" => Ok(OctavePolicy::Exact),
+            "pitch_class" => Ok(OctavePolicy::PitchClass),
+            "snap_to_string" => Ok(OctavePolicy::SnapToString),
+            _ => Err(format!(
+                "unknown octave policy '{}', expected one of: exact, pitch_class, snap_to_string",
+                name
+            )),
+        }
+    }
+}
+
+// Strings from note names, so a new tuning is one line of notes
+fn from_notes(notes: &[&str]) -> Vec<StringInfo> {
+    notes
+        .iter()
+        .map(|n| StringInfo { name: n.to_string(), frequency: note_to_freq(n, A4_HZ).expect("valid note name") })
+        .collect()
+}
+
+// A tuning derived from `base`: every string moved by `semitones`, and the
+// lowest string by `drop` semitones more (drop tunings). Down-tuned
+// variants are spelled with flats, as players usually name them.
+pub fn shifted_tuning(base: &[StringInfo], semitones: i32, drop: i32) -> Vec<StringInfo> {
+    let lowest = base.iter().map(|s| s.frequency).fold(f64::INFINITY, f64::min);
+    base.iter()
+        .map(|s| {
+            let shift = if s.frequency == lowest { semitones - drop } else { semitones };
+            let midi = freq_to_midi(s.frequency).round() as i32 + shift;
+            StringInfo { name: midi_to_name(midi, shift < 0), frequency: s.frequency * 2f64.powf(shift as f64 / 12.0) }
+        })
+        .collect()
+}
+
+// Registers `name` as `base` shifted, see shifted_tuning
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn register_shifted_tuning(name: &str, base: &str, semitones: i32, drop: i32) -> Result<(), JsError> {
+    let base = TuningHandle::resolve(base).map_err(|e| JsError::new(&e))?;
+    register_tuning(name, shifted_tuning(base.strings(), semitones, drop)).map_err(|e| JsError::new(&e))
+}
+
+// Tunings by name, strings in the order they sit on the instrument: lowest to
+// highest, except that re-entrant tunings keep the physical string order.
+// Applications can add their own with register_tuning.
+lazy_static! {
+    static ref TUNINGS: RwLock<HashMap<String, Vec<StringInfo>>> = RwLock::new({
+        let mut m = HashMap::new();
+        let standard = from_notes(&["E2", "A2", "D3", "G3", "B3", "E4"]);
+        for (name, semitones, drop) in [
+            ("half_step_down", -1, 0),
+            ("full_step_down", -2, 0),
+            ("drop_d", 0, 2),
+            ("drop_c", -2, 2),
+            ("drop_b", -3, 2),
+            ("drop_a", -5, 2),
+        ] {
+            m.insert(name.to_string(), shifted_tuning(&standard, semitones, drop));
+        }
+        m.insert(DEFAULT_TUNING.to_string(), standard);
+        m.insert("standard-b7".to_string(), from_notes(&["B1", "E2", "A2", "D3", "G3", "B3", "E4"]));
+        m.insert("standard-f#8".to_string(), from_notes(&["F#1", "B1", "E2", "A2", "D3", "G3", "B3", "E4"]));
+        m.insert("bass_standard".to_string(), from_notes(&["E1", "A1", "D2", "G2"]));
+        m.insert("bass_drop_d".to_string(), from_notes(&["D1", "A1", "D2", "G2"]));
+        m.insert("bass_bead".to_string(), from_notes(&["B0", "E1", "A1", "D2"]));
+        m.insert("bass_5_string".to_string(), from_notes(&["B0", "E1", "A1", "D2", "G2"]));
+        m.insert("bass_6_string".to_string(), from_notes(&["B0", "E1", "A1", "D2", "G2", "C3"]));
+        m.insert("dadgad".to_string(), from_notes(&["D2", "A2", "D3", "G3", "A3", "D4"]));
+        m.insert("open_g".to_string(), from_notes(&["D2", "G2", "D3", "G3", "B3", "D4"]));
+        m.insert("open_d".to_string(), from_notes(&["D2", "A2", "D3", "F#3", "A3", "D4"]));
+        m.insert("open_c".to_string(), from_notes(&["C2", "G2", "C3", "G3", "C4", "E4"]));
+        m.insert("open_e".to_string(), from_notes(&["E2", "B2", "E3", "G#3", "B3", "E4"]));
+        m.insert("violin".to_string(), from_notes(&["G3", "D4", "A4", "E5"]));
+        m.insert("viola".to_string(), from_notes(&["C3", "G3", "D4", "A4"]));
+        m.insert("cello".to_string(), from_notes(&["C2", "G2", "D3", "A3"]));
+        // Courses of two strings count as one string
+        m.insert("mandolin-gdae".to_string(), from_notes(&["G3", "D4", "A4", "E5"]));
+        // Short drone string first, like on the instrument
+        m.insert("banjo-open-g".to_string(), from_notes(&["G4", "D3", "G3", "B3", "D4"]));
+        // High G on the string nearest the player's chin
+        m.insert("ukulele-gcea".to_string(), from_notes(&["G4", "C4", "E4", "A4"]));
+        // Every note from C2 to B5, for singers and anything without a fixed tuning
+        m.insert(
+            "chromatic".to_string(),
+            (36..84)
+                .map(|midi| StringInfo { name: midi_to_name(midi, false), frequency: midi_to_freq(midi as f64, A4_HZ) })
+                .collect(),
+        );
+        m.insert("ukulele-low-g".to_string(), from_notes(&["G3", "C4", "E4", "A4"]));
+        m
+    });
+    // Names added with register_tuning, in the order they were first added
+    static ref CUSTOM_TUNINGS: RwLock<Vec<String>> = RwLock::new(vec![]);
+}
+
+// Adds (or replaces) a tuning that resolve() and everything built on
+// TuningHandle will find by name. Strings keep the order given, like the
+// built-in tunings, so string_index matches the caller's table.
+pub fn register_tuning(name: &str, notes: Vec<StringInfo>) -> Result<(), String> {
+    check_tuning(name, &notes)?;
+    TUNINGS.write().unwrap().insert(name.to_string(), notes);
+    let mut custom = CUSTOM_TUNINGS.write().unwrap();
+    if !custom.iter().any(|n| n == name) {
+        custom.push(name.to_string());
+    }
+    Ok(())
+}
+
+// Tunings registered at runtime, including built-in names that were
+// replaced; what an application has to save to get them back
+pub fn custom_tunings() -> Vec<String> {
+    CUSTOM_TUNINGS.read().unwrap().clone()
+}
+
+pub(crate) fn check_tuning(name: &str, notes: &[StringInfo]) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("tuning name must not be empty".to_string());
+    }
+    if name == DEFAULT_TUNING {
+        return Err(format!("the built-in '{}' tuning can't be replaced", DEFAULT_TUNING));
+    }
+    if notes.is_empty() {
+        return Err(format!("tuning '{}' has no strings", name));
+    }
+    if let Some(bad) = notes.iter().find(|s| !(s.frequency.is_finite() && s.frequency > 0.0)) {
+        return Err(format!("string {} of tuning '{}' has invalid frequency {}", bad.name, name, bad.frequency));
+    }
+    // Notes are told apart by name, a second E4 would never be matched
+    if let Some((i, dup)) = notes.iter().enumerate().find(|(i, s)| notes[..*i].iter().any(|o| o.name == s.name)) {
+        return Err(format!("tuning '{}' names string {} twice (string {})", name, dup.name, i + 1));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn check_tuning_range(name: &str, freq_min: f64, freq_max: f64) -> Result<(), JsError> {
+    let tuning = TuningHandle::resolve(name).map_err(|e| JsError::new(&e))?;
+    tuning.check_range(freq_min, freq_max).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = register_tuning)]
+pub fn register_tuning_js(name: &str, notes: GuitarStrings) -> Result<(), JsError> {
+    register_tuning(name, notes.0).map_err(|e| JsError::new(&e))
+}
+
+// A string in a tunings file, either a note name ("D2") or a name with an
+// explicit frequency for anything that isn't equal tempered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum StringSpec {
+    Note(String),
+    Info(StringInfo),
+}
+
+// Tuning names, e.g. the ones a file registered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TuningNames(pub Vec<String>);
+
+// A library of custom tunings in YAML or JSON, keyed by tuning name:
+//
+//   nashville:
+//     - E3
+//     - { name: A3, frequency: 220.0 }
+//
+// Loading registers every tuning in the file, so the CLI and the web app
+// can ship the same file.
+pub struct Tunings;
+
+impl Tunings {
+    pub fn load_from_yaml(text: &str) -> Result<Vec<String>, String> {
+        let file: BTreeMap<String, Vec<StringSpec>> =
+            serde_yaml::from_str(text).map_err(|e| format!("invalid tunings YAML: {}", e))?;
+        Tunings::register_all(file)
+    }
+
+    pub fn load_from_json(text: &str) -> Result<Vec<String>, String> {
+        let file: BTreeMap<String, Vec<StringSpec>> =
+            serde_json::from_str(text).map_err(|e| format!("invalid tunings JSON: {}", e))?;
+        Tunings::register_all(file)
+    }
+
+    // .json files are read as JSON, anything else as YAML
+    pub fn load_from_file(path: &str) -> Result<Vec<String>, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("can't read tunings file {}: {}", path, e))?;
+        if path.ends_with(".json") { Tunings::load_from_json(&text) } else { Tunings::load_from_yaml(&text) }
+    }
+
+    pub fn to_yaml(names: &[&str]) -> Result<String, String> {
+        serde_yaml::to_string(&Tunings::collect(names)?).map_err(|e| e.to_string())
+    }
+
+    pub fn to_json(names: &[&str]) -> Result<String, String> {
+        serde_json::to_string_pretty(&Tunings::collect(names)?).map_err(|e| e.to_string())
+    }
+
+    pub fn save_to_file(path: &str, names: &[&str]) -> Result<(), String> {
+        let text = if path.ends_with(".json") { Tunings::to_json(names)? } else { Tunings::to_yaml(names)? };
+        std::fs::write(path, text).map_err(|e| format!("can't write tunings file {}: {}", path, e))
+    }
+
+    fn collect(names: &[&str]) -> Result<BTreeMap<String, Vec<StringInfo>>, String> {
+        names
+            .iter()
+            .map(|name| TuningHandle::resolve(name).map(|t| (name.to_string(), t.strings().to_vec())))
+            .collect()
+    }
+
+    // Checks the whole file before registering anything
+    fn register_all(file: BTreeMap<String, Vec<StringSpec>>) -> Result<Vec<String>, String> {
+        let mut tunings = vec![];
+        for (name, specs) in file {
+            let notes = specs
+                .into_iter()
+                .map(|spec| match spec {
+                    StringSpec::Info(info) => Ok(info),
+                    StringSpec::Note(note) => note_to_freq(&note, A4_HZ)
+                        .map(|frequency| StringInfo { name: note.clone(), frequency })
+                        .map_err(|e| format!("tuning '{}': {}", name, e)),
+                })
+                .collect::<Result<Vec<StringInfo>, String>>()?;
+            check_tuning(&name, &notes)?;
+            tunings.push((name, notes));
+        }
+        let mut names = vec![];
+        for (name, notes) in tunings {
+            register_tuning(&name, notes)?;
+            names.push(name);
+        }
+        Ok(names)
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn load_tunings_yaml(text: &str) -> Result<TuningNames, JsError> {
+    Tunings::load_from_yaml(text).map(TuningNames).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn load_tunings_json(text: &str) -> Result<TuningNames, JsError> {
+    Tunings::load_from_json(text).map(TuningNames).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn save_tunings_json(names: TuningNames) -> Result<String, JsError> {
+    let names: Vec<&str> = names.0.iter().map(|n| n.as_str()).collect();
+    Tunings::to_json(&names).map_err(|e| JsError::new(&e))
+}
+
+// How far outside the lowest/highest string a detection may fall before it
+// is treated as a harmonic or noise rather than a string being tuned. Half an
+// octave keeps badly detuned strings but drops the 2nd harmonic of E4.
+const RANGE_MARGIN_SEMITONES: f64 = 6.0;
+
+// A tuning looked up by name once. Per-frame code works on the handle so it
+// never repeats the lookup or has to deal with a missing name.
+#[derive(Debug, Clone)]
+pub struct TuningHandle {
+    name: String,
+    strings: Vec<StringInfo>,
+}
+
+impl TuningHandle {
+    pub fn resolve(name: &str) -> Result<TuningHandle, String> {
+        let tunings = TUNINGS.read().unwrap();
+        match tunings.get(name) {
+            Some(strings) => Ok(TuningHandle { name: name.to_string(), strings: strings.clone() }),
+            None => {
+                let mut known: Vec<&String> = tunings.keys().collect();
+                known.sort();
+                Err(format!("unknown tuning '{}', expected one of: {:?}", name, known))
+            }
+        }
+    }
+
+    pub(crate) fn from_strings(name: &str, strings: Vec<StringInfo>) -> TuningHandle {
+        TuningHandle { name: name.to_string(), strings }
+    }
+
+    // The same strings with their names respelled, e.g. Eb2 instead of D#2
+    pub fn with_spelling(&self, spelling: NoteSpelling) -> TuningHandle {
+        let strings = self
+            .strings
+            .iter()
+            .map(|s| StringInfo { name: spelling.spell(&s.name), frequency: s.frequency })
+            .collect();
+        TuningHandle::from_strings(&self.name, strings)
+    }
+
+    pub fn standard() -> TuningHandle {
+        TuningHandle::resolve(DEFAULT_TUNING).expect("standard tuning is built in")
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn strings(&self) -> &[StringInfo] {
+        &self.strings
+    }
+
+    pub fn find_string_and_distance(&self, freq: f64) -> (f64, f64, String) {
+        let mut min_distance = f64::INFINITY;
+        let mut string_freq = 0.0;
+        let mut string_key = "".to_string();
+        for s in self.strings.iter() {
+            let distance = freq - s.frequency;
+            if distance.abs() < min_distance.abs() {
+                min_distance = distance;
+                string_freq = s.frequency;
+                string_key = s.name.clone();
+            }
+        }
+        (string_freq, min_distance, string_key)
+    }
+
+    pub fn find_closest_note(&self, freq: f64) -> TuningTo {
+        let (string_freq, distance, note) = self.find_string_and_distance(freq);
+        let index = self.strings.iter().position(|s| s.frequency == string_freq && s.name == note).unwrap_or(0);
+        TuningTo::new(note, string_freq, distance, cents_between(freq, string_freq), index)
+    }
+
+    pub fn find_closest_note_with(&self, freq: f64, policy: OctavePolicy) -> TuningTo {
+        if policy == OctavePolicy::Exact {
+            return self.find_closest_note(freq);
+        }
+        let best = self
+            .strings
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                // Distance to the nearest octave of the string, -600..600 cents
+                let cents = cents_between(freq, s.frequency);
+                (cents - 1200.0 * (cents / 1200.0).round(), i, s)
+            })
+            .min_by(|a, b| a.0.abs().total_cmp(&b.0.abs()));
+        let Some((cents, index, s)) = best else {
+            return self.find_closest_note(freq);
+        };
+        let note = match policy {
+            OctavePolicy::PitchClass => s.name.trim_end_matches(|c: char| c.is_ascii_digit()).to_string(),
+            _ => s.name.clone(),
+        };
+        let folded_freq = s.frequency * 2f64.powf(cents / 1200.0);
+        TuningTo::new(note, s.frequency, folded_freq - s.frequency, cents, index)
+    }
+
+    // Like find_closest_note_with, but measured against the string called
+    // `note` (as reported under `policy`) even if another one is closer
+    pub fn tuning_to_note(&self, freq: f64, note: &str, policy: OctavePolicy) -> Option<TuningTo> {
+        let index = self.strings.iter().position(|s| match policy {
+            OctavePolicy::PitchClass => s.name.trim_end_matches(|c: char| c.is_ascii_digit()) == note,
+            _ => s.name == note,
+        })?;
+        let s = &self.strings[index];
+        let mut cents = cents_between(freq, s.frequency);
+        if policy != OctavePolicy::Exact {
+            cents -= 1200.0 * (cents / 1200.0).round();
+        }
+        let target_octave_freq = s.frequency * 2f64.powf(cents / 1200.0);
+        Some(TuningTo::new(note.to_string(), s.frequency, target_octave_freq - s.frequency, cents, index))
+    }
+
+    pub fn lowest(&self) -> f64 {
+        self.strings.iter().map(|s| s.frequency).fold(f64::INFINITY, f64::min)
+    }
+
+    pub fn highest(&self) -> f64 {
+        self.strings.iter().map(|s| s.frequency).fold(0.0, f64::max)
+    }
+
+    // Err naming the strings a detector limited to freq_min..freq_max
+    // would never report
+    pub fn check_range(&self, freq_min: f64, freq_max: f64) -> Result<(), String> {
+        let outside: Vec<String> = self
+            .strings
+            .iter()
+            .filter(|s| s.frequency < freq_min || s.frequency > freq_max)
+            .map(|s| format!("{} ({:.2} Hz)", s.name, s.frequency))
+            .collect();
+        if outside.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "tuning '{}' has strings outside the detection range {}..{} Hz: {}",
+            self.name, freq_min, freq_max, outside.join(", ")
+        ))
+    }
+
+    pub fn in_range(&self, freq: f64) -> bool {
+        let (lowest, highest) = (self.lowest(), self.highest());
+        let margin = 2f64.powf(RANGE_MARGIN_SEMITONES / 12.0);
+        freq.is_finite() && freq >= lowest / margin && freq <= highest * margin
+    }
+
+    // Like find_string_and_distance, but rejects detections far outside the
+    // range of the strings.
+    pub fn maybe_find_string_and_distance(&self, freq: f64) -> Option<(f64, f64, String)> {
+        if !self.in_range(freq) {
+            return None;
+        }
+        Some(self.find_string_and_distance(freq))
+    }
+}
+
+impl Default for TuningHandle {
+    fn default() -> Self {
+        TuningHandle::standard()
+    }
+}
+
+// Closest note for `freq` under each of the named tunings, in the given
+// order, so players deciding between tunings can see the readouts side by side
+pub fn compare_tunings(freq: f64, tunings: &[TuningHandle]) -> Vec<TuningComparison> {
+    tunings
+        .iter()
+        .map(|t| TuningComparison { tuning: t.name().to_string(), tuning_to: t.find_closest_note(freq) })
+        .collect()
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = compare_tunings)]
+pub fn compare_tunings_js(freq: f64, names: Vec<String>) -> Result<TuningComparisons, JsError> {
+    let tunings = names
+        .iter()
+        .map(|name| TuningHandle::resolve(name))
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(|e| JsError::new(&e))?;
+    Ok(TuningComparisons(compare_tunings(freq, &tunings)))
+}
+
+// Every tuning resolve() knows, sorted by name, for string pickers
+pub fn list_tunings() -> Vec<String> {
+    let mut names: Vec<String> = TUNINGS.read().unwrap().keys().cloned().collect();
+    names.sort();
+    names
+}
+
+// Strings of a tuning in table order
+pub fn tuning_notes(name: &str) -> Result<Vec<StringInfo>, String> {
+    TuningHandle::resolve(name).map(|t| t.strings().to_vec())
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = list_tunings)]
+pub fn list_tunings_js() -> TuningNames {
+    TuningNames(list_tunings())
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = tuning_notes)]
+pub fn tuning_notes_js(name: &str) -> Result<GuitarStrings, JsError> {
+    tuning_notes(name).map(GuitarStrings).map_err(|e| JsError::new(&e))
+}
+
+// All tunings with their strings as one JSON object, keyed by name
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn tunings_json() -> Result<String, JsError> {
+    let names = list_tunings();
+    let names: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+    Tunings::to_json(&names).map_err(|e| JsError::new(&e))
+}
+
+// Guitar strings in standard tuning ordered from lowest to highest
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn guitar_strings() -> GuitarStrings {
+    GuitarStrings(TuningHandle::standard().strings().to_vec())
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn find_string_and_distance_js(freq: f64) -> StringMatch {
+    let (frequency, distance, name) = find_string_and_distance(freq);
+    StringMatch { name, frequency, distance }
+}
+
+pub fn find_string_and_distance(freq: f64) -> (f64, f64, String) {
+    TuningHandle::standard().find_string_and_distance(freq)
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn in_string_range(freq: f64) -> bool {
+    TuningHandle::standard().in_range(freq)
+}
+
+pub fn maybe_find_string_and_distance(freq: f64) -> Option<(f64, f64, String)> {
+    TuningHandle::standard().maybe_find_string_and_distance(freq)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(tuning: &TuningHandle) -> Vec<&str> {
+        tuning.strings().iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn registered_tuning_keeps_caller_order() {
+        register_tuning("test-reentrant", from_notes(&["A4", "E4", "C4", "G4"])).unwrap();
+        let tuning = TuningHandle::resolve("test-reentrant").unwrap();
+        assert_eq!(names(&tuning), ["A4", "E4", "C4", "G4"]);
+        assert_eq!(tuning.find_closest_note(440.0).string_index, 0);
+    }
+
+    #[test]
+    fn ukulele_keeps_physical_string_order() {
+        let tuning = TuningHandle::resolve("ukulele-gcea").unwrap();
+        assert_eq!(names(&tuning), ["G4", "C4", "E4", "A4"]);
+        assert_eq!(tuning.find_closest_note(392.0).string_index, 0);
+        assert_eq!(tuning.find_closest_note(261.63).string_index, 1);
+        let low_g = TuningHandle::resolve("ukulele-low-g").unwrap();
+        assert_eq!(names(&low_g), ["G3", "C4", "E4", "A4"]);
+    }
+}
@@ -0,0 +1,160 @@
+// src/vibrato.rs
+//
+// Vibrato rate and depth from a pitch track, for practising vibrato and
+// bends rather than tuning. The track over a sliding window is taken in
+// cents, a straight line is fitted and removed (a slow bend under the
+// vibrato), and what is left is measured: the rate from the zero crossings,
+// the depth from the RMS as the amplitude of an equivalent sine.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+const DEFAULT_VIBRATO_WINDOW_SECONDS: f64 = 1.0;
+// Vibrato on strings and voice runs at about 4 to 8 Hz
+const VIBRATO_MIN_RATE: f64 = 2.0;
+const VIBRATO_MAX_RATE: f64 = 12.0;
+// Full cycles needed in the window before anything is reported
+const VIBRATO_MIN_CYCLES: f64 = 1.5;
+// Shallower wobble is measurement noise
+const VIBRATO_MIN_DEPTH_CENTS: f64 = 3.0;
+// A reading this far from the window's average starts a new track
+const VIBRATO_RESET_CENTS: f64 = 300.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Vibrato {
+    // Cycles per second
+    pub rate: f64,
+    // Amplitude either side of the centre, so 20 means +-20 cents
+    pub depth_cents: f64,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct VibratoAnalyzer {
+    window_seconds: f64,
+    // (stream time, frequency), oldest first
+    track: VecDeque<(f64, f64)>,
+}
+
+impl VibratoAnalyzer {
+    // Adds a reading at stream time `time`, e.g. PitchResult::raw_freq with
+    // PitchTracker::stream_time. Smoothed frequencies hide fast vibrato.
+    pub fn push(&mut self, time: f64, freq: f64) {
+        if !freq.is_finite() || freq <= 0.0 {
+            return;
+        }
+        let jumped = self.centre().is_some_and(|centre| (1200.0 * (freq / centre).log2()).abs() > VIBRATO_RESET_CENTS);
+        let backwards = self.track.back().is_some_and(|(t, _)| time <= *t);
+        if jumped || backwards {
+            self.track.clear();
+        }
+        self.track.push_back((time, freq));
+        while self.track.front().is_some_and(|(t, _)| time - t > self.window_seconds) {
+            self.track.pop_front();
+        }
+    }
+
+    // Forgets the track, e.g. between notes
+    pub fn reset(&mut self) {
+        self.track.clear();
+    }
+
+    // Vibrato over the window, None when there is none to speak of
+    pub fn measure(&self) -> Option<Vibrato> {
+        let centre = self.centre()?;
+        let points: Vec<(f64, f64)> = self.track.iter().map(|(t, f)| (*t, 1200.0 * (f / centre).log2())).collect();
+        let residual = detrend(&points)?;
+
+        // Zero crossings, placed between frames by linear interpolation
+        let crossings: Vec<f64> = residual
+            .windows(2)
+            .filter(|w| (w[0].1 < 0.0) != (w[1].1 < 0.0))
+            .map(|w| {
+                let ((t0, c0), (t1, c1)) = (w[0], w[1]);
+                t0 + (t1 - t0) * c0 / (c0 - c1)
+            })
+            .collect();
+        if crossings.len() < 2 {
+            return None;
+        }
+        let half_cycles = (crossings.len() - 1) as f64;
+        let span = crossings[crossings.len() - 1] - crossings[0];
+        if half_cycles < 2.0 * VIBRATO_MIN_CYCLES || span <= 0.0 {
+            return None;
+        }
+        let rate = half_cycles / (2.0 * span);
+
+        let rms = (residual.iter().map(|(_, c)| c * c).sum::<f64>() / residual.len() as f64).sqrt();
+        let depth_cents = std::f64::consts::SQRT_2 * rms;
+        if !(VIBRATO_MIN_RATE..=VIBRATO_MAX_RATE).contains(&rate) || depth_cents < VIBRATO_MIN_DEPTH_CENTS {
+            return None;
+        }
+        Some(Vibrato { rate, depth_cents })
+    }
+
+    // Geometric mean frequency of the window
+    fn centre(&self) -> Option<f64> {
+        if self.track.is_empty() {
+            return None;
+        }
+        let mean_log = self.track.iter().map(|(_, f)| f.log2()).sum::<f64>() / self.track.len() as f64;
+        Some(2f64.powf(mean_log))
+    }
+}
+
+// Points less their least-squares line
+fn detrend(points: &[(f64, f64)]) -> Option<Vec<(f64, f64)>> {
+    let n = points.len() as f64;
+    if n < 3.0 {
+        return None;
+    }
+    let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+    let mean_c = points.iter().map(|(_, c)| c).sum::<f64>() / n;
+    let var_t: f64 = points.iter().map(|(t, _)| (t - mean_t) * (t - mean_t)).sum();
+    if var_t <= 0.0 {
+        return None;
+    }
+    let slope = points.iter().map(|(t, c)| (t - mean_t) * (c - mean_c)).sum::<f64>() / var_t;
+    Some(points.iter().map(|(t, c)| (*t, c - mean_c - slope * (t - mean_t))).collect())
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl VibratoAnalyzer {
+    // Window in seconds, null for 1 s; two or three cycles of the slowest
+    // vibrato expected
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(window_seconds: Option<f64>) -> VibratoAnalyzer {
+        let window_seconds = window_seconds.unwrap_or(DEFAULT_VIBRATO_WINDOW_SECONDS).max(0.1);
+        VibratoAnalyzer { window_seconds, track: VecDeque::new() }
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = push)]
+    pub fn push_js(&mut self, time: f64, freq: f64) {
+        self.push(time, freq);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = reset)]
+    pub fn reset_js(&mut self) {
+        self.reset();
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = measure)]
+    pub fn measure_js(&self) -> Option<Vibrato> {
+        self.measure()
+    }
+}
+
+impl Default for VibratoAnalyzer {
+    fn default() -> Self {
+        VibratoAnalyzer::new(None)
+    }
+}
@@ -0,0 +1,92 @@
+// src/warnings.rs
+//
+// Recoverable conditions that make detection unreliable without being
+// errors. Frontends drain them after each frame and show a message instead of
+// the tuner silently not reacting. Each kind is rate limited so a persistent
+// problem doesn't flood the queue.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use tsify::Tsify;
+
+use crate::dsp;
+
+// Samples at or above this magnitude are treated as clipped
+const CLIP_LEVEL: f64 = 0.999;
+// RMS below this (about -60 dBFS) is too quiet to detect reliably
+const LOW_SIGNAL_RMS: f64 = 0.001;
+// Default minimum number of frames between two warnings of the same kind
+pub const DEFAULT_WARNING_INTERVAL_FRAMES: u64 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub enum WarningKind {
+    WrongFrameLength,
+    ClippingDetected,
+    LowSignal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TunerWarning {
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(Tsify), tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TunerWarnings(pub Vec<TunerWarning>);
+
+pub struct WarningChannel {
+    pending: Vec<TunerWarning>,
+    last_emitted: HashMap<WarningKind, u64>,
+    frame: u64,
+    min_interval_frames: u64,
+}
+
+impl WarningChannel {
+    pub fn new(min_interval_frames: u64) -> WarningChannel {
+        WarningChannel { pending: vec![], last_emitted: HashMap::new(), frame: 0, min_interval_frames }
+    }
+
+    // Queues a warning unless one of the same kind went out recently
+    pub fn emit(&mut self, kind: WarningKind, message: String) {
+        if let Some(last) = self.last_emitted.get(&kind) {
+            if self.frame - last < self.min_interval_frames {
+                return;
+            }
+        }
+        self.last_emitted.insert(kind, self.frame);
+        self.pending.push(TunerWarning { kind, message });
+    }
+
+    // Inspects one frame and queues warnings for anything that looks wrong.
+    // `accepted` is the detector's verdict on the frame length.
+    pub fn check_frame(&mut self, data: &[f64], accepted: bool, preferred_len: usize) {
+        self.frame += 1;
+        if !accepted {
+            self.emit(
+                WarningKind::WrongFrameLength,
+                format!("got {} samples per frame, detector prefers {}", data.len(), preferred_len),
+            );
+        }
+        if data.iter().any(|x| x.abs() >= CLIP_LEVEL) {
+            self.emit(WarningKind::ClippingDetected, "input is clipping, lower the input gain".to_string());
+        }
+        if !data.is_empty() && dsp::rms(data) < LOW_SIGNAL_RMS {
+            self.emit(WarningKind::LowSignal, "input level is very low".to_string());
+        }
+    }
+
+    pub fn drain(&mut self) -> Vec<TunerWarning> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+impl Default for WarningChannel {
+    fn default() -> Self {
+        WarningChannel::new(DEFAULT_WARNING_INTERVAL_FRAMES)
+    }
+}
@@ -0,0 +1,119 @@
+// src/wav.rs
+//
+// Minimal 16-bit mono PCM WAV output, used to dump what the detectors
+// actually analysed so filter problems can be diagnosed by ear. Reading
+// handles 16-bit PCM with any channel count, mixed down to mono.
+
+const HEADER_LEN: usize = 44;
+
+fn header(sample_rate: u32, data_len: u32) -> [u8; HEADER_LEN] {
+    let mut h = [0u8; HEADER_LEN];
+    h[0..4].copy_from_slice(b"RIFF");
+    h[4..8].copy_from_slice(&(36 + data_len).to_le_bytes());
+    h[8..12].copy_from_slice(b"WAVE");
+    h[12..16].copy_from_slice(b"fmt ");
+    h[16..20].copy_from_slice(&16u32.to_le_bytes());
+    h[20..22].copy_from_slice(&1u16.to_le_bytes()); // PCM
+    h[22..24].copy_from_slice(&1u16.to_le_bytes()); // mono
+    h[24..28].copy_from_slice(&sample_rate.to_le_bytes());
+    h[28..32].copy_from_slice(&(sample_rate * 2).to_le_bytes());
+    h[32..34].copy_from_slice(&2u16.to_le_bytes());
+    h[34..36].copy_from_slice(&16u16.to_le_bytes());
+    h[36..40].copy_from_slice(b"data");
+    h[40..44].copy_from_slice(&data_len.to_le_bytes());
+    h
+}
+
+fn pcm16(samples: &[f64]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(samples.len() * 2);
+    for x in samples {
+        let v = (x.clamp(-1.0, 1.0) * i16::MAX as f64).round() as i16;
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+    out
+}
+
+// Complete WAV file in memory
+pub fn encode_wav(samples: &[f64], sample_rate: u32) -> Vec<u8> {
+    let data = pcm16(samples);
+    let mut out = header(sample_rate, data.len() as u32).to_vec();
+    out.extend_from_slice(&data);
+    out
+}
+
+fn read_u16(b: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([b[at], b[at + 1]])
+}
+
+fn read_u32(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+// Samples (mono, -1..1) and sample rate of a 16-bit PCM WAV file
+pub fn decode_wav(bytes: &[u8]) -> Result<(Vec<f64>, u32), String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a RIFF/WAVE file".to_string());
+    }
+    let mut format: Option<(u16, u16, u32, u16)> = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let len = read_u32(bytes, pos + 4) as usize;
+        let body = pos + 8;
+        let end = (body + len).min(bytes.len());
+        if id == b"fmt " && len >= 16 && end >= body + 16 {
+            format = Some((
+                read_u16(bytes, body),
+                read_u16(bytes, body + 2),
+                read_u32(bytes, body + 4),
+                read_u16(bytes, body + 14),
+            ));
+        } else if id == b"data" {
+            let (tag, channels, sample_rate, bits) = format.ok_or("data chunk before fmt chunk")?;
+            if tag != 1 || bits != 16 || channels == 0 {
+                return Err(format!("only 16-bit PCM is supported (format {}, {} bits)", tag, bits));
+            }
+            let samples = bytes[body..end]
+                .chunks_exact(2 * channels as usize)
+                .map(|frame| {
+                    let sum: f64 = frame.chunks_exact(2).map(|s| i16::from_le_bytes([s[0], s[1]]) as f64).sum();
+                    sum / channels as f64 / i16::MAX as f64
+                })
+                .collect();
+            return Ok((samples, sample_rate));
+        }
+        // Chunks are padded to an even length
+        pos = body + len + (len & 1);
+    }
+    Err("no data chunk".to_string())
+}
+
+// Appends to a WAV file on disk and keeps the header sizes current after
+// every write, so the file stays playable even if the process is killed.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct WavFileWriter {
+    file: std::fs::File,
+    sample_rate: u32,
+    data_len: u32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl WavFileWriter {
+    pub fn create(path: &str, sample_rate: u32) -> std::io::Result<WavFileWriter> {
+        use std::io::Write;
+        let mut file = std::fs::File::create(path)?;
+        file.write_all(&header(sample_rate, 0))?;
+        Ok(WavFileWriter { file, sample_rate, data_len: 0 })
+    }
+
+    pub fn write_samples(&mut self, samples: &[f64]) -> std::io::Result<()> {
+        use std::io::{Seek, SeekFrom, Write};
+        let data = pcm16(samples);
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&data)?;
+        self.data_len += data.len() as u32;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header(self.sample_rate, self.data_len))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+// src/zcr.rs
+//
+// Zero-crossing detector: the period is the spacing of upward crossings.
+// Almost free to compute and exact on a clean, nearly sinusoidal note, such
+// as a softly plucked high E, where YIN sometimes finds no dip below its
+// threshold. Partials strong enough to add crossings of their own make the
+// spacing irregular, and the frame is then rejected rather than misread.
+
+use crate::{fit_freq_max, min_frame_len, PitchFindTrait, TuningHandle};
+
+// Crossings count once the signal has swung past this share of its RMS,
+// so noise around zero doesn't add crossings
+const ZC_HYSTERESIS: f64 = 0.3;
+// Largest spread of the crossing intervals, relative to their mean, of a
+// frame taken as periodic
+const ZC_MAX_JITTER: f64 = 0.02;
+// Periods needed in the frame
+const ZC_MIN_PERIODS: usize = 4;
+
+pub struct ZeroCrossingPitchDetector {
+    freq_min: f64,
+    freq_max: f64,
+    sample_rate: usize,
+    regularity: Option<f64>,
+}
+
+impl ZeroCrossingPitchDetector {
+    pub fn new(freq_min: f64, freq_max: f64, sample_rate: usize) -> ZeroCrossingPitchDetector {
+        ZeroCrossingPitchDetector { freq_min, freq_max, sample_rate, regularity: None }
+    }
+}
+
+impl PitchFindTrait for ZeroCrossingPitchDetector {
+    fn maybe_find_pitch(&mut self, data: &[f64]) -> Option<f64> {
+        self.regularity = None;
+        if data.len() < 2 {
+            return None;
+        }
+        let mean = data.iter().sum::<f64>() / data.len() as f64;
+        let rms = (data.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / data.len() as f64).sqrt();
+        if rms <= 0.0 {
+            return None;
+        }
+        let low = -ZC_HYSTERESIS * rms;
+
+        // Upward crossings of zero after the signal was below `low`, placed
+        // between samples by linear interpolation
+        let mut crossings = vec![];
+        let mut armed = false;
+        for (i, pair) in data.windows(2).enumerate() {
+            let (a, b) = (pair[0] - mean, pair[1] - mean);
+            if a < low {
+                armed = true;
+            }
+            if armed && a < 0.0 && b >= 0.0 {
+                crossings.push(i as f64 + a / (a - b));
+                armed = false;
+            }
+        }
+        if crossings.len() < ZC_MIN_PERIODS + 1 {
+            return None;
+        }
+
+        let intervals: Vec<f64> = crossings.windows(2).map(|w| w[1] - w[0]).collect();
+        let period = intervals.iter().sum::<f64>() / intervals.len() as f64;
+        let spread = (intervals.iter().map(|p| (p - period) * (p - period)).sum::<f64>() / intervals.len() as f64).sqrt();
+        let jitter = spread / period;
+        if jitter > ZC_MAX_JITTER {
+            return None;
+        }
+        let freq = self.sample_rate as f64 / period;
+        if freq < self.freq_min || freq > self.freq_max {
+            return None;
+        }
+        self.regularity = Some(1.0 - jitter / ZC_MAX_JITTER);
+        Some(freq)
+    }
+
+    fn preferred_frame_len(&self) -> usize {
+        min_frame_len(self.freq_min, self.sample_rate).next_power_of_two()
+    }
+
+    // Enough for the periods needed of the highest note; lower notes need
+    // longer frames
+    fn accepts_frame_len(&self, len: usize) -> bool {
+        len as f64 >= (ZC_MIN_PERIODS + 1) as f64 * self.sample_rate as f64 / self.freq_max
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: usize) -> Result<(), String> {
+        self.sample_rate = sample_rate;
+        Ok(())
+    }
+
+    fn fit_to_tuning(&mut self, tuning: &TuningHandle) {
+        self.freq_max = fit_freq_max(tuning, self.freq_max, self.sample_rate);
+    }
+
+    fn last_confidence(&self) -> Option<f64> {
+        self.regularity
+    }
+}
@@ -0,0 +1,74 @@
+// src/jack_midi.rs
+//
+// Guitar-to-MIDI over JACK: a "midi_out" port that plays the detected note
+// and follows the cents deviation with pitch bend (+-2 semitone range). The
+// analysis thread queues raw messages into a JACK ringbuffer which the
+// realtime process callback drains, so nothing blocks in the JACK thread.
+
+use jack::{AsyncClient, Client, ClientOptions, ClosureProcessHandler, Control, MidiOut, ProcessScope, RawMidi, RingBuffer, RingBufferWriter};
+
+const CHANNEL: u8 = 0;
+const VELOCITY: u8 = 100;
+const BEND_RANGE_CENTS: f64 = 200.0;
+const QUEUE_BYTES: usize = 4096;
+
+type ProcessFn = Box<dyn FnMut(&Client, &ProcessScope) -> Control + Send>;
+type Handler = ClosureProcessHandler<ProcessFn>;
+
+pub struct JackMidiOut {
+    _client: AsyncClient<(), Handler>,
+    queue: RingBufferWriter,
+    note: Option<u8>,
+}
+
+impl JackMidiOut {
+    pub fn open(name: &str) -> Result<JackMidiOut, jack::Error> {
+        let (client, _status) = Client::new(name, ClientOptions::NO_START_SERVER)?;
+        let mut port = client.register_port("midi_out", MidiOut)?;
+        let (mut reader, queue) = RingBuffer::new(QUEUE_BYTES)?.into_reader_writer();
+
+        let process: ProcessFn = Box::new(move |_, ps| {
+            let mut writer = port.writer(ps);
+            let mut msg = [0u8; 3];
+            while reader.space() >= 3 {
+                reader.read_buffer(&mut msg);
+                let _ = writer.write(&RawMidi { time: 0, bytes: &msg });
+            }
+            Control::Continue
+        });
+        let client = client.activate_async((), ClosureProcessHandler::new(process))?;
+        Ok(JackMidiOut { _client: client, queue, note: None })
+    }
+
+    // Sounds the note nearest to `target_freq`, bent by `cents`
+    pub fn send(&mut self, target_freq: f64, cents: f64) {
+        let note = (69.0 + 12.0 * (target_freq / 440.0).log2()).round().clamp(0.0, 127.0) as u8;
+        if self.note != Some(note) {
+            self.release();
+            self.push([0x90 | CHANNEL, note, VELOCITY]);
+            self.note = Some(note);
+        }
+        let bend = (8192.0 + 8192.0 * cents / BEND_RANGE_CENTS).round().clamp(0.0, 16383.0) as u16;
+        self.push([0xE0 | CHANNEL, (bend & 0x7f) as u8, (bend >> 7) as u8]);
+    }
+
+    pub fn release(&mut self) {
+        if let Some(note) = self.note.take() {
+            self.push([0x80 | CHANNEL, note, 0]);
+        }
+    }
+
+    // Messages are dropped when the queue is full rather than waiting on
+    // the JACK thread
+    fn push(&mut self, msg: [u8; 3]) {
+        if self.queue.space() >= msg.len() {
+            self.queue.write_buffer(&msg);
+        }
+    }
+}
+
+impl Drop for JackMidiOut {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
@@ -2,20 +2,82 @@ use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::*;
 use crossterm::{QueueableCommand, cursor, terminal, ExecutableCommand};
 use std::io::{Write, stdout};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
 use serde_yaml;
 
+use nofuzz_tuner_lib::AbTest;
+use nofuzz_tuner_lib::AbTestConfig;
+use nofuzz_tuner_lib::AcfPitchDetector;
 use nofuzz_tuner_lib::Config;
 use nofuzz_tuner_lib::PitchFindTrait;
 use nofuzz_tuner_lib::YinPitchDetector;
 use nofuzz_tuner_lib::McleodPitchDetector;
+#[cfg(feature = "neural")]
+use nofuzz_tuner_lib::NeuralPitchDetector;
+use nofuzz_tuner_lib::NoteNotation;
+use nofuzz_tuner_lib::NoteSpelling;
 use nofuzz_tuner_lib::FftPitchDetector;
-use nofuzz_tuner_lib::find_string_and_distance;
+use nofuzz_tuner_lib::Temperament;
+use nofuzz_tuner_lib::TuneDirection;
+use nofuzz_tuner_lib::TuningHandle;
+use nofuzz_tuner_lib::Tunings;
+use nofuzz_tuner_lib::PitchTracker;
+use nofuzz_tuner_lib::TrackerEvent;
+use nofuzz_tuner_lib::monitor_strings;
+use nofuzz_tuner_lib::monitor_score;
+use nofuzz_tuner_lib::StringStatus;
+use nofuzz_tuner_lib::RangePreset;
+use nofuzz_tuner_lib::InTuneSettings;
+use nofuzz_tuner_lib::InputKind;
+use nofuzz_tuner_lib::InstrumentProfile;
+use nofuzz_tuner_lib::DEFAULT_TUNING;
+use nofuzz_tuner_lib::OctavePolicy;
+use nofuzz_tuner_lib::validate_range;
+use nofuzz_tuner_lib::DEFAULT_AB_GAP_SECONDS;
+use nofuzz_tuner_lib::wav::{decode_wav, WavFileWriter};
+
+mod session;
+use session::Session;
+
+#[cfg(all(feature = "jack-midi", target_os = "linux"))]
+mod jack_midi;
+#[cfg(all(feature = "jack-midi", target_os = "linux"))]
+use jack_midi::JackMidiOut;
+
+// Stand-in so the analysis loop doesn't need cfgs of its own
+#[cfg(not(all(feature = "jack-midi", target_os = "linux")))]
+struct JackMidiOut;
+
+#[cfg(not(all(feature = "jack-midi", target_os = "linux")))]
+impl JackMidiOut {
+    fn open(_name: &str) -> Result<JackMidiOut, String> {
+        Err("JACK MIDI output needs the jack-midi feature on Linux".to_string())
+    }
+
+    fn send(&mut self, _target_freq: f64, _cents: f64) {}
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // read config.cfg
     let f = std::fs::File::open("config.yaml")?;
-    let config: Config = serde_yaml::from_reader(f)?;
+    let mut config: Config = serde_yaml::from_reader(f)?;
     println!("{:?}", config);
+    if let Some(ab_test) = &config.ab_test {
+        return run_ab_test(ab_test);
+    }
+    let loaded = match &config.tunings_file {
+        Some(path) => Tunings::load_from_file(path)?,
+        None => vec![],
+    };
+    let instrument = config.instrument.as_deref().map(InstrumentProfile::from_name).transpose()?;
+    if let Some(instrument) = instrument {
+        if config.tuning == DEFAULT_TUNING {
+            config.tuning = instrument.tuning().to_string();
+        }
+    }
+    let tuning = TuningHandle::resolve(&config.tuning)?;
+    let mut session = Session::new(&tuning);
     
     let host = cpal::default_host();
     let device = host
@@ -23,7 +85,35 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .expect("failed to find input device");
     let supported_config = device.default_input_config().unwrap();
 
-    let buffer_size = 1024;
+    let mut buffer_size = 1024;
+    let mut in_tune = InTuneSettings::default();
+    if let Some(instrument) = instrument {
+        in_tune = instrument.in_tune();
+        let settings = instrument.settings(supported_config.sample_rate().0 as usize);
+        config.freq_min = settings.freq_min;
+        config.freq_max = settings.freq_max;
+        buffer_size = settings.frame_len as u32;
+    }
+    if config.preset.is_none() && tuning.lowest() < config.freq_min {
+        session.warn(format!(
+            "freq_min {} Hz is above the lowest string of '{}' ({} Hz), try preset: {}",
+            config.freq_min, tuning.name(), tuning.lowest(), RangePreset::for_tuning(&tuning).name()));
+    }
+    if let Some(name) = &config.preset {
+        let preset = RangePreset::from_name(name)?;
+        in_tune = preset.in_tune();
+        let settings = preset.settings(supported_config.sample_rate().0 as usize);
+        config.freq_min = settings.freq_min;
+        config.freq_max = settings.freq_max;
+        buffer_size = settings.frame_len as u32;
+    }
+    // e.g. mandolin E5 above the guitar range, unless a preset says otherwise
+    config.freq_max = config.fitted_freq_max(config.analysis_rate.unwrap_or(supported_config.sample_rate().0 as usize));
+    // Custom tunings have no preset to suggest, a string out of range is an
+    // error in the file
+    if loaded.contains(&config.tuning) {
+        tuning.check_range(config.freq_min, config.freq_max)?;
+    }
     let stream_config: StreamConfig = 
         StreamConfig {
             channels: 1,
@@ -31,56 +121,267 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             buffer_size: cpal::BufferSize::Fixed(buffer_size),
         };
     
-    let sample_rate = stream_config.sample_rate.0 as usize;
+    let device_rate = stream_config.sample_rate.0 as usize;
+    // Detection runs at analysis_rate when set, the input is resampled to it
+    let sample_rate = config.analysis_rate.unwrap_or(device_rate);
+    let frame_len = buffer_size as usize * sample_rate / device_rate;
     let detector: Box<dyn PitchFindTrait>;
 
     match config.pitch_detection.as_str() {
         "yin" => {
-            let yin = YinPitchDetector::new(
-                config.threshold, 
-                config.freq_min, 
-                config.freq_max, 
-                sample_rate);
-            detector = Box::new(yin);
+            detector = Box::new(YinPitchDetector::from_config(&config, sample_rate));
         } 
         "mcleod" => {
             let mcleod = McleodPitchDetector::new(
-                buffer_size as usize, 
-                (buffer_size / 2) as usize, 
+                frame_len, 
+                frame_len / 2, 
                 sample_rate, 
                 config.power_threshold, 
                 config.clarity_threshold);
             detector = Box::new(mcleod);
         }
         "fft" => {
-            let fft = FftPitchDetector::new();
+            let mut fft = FftPitchDetector::new(sample_rate, config.freq_min, config.freq_max);
+            fft.set_fft_len(config.fft_len, config.fft_accumulate);
             detector = Box::new(fft);
         }
+        "acf" => {
+            let acf = AcfPitchDetector::new(config.freq_min, config.freq_max, sample_rate);
+            detector = Box::new(acf);
+        }
+        #[cfg(feature = "neural")]
+        "neural" => {
+            let path = config.neural_model.as_deref().ok_or("pitch_detection: neural needs neural_model")?;
+            let neural = NeuralPitchDetector::from_onnx_file(path, config.freq_min, config.freq_max, sample_rate)?;
+            detector = Box::new(neural);
+        }
         _ => panic!("Invalid pitch detection method"),
     };
+
+    if let Err(e) = validate_range(config.freq_min, config.freq_max, frame_len, sample_rate) {
+        session.warn(e);
+    } else if !detector.accepts_frame_len(frame_len) {
+        session.warn(format!(
+            "{} detector prefers frames of {} samples, input buffer is {}",
+            config.pitch_detection, detector.preferred_frame_len(), frame_len));
+    }
     
     
+    let mut tracker = PitchTracker::with_detector(detector, tuning.clone(), sample_rate);
+    tracker.set_input_rate(device_rate)?;
+    tracker.set_live_input(true);
+    tracker.set_gate(config.gate_lufs);
+    if let Some(name) = &config.input_kind {
+        tracker.set_input_kind(Some(InputKind::from_name(name)?));
+    } else if let Some(instrument) = instrument {
+        tracker.set_input_kind(instrument.input_kind());
+    }
+    if let Some(specs) = &config.filters {
+        tracker.set_filters(specs)?;
+    }
+    tracker.set_dc_blocker(config.dc_blocker);
+    tracker.set_auto_hum_notch(config.auto_hum_notch);
+    tracker.set_agc(config.agc_target_rms);
+    if config.auto_gate {
+        tracker.set_auto_gate(true);
+    }
+    tracker.set_decay_gate(config.decay_gate_db);
+    tracker.set_settle_time(config.settle_ms);
+    tracker.set_onset_hold(config.onset_hold_ms);
+    tracker.set_kalman(config.kalman, config.kalman_process_noise, config.kalman_measurement_noise);
+    tracker.set_median_smoothing(config.median_frames);
+    tracker.set_narrowband(config.narrowband);
+    tracker.set_dynamic_block_size(config.dynamic_block_size);
+    tracker.set_multi_resolution(config.multi_resolution);
+    tracker.set_lag_refinement(config.refine_lag);
+    tracker.set_octave_correction(config.octave_correction);
+    tracker.set_dominant_pitch_gate(config.dominant_pitch_gate);
+    in_tune.tolerance_cents = config.in_tune_cents.unwrap_or(in_tune.tolerance_cents);
+    in_tune.hysteresis_cents = config.in_tune_hysteresis_cents.unwrap_or(in_tune.hysteresis_cents);
+    tracker.set_in_tune(in_tune);
+    tracker.set_calibration_ppm(config.calibration_ppm.unwrap_or(0.0));
+    if let (Some(cents), Some(frames)) = (config.note_hysteresis_cents, config.note_hysteresis_frames) {
+        tracker.set_note_hysteresis(cents, frames);
+    }
+    if let Some(name) = &config.temperament {
+        let tonic = config.temperament_tonic.as_deref().unwrap_or("C");
+        tracker.set_temperament(Temperament::from_name(name, tonic)?);
+    }
+    if let Some(name) = &config.note_spelling {
+        tracker.set_note_spelling(Some(NoteSpelling::from_name(name)?));
+    }
+    if let Some(name) = &config.note_notation {
+        tracker.set_note_notation(NoteNotation::from_name(name)?);
+    }
+    for (string, cents) in config.string_offsets.iter().flatten() {
+        tracker.set_string_offset(string, *cents);
+    }
+    if let Some(name) = &config.octave_policy {
+        tracker.set_octave_policy(OctavePolicy::from_name(name)?);
+    }
+    if let Some(freq) = config.inject_tone {
+        tracker.inject_tone(freq, 0.5);
+    }
+    let mut midi_out = None;
+    if config.jack_midi {
+        midi_out = Some(JackMidiOut::open("nofuzz_tuner")?);
+    }
+    let mut wav_writer = None;
+    if let Some(path) = &config.record_wav {
+        wav_writer = Some(WavFileWriter::create(path, sample_rate as u32)?);
+        tracker.start_capture();
+    }
+
+    if config.monitor {
+        match supported_config.sample_format() {
+            cpal::SampleFormat::F32 => monitor_from_input_stream::<f32>(&device, &stream_config, tuning, session),
+            cpal::SampleFormat::I16 => monitor_from_input_stream::<i16>(&device, &stream_config, tuning, session),
+            cpal::SampleFormat::U16 => monitor_from_input_stream::<u16>(&device, &stream_config, tuning, session),
+        }
+        return Ok(());
+    }
+
     match supported_config.sample_format() {
-        cpal::SampleFormat::F32 => detect_from_input_stream::<f32>(&device, &stream_config.into(), detector),
-        cpal::SampleFormat::I16 => detect_from_input_stream::<i16>(&device, &stream_config.into(), detector),
-        cpal::SampleFormat::U16 => detect_from_input_stream::<u16>(&device, &stream_config.into(), detector),
+        cpal::SampleFormat::F32 => detect_from_input_stream::<f32>(&device, &stream_config.into(), tracker, wav_writer, midi_out, config.realtime, session),
+        cpal::SampleFormat::I16 => detect_from_input_stream::<i16>(&device, &stream_config.into(), tracker, wav_writer, midi_out, config.realtime, session),
+        cpal::SampleFormat::U16 => detect_from_input_stream::<u16>(&device, &stream_config.into(), tracker, wav_writer, midi_out, config.realtime, session),
     }
 
     Ok(())
 }
 
-fn detect_from_input_stream<T: Sample>(device: &Device, config: &StreamConfig, mut detector: Box<dyn PitchFindTrait>) {
+fn detect_from_input_stream<T: Sample>(device: &Device, config: &StreamConfig, mut tracker: PitchTracker, mut wav_writer: Option<WavFileWriter>, mut midi_out: Option<JackMidiOut>, realtime: bool, session: Session) {
     let err_fn = |err| println!("{}", err);
-    
+    let sample_rate = config.sample_rate.0;
+    let (tx, rx) = mpsc::channel::<Vec<f64>>();
+    let session = Arc::new(Mutex::new(session));
+    let analysis_session = session.clone();
+
+    // Analysis runs on its own thread so the audio callback only copies
+    // samples and heavy detection can't make the input stream glitch.
+    thread::spawn(move || {
+        let mut priority = None;
+        let mut rate_warned = false;
+        for f64_vals in rx {
+            if realtime && priority.is_none() {
+                priority = Some(promote_to_realtime(f64_vals.len() as u32, sample_rate));
+            }
+            let result = tracker.process(&f64_vals);
+            for event in tracker.drain_events() {
+                match event {
+                    TrackerEvent::SampleRateMismatch { expected, observed } => {
+                        if rate_warned {
+                            continue;
+                        }
+                        rate_warned = true;
+                        analysis_session.lock().unwrap().warn(format!(
+                            "device delivers about {:.0} Hz but the stream reports {} Hz, readings will be off",
+                            observed, expected
+                        ));
+                    }
+                    TrackerEvent::HumNotched { mains_hz, notches } => {
+                        let notches: Vec<String> = notches.iter().map(|f| format!("{:.0}", f)).collect();
+                        analysis_session.lock().unwrap().warn(format!(
+                            "{:.0} Hz mains hum in the input, notching {} Hz",
+                            mains_hz,
+                            notches.join("/")
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+            let frame_seconds = f64_vals.len() as f64 / sample_rate as f64;
+            analysis_session.lock().unwrap().record(frame_seconds, result.as_ref());
+            if let Some(writer) = wav_writer.as_mut() {
+                writer.write_samples(&tracker.take_captured()).unwrap();
+            }
+            if let Some(result) = result {
+                let t = result.tuning_to;
+                if let Some(midi) = midi_out.as_mut() {
+                    midi.send(t.freq, t.cents);
+                }
+                output(result.freq, t.freq, t.distance, t.direction, t.label, tracker.is_in_tune());
+            }
+        }
+    });
+
     let stream = device
         .build_input_stream(
             &config,
             move |data: &[T], _| {
                 let f64_vals: Vec<f64> = data.iter().map(|x| x.to_f32() as f64).collect();
-                let freq = (*detector).maybe_find_pitch(&f64_vals);
-                if freq != None {
-                    let s_and_f = find_string_and_distance(freq.unwrap());
-                    output(freq.unwrap(), s_and_f.0, s_and_f.1, s_and_f.2);
+                let _ = tx.send(f64_vals);
+            },
+            err_fn,
+        )
+        .unwrap();
+
+    stream.play().unwrap();
+    wait_for_ctrl_c(&session);
+}
+
+// Runs until Ctrl-C, then summarises the session
+// Writes the A/B renders next to the phrase, e.g. phrase_a.wav
+fn run_ab_test(ab_test: &AbTestConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(&ab_test.phrase)?;
+    let (phrase, sample_rate) = decode_wav(&bytes)?;
+    let test = AbTest::render(&phrase, sample_rate as usize, &ab_test.a, &ab_test.b)?;
+    let stem = ab_test.phrase.strip_suffix(".wav").unwrap_or(&ab_test.phrase);
+    for path in test.save(stem, ab_test.gap_seconds.unwrap_or(DEFAULT_AB_GAP_SECONDS))? {
+        println!("wrote {}", path);
+    }
+    Ok(())
+}
+
+fn wait_for_ctrl_c(session: &Mutex<Session>) {
+    let (stop_tx, stop_rx) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = stop_tx.send(());
+    })
+    .expect("failed to set Ctrl-C handler");
+    let _ = stop_rx.recv();
+    stdout().execute(cursor::Show).unwrap();
+    session.lock().unwrap().print_summary();
+}
+
+// Raises the calling thread to realtime priority. The returned handle keeps
+// the promotion for as long as it is held.
+#[cfg(feature = "realtime")]
+fn promote_to_realtime(buffer_frames: u32, sample_rate: u32) -> Option<audio_thread_priority::RtPriorityHandle> {
+    match audio_thread_priority::promote_current_thread_to_real_time(buffer_frames, sample_rate) {
+        Ok(handle) => Some(handle),
+        Err(e) => {
+            println!("Warning: could not raise analysis thread priority: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "realtime"))]
+fn promote_to_realtime(_buffer_frames: u32, _sample_rate: u32) -> Option<()> {
+    println!("Warning: realtime priority requested but built without the realtime feature");
+    None
+}
+
+fn monitor_from_input_stream<T: Sample>(device: &Device, config: &StreamConfig, tuning: TuningHandle, session: Session) {
+    let err_fn = |err| println!("{}", err);
+    let sample_rate = config.sample_rate.0 as usize;
+    // half a second per analysis so the narrow probes can resolve cents
+    let window = sample_rate / 2;
+    let mut buffer: Vec<f64> = Vec::with_capacity(window);
+    let session = Arc::new(Mutex::new(session));
+    let stream_session = session.clone();
+
+    let stream = device
+        .build_input_stream(
+            config,
+            move |data: &[T], _| {
+                buffer.extend(data.iter().map(|x| x.to_f32() as f64));
+                if buffer.len() >= window {
+                    let statuses = monitor_strings(&tuning, &buffer, sample_rate);
+                    stream_session.lock().unwrap().record_monitor(buffer.len() as f64 / sample_rate as f64, &statuses);
+                    output_monitor(&statuses);
+                    buffer.clear();
                 }
             },
             err_fn,
@@ -88,13 +389,42 @@ fn detect_from_input_stream<T: Sample>(device: &Device, config: &StreamConfig, m
         .unwrap();
 
     stream.play().unwrap();
-    loop {}
+    wait_for_ctrl_c(&session);
 }
 
-fn output(freq:f64, string_freq:f64, distance:f64, string_key:String) {
-    let mut corr = "".to_string();
-    if distance.abs() > 0.9 {
-        let dir = if distance < 0.0 {">"} else {"<"};
+fn output_monitor(statuses: &[StringStatus]) {
+    let mut line: Vec<String> = statuses
+        .iter()
+        .map(|s| {
+            if s.ringing {
+                format!("{}: {:+.1}", s.name, s.cents)
+            } else {
+                format!("{}: --", s.name)
+            }
+        })
+        .collect();
+    if let Some(score) = monitor_score(statuses) {
+        line.push(format!("| {:.1} cents RMS", score.rms_cents));
+    }
+
+    let mut stdout = stdout();
+    stdout.execute(cursor::Hide).unwrap();
+    stdout.queue(cursor::SavePosition).unwrap();
+    stdout.write_all(line.join("  ").as_bytes()).unwrap();
+    stdout.queue(cursor::RestorePosition).unwrap();
+    stdout.flush().unwrap();
+    stdout.queue(cursor::RestorePosition).unwrap();
+    stdout.queue(terminal::Clear(terminal::ClearType::FromCursorDown)).unwrap();
+}
+
+fn output(freq:f64, string_freq:f64, distance:f64, direction: TuneDirection, string_key:String, in_tune: bool) {
+    let mut corr = " --- In tune".to_string();
+    if !in_tune {
+        let dir = match direction {
+            TuneDirection::Up => ">",
+            TuneDirection::Down => "<",
+            TuneDirection::OnPitch => "=",
+        };
         corr = format!(" --- Correction: {} {:.1}", dir, distance);
     }
 
@@ -0,0 +1,105 @@
+// src/session.rs
+//
+// What happened during a CLI run, printed when the user quits with Ctrl-C:
+// final cents and time spent per string, how many frames gave a reading and
+// the warnings seen along the way.
+
+use nofuzz_tuner_lib::{aggregate_score, PitchResult, StringStatus, TuningHandle};
+
+#[derive(Debug, Clone, Default)]
+struct StringStats {
+    seconds: f64,
+    last_cents: Option<f64>,
+}
+
+pub struct Session {
+    // In tuning order, notes outside the tuning are appended as they appear
+    strings: Vec<(String, StringStats)>,
+    frames: u64,
+    detected: u64,
+    seconds: f64,
+    warnings: Vec<String>,
+}
+
+impl Session {
+    pub fn new(tuning: &TuningHandle) -> Session {
+        Session {
+            strings: tuning.strings().iter().map(|s| (s.name.clone(), StringStats::default())).collect(),
+            frames: 0,
+            detected: 0,
+            seconds: 0.0,
+            warnings: vec![],
+        }
+    }
+
+    pub fn record(&mut self, frame_seconds: f64, result: Option<&PitchResult>) {
+        self.frames += 1;
+        self.seconds += frame_seconds;
+        let Some(result) = result else { return };
+        self.detected += 1;
+        self.reading(&result.tuning_to.note, result.tuning_to.cents, frame_seconds);
+    }
+
+    // Monitor mode: every string ringing in the window counts as a reading
+    pub fn record_monitor(&mut self, window_seconds: f64, statuses: &[StringStatus]) {
+        self.frames += 1;
+        self.seconds += window_seconds;
+        let mut ringing = statuses.iter().filter(|s| s.ringing).peekable();
+        if ringing.peek().is_some() {
+            self.detected += 1;
+        }
+        for status in ringing {
+            self.reading(&status.name, status.cents, window_seconds);
+        }
+    }
+
+    fn reading(&mut self, note: &str, cents: f64, seconds: f64) {
+        let pos = match self.strings.iter().position(|(name, _)| name == note) {
+            Some(pos) => pos,
+            None => {
+                self.strings.push((note.to_string(), StringStats::default()));
+                self.strings.len() - 1
+            }
+        };
+        let stats = &mut self.strings[pos].1;
+        stats.seconds += seconds;
+        stats.last_cents = Some(cents);
+    }
+
+    // Prints the warning and keeps it for the summary
+    pub fn warn(&mut self, message: String) {
+        println!("Warning: {}", message);
+        self.warnings.push(message);
+    }
+
+    pub fn print_summary(&self) {
+        println!();
+        println!("Session: {:.1} s, readings in {} of {} frames ({:.0}%)",
+            self.seconds, self.detected, self.frames, self.success_rate() * 100.0);
+        for (name, stats) in self.strings.iter() {
+            match stats.last_cents {
+                Some(cents) => println!("  {:<4} final {:+6.1} cents, {:5.1} s", name, cents, stats.seconds),
+                None => println!("  {:<4} not played", name),
+            }
+        }
+        let residuals: Vec<(f64, f64)> = self
+            .strings
+            .iter()
+            .filter_map(|(_, s)| s.last_cents.map(|c| (c, s.seconds)))
+            .collect();
+        if let Some(score) = aggregate_score(&residuals) {
+            println!("Overall: {:.1} cents RMS over {} strings{}",
+                score.rms_cents, score.strings, if score.practically_in_tune { ", practically in tune" } else { "" });
+        }
+        if !self.warnings.is_empty() {
+            println!("Warnings:");
+            for w in self.warnings.iter() {
+                println!("  {}", w);
+            }
+        }
+    }
+
+    fn success_rate(&self) -> f64 {
+        if self.frames == 0 { 0.0 } else { self.detected as f64 / self.frames as f64 }
+    }
+}